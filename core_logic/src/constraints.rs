@@ -0,0 +1,489 @@
+//! The scheduling constraints of a todo, read from `wire::state::Scheduling` and checked, so that
+//! a scheduler doesn't have to deal with values that make no sense.
+//!
+//! The rules are:
+//! - the chunk size, if any, is positive, and its minimum isn't larger than its maximum;
+//! - every window ends after it starts, and fractional windows lie within their period;
+//! - recurring todos only have fractional windows, and other todos only absolute ones;
+//! - every required window lies within the ideal windows, unless there are none.
+
+use chrono::{Duration, NaiveDateTime};
+use wire::state::{ActivityKindVariant, Scheduling, TimeWindow, TimeWindowVariant, Todo};
+use yrs_wrappers::{
+    yrs_vec::YrsVec,
+    yrs_wrapper_error::{YrsResult, YrsWrapperError},
+};
+
+use crate::Fraction;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Activity {
+    TimeBased,
+    GoalBased,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkSize {
+    pub min: Duration,
+    pub max: Duration,
+}
+
+/// A window within the day or week of a recurring todo. See `wire::state::TimeWindowPrelim`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PeriodForRecurring {
+    pub start: Fraction,
+
+    /// The duration after the start of the period.
+    pub duration: Fraction,
+}
+
+impl PeriodForRecurring {
+    pub fn end(&self) -> f64 {
+        self.start.into_inner() + self.duration.into_inner()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeriodForNonRecurring {
+    /// In UTC.
+    pub start: NaiveDateTime,
+    pub duration: Duration,
+}
+
+impl PeriodForNonRecurring {
+    pub fn end(&self) -> NaiveDateTime {
+        self.start + self.duration
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Windows<P> {
+    /// Empty means any time.
+    pub ideal: Vec<P>,
+    /// Empty means any time.
+    pub required: Vec<P>,
+}
+
+impl<P> Default for Windows<P> {
+    fn default() -> Self {
+        Windows {
+            ideal: Vec::new(),
+            required: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TodoWindows {
+    Recurring(Windows<PeriodForRecurring>),
+    NonRecurring(Windows<PeriodForNonRecurring>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Constraints {
+    pub activity: Activity,
+    /// The total time the todo takes.
+    pub estimated_duration: Duration,
+    /// `None` means the todo can be split up any way.
+    pub chunk_size: Option<ChunkSize>,
+    pub windows: TodoWindows,
+}
+
+#[derive(Debug, thiserror::Error, Clone, PartialEq)]
+pub enum ConstraintError {
+    #[error("YrsWrapperError: {0}")]
+    Yrs(#[from] YrsWrapperError),
+
+    #[error("The chunk size has to be positive, with a minimum of at most its maximum")]
+    InvalidChunkSize,
+
+    #[error("The {kind} window {index} doesn't end after it starts, or is outside of its period")]
+    InvalidWindow { kind: WindowKind, index: usize },
+
+    #[error("Recurring todos need fractional windows, and others absolute ones, but the {kind} window {index} isn't {expected}")]
+    WrongWindowType {
+        kind: WindowKind,
+        index: usize,
+        expected: &'static str,
+    },
+
+    #[error("The required window {index} isn't within the ideal windows")]
+    RequiredOutsideIdeal { index: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowKind {
+    Ideal,
+    Required,
+}
+
+impl std::fmt::Display for WindowKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WindowKind::Ideal => write!(f, "ideal"),
+            WindowKind::Required => write!(f, "required"),
+        }
+    }
+}
+
+impl Constraints {
+    /// Todos without scheduling constraints are goal based, and can be scheduled any time, in
+    /// chunks of any size.
+    pub fn from_todo(todo: &Todo, txn: &impl yrs::ReadTxn) -> Result<Self, ConstraintError> {
+        let estimated_duration = *todo.estimated_duration(txn)?;
+        let recurs = todo.recurrence(txn).transpose()?.is_some();
+        let scheduling = match todo.scheduling(txn).transpose()? {
+            Some(scheduling) => scheduling,
+            None => {
+                return Ok(Constraints {
+                    activity: Activity::GoalBased,
+                    estimated_duration,
+                    chunk_size: None,
+                    windows: if recurs {
+                        TodoWindows::Recurring(Windows::default())
+                    } else {
+                        TodoWindows::NonRecurring(Windows::default())
+                    },
+                })
+            }
+        };
+
+        let activity = match scheduling.activity(txn)?.variant(txn)? {
+            ActivityKindVariant::TimeBased => Activity::TimeBased,
+            ActivityKindVariant::GoalBased => Activity::GoalBased,
+        };
+
+        let chunk_size = match scheduling.chunk_size(txn).transpose()? {
+            Some(chunk_size) => {
+                let min = *chunk_size.min(txn)?;
+                let max = *chunk_size.max(txn)?;
+                if min <= Duration::zero() || min > max {
+                    return Err(ConstraintError::InvalidChunkSize);
+                }
+                Some(ChunkSize { min, max })
+            }
+            None => None,
+        };
+
+        let windows = if recurs {
+            let windows = read_windows(&scheduling, txn, recurring_period)?;
+            check_required_within_ideal(&windows, |p| (p.start.into_inner(), p.end()))?;
+            TodoWindows::Recurring(windows)
+        } else {
+            let windows = read_windows(&scheduling, txn, non_recurring_period)?;
+            check_required_within_ideal(&windows, |p| (p.start, p.end()))?;
+            TodoWindows::NonRecurring(windows)
+        };
+
+        Ok(Constraints {
+            activity,
+            estimated_duration,
+            chunk_size,
+            windows,
+        })
+    }
+}
+
+/// Converts a window, returning `None` if it's invalid, and `Err(expected)` if it's of the wrong
+/// type, where `expected` is the right one.
+type ReadPeriod<P> = fn(TimeWindowVariant) -> Result<Option<P>, &'static str>;
+
+fn recurring_period(window: TimeWindowVariant) -> Result<Option<PeriodForRecurring>, &'static str> {
+    match window {
+        TimeWindowVariant::Fractional { start, end } => {
+            let (start, end) = (*start, *end);
+            Ok(Fraction::new(start)
+                .ok()
+                .zip(Fraction::new(end - start).ok())
+                .filter(|_| start < end && end <= 1.0)
+                .map(|(start, duration)| PeriodForRecurring { start, duration }))
+        }
+        TimeWindowVariant::Absolute { .. } => Err("fractional"),
+    }
+}
+
+fn non_recurring_period(
+    window: TimeWindowVariant,
+) -> Result<Option<PeriodForNonRecurring>, &'static str> {
+    match window {
+        TimeWindowVariant::Absolute { start, end } => {
+            let (start, end) = (*start, *end);
+            Ok((start < end).then(|| PeriodForNonRecurring {
+                start,
+                duration: end - start,
+            }))
+        }
+        TimeWindowVariant::Fractional { .. } => Err("absolute"),
+    }
+}
+
+fn read_windows<P>(
+    scheduling: &Scheduling,
+    txn: &impl yrs::ReadTxn,
+    read_period: ReadPeriod<P>,
+) -> Result<Windows<P>, ConstraintError> {
+    let read = |windows: YrsVec<TimeWindow>, kind: WindowKind| {
+        windows
+            .iter(txn)
+            .enumerate()
+            .map(|(index, window)| -> Result<P, ConstraintError> {
+                let window: YrsResult<_> = window.and_then(|window| window.variant(txn));
+                match read_period(window?) {
+                    Ok(Some(period)) => Ok(period),
+                    Ok(None) => Err(ConstraintError::InvalidWindow { kind, index }),
+                    Err(expected) => Err(ConstraintError::WrongWindowType {
+                        kind,
+                        index,
+                        expected,
+                    }),
+                }
+            })
+            .collect::<Result<Vec<P>, ConstraintError>>()
+    };
+
+    Ok(Windows {
+        ideal: read(scheduling.ideal_windows(txn)?, WindowKind::Ideal)?,
+        required: read(scheduling.required_windows(txn)?, WindowKind::Required)?,
+    })
+}
+
+/// Checks that the union of the ideal windows covers every required window. Adjacent ideal
+/// windows count as one.
+fn check_required_within_ideal<P, T: PartialOrd + Copy>(
+    windows: &Windows<P>,
+    bounds: impl Fn(&P) -> (T, T),
+) -> Result<(), ConstraintError> {
+    if windows.ideal.is_empty() {
+        return Ok(());
+    }
+
+    let mut ideal = windows.ideal.iter().map(&bounds).collect::<Vec<_>>();
+    ideal.sort_by(|(a, _), (b, _)| a.partial_cmp(b).expect("Bounds should be comparable"));
+    let mut merged: Vec<(T, T)> = Vec::new();
+    for (start, end) in ideal {
+        match merged.last_mut() {
+            Some((_, last_end)) if start <= *last_end => {
+                if end > *last_end {
+                    *last_end = end;
+                }
+            }
+            _ => merged.push((start, end)),
+        }
+    }
+
+    for (index, required) in windows.required.iter().enumerate() {
+        let (start, end) = bounds(required);
+        if !merged.iter().any(|(s, e)| *s <= start && end <= *e) {
+            return Err(ConstraintError::RequiredOutsideIdeal { index });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveDate, NaiveDateTime, TimeZone};
+    use chrono_tz::Tz;
+    use wire::state::{
+        ActivityKindPrelim, ChunkSizePrelim, FrequencyPrelim, RecurrencePrelim, SchedulingPrelim,
+        TimeWindowPrelim, Todo, TodoPrelim,
+    };
+    use yrs::{Doc, Map, Transact};
+
+    use super::{
+        Activity, ChunkSize, ConstraintError, Constraints, PeriodForNonRecurring, TodoWindows,
+        WindowKind, Windows,
+    };
+
+    fn at(hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2023, 5, 1)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    fn absolute(start: u32, end: u32) -> TimeWindowPrelim {
+        TimeWindowPrelim::Absolute {
+            start: at(start).into(),
+            end: at(end).into(),
+        }
+    }
+
+    fn fractional(start: f64, end: f64) -> TimeWindowPrelim {
+        TimeWindowPrelim::Fractional {
+            start: start.into(),
+            end: end.into(),
+        }
+    }
+
+    fn scheduling(
+        ideal_windows: Vec<TimeWindowPrelim>,
+        required_windows: Vec<TimeWindowPrelim>,
+    ) -> SchedulingPrelim {
+        SchedulingPrelim {
+            activity: ActivityKindPrelim::TimeBased,
+            chunk_size: Some(ChunkSizePrelim {
+                min: Duration::minutes(30).into(),
+                max: Duration::hours(2).into(),
+            }),
+            ideal_windows: ideal_windows.into(),
+            required_windows: required_windows.into(),
+        }
+    }
+
+    fn constraints(
+        recurs: bool,
+        scheduling: Option<SchedulingPrelim>,
+    ) -> Result<Constraints, ConstraintError> {
+        let doc = Doc::new();
+        let map = doc.get_or_insert_map("state");
+        let mut txn = doc.transact_mut();
+        let todo: Todo = map.insert(
+            &mut txn,
+            "todo",
+            TodoPrelim {
+                recurrence: recurs.then(|| RecurrencePrelim {
+                    start: Tz::UTC.from_utc_datetime(&at(9)).into(),
+                    frequency: FrequencyPrelim::Daily,
+                    interval: 1.into(),
+                    by_weekday: vec![].into(),
+                    end: None,
+                    overrides: Default::default(),
+                }),
+                scheduling,
+                ..TodoPrelim::new("practice", at(0), Duration::hours(4))
+            },
+        );
+        Constraints::from_todo(&todo, &txn)
+    }
+
+    #[test]
+    fn test_non_recurring_windows() {
+        // Adjacent ideal windows cover a required window spanning both.
+        assert_eq!(
+            constraints(
+                false,
+                Some(scheduling(
+                    vec![absolute(12, 17), absolute(9, 12)],
+                    vec![absolute(10, 14)],
+                ))
+            ),
+            Ok(Constraints {
+                activity: Activity::TimeBased,
+                estimated_duration: Duration::hours(4),
+                chunk_size: Some(ChunkSize {
+                    min: Duration::minutes(30),
+                    max: Duration::hours(2),
+                }),
+                windows: TodoWindows::NonRecurring(Windows {
+                    ideal: vec![
+                        PeriodForNonRecurring {
+                            start: at(12),
+                            duration: Duration::hours(5),
+                        },
+                        PeriodForNonRecurring {
+                            start: at(9),
+                            duration: Duration::hours(3),
+                        },
+                    ],
+                    required: vec![PeriodForNonRecurring {
+                        start: at(10),
+                        duration: Duration::hours(4),
+                    }],
+                }),
+            })
+        );
+
+        assert_eq!(
+            constraints(
+                false,
+                Some(scheduling(
+                    vec![absolute(9, 12), absolute(13, 17)],
+                    vec![absolute(14, 15), absolute(11, 14)],
+                ))
+            ),
+            Err(ConstraintError::RequiredOutsideIdeal { index: 1 })
+        );
+
+        // No ideal windows means any time is ideal.
+        assert!(constraints(false, Some(scheduling(vec![], vec![absolute(1, 2)]))).is_ok());
+
+        assert_eq!(
+            constraints(false, Some(scheduling(vec![absolute(12, 9)], vec![]))),
+            Err(ConstraintError::InvalidWindow {
+                kind: WindowKind::Ideal,
+                index: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_recurring_windows() {
+        let windows = |recurs, ideal, required| -> Result<TodoWindows, ConstraintError> {
+            Ok(constraints(recurs, Some(scheduling(ideal, required)))?.windows)
+        };
+
+        match windows(
+            true,
+            vec![fractional(0.375, 0.5)],
+            vec![fractional(0.4, 0.45)],
+        ) {
+            Ok(TodoWindows::Recurring(Windows { ideal, required })) => {
+                assert_eq!(ideal[0].start.into_inner(), 0.375);
+                assert_eq!(ideal[0].end(), 0.5);
+                assert_eq!(required.len(), 1);
+                // Not narrowed, which would make this 0.4000000059604645.
+                assert_eq!(required[0].start.into_inner(), 0.4);
+            }
+            other => panic!("Expected recurring windows, got {other:?}"),
+        }
+
+        assert_eq!(
+            windows(true, vec![fractional(0.5, 1.5)], vec![]),
+            Err(ConstraintError::InvalidWindow {
+                kind: WindowKind::Ideal,
+                index: 0,
+            })
+        );
+        assert_eq!(
+            windows(true, vec![], vec![absolute(9, 10)]),
+            Err(ConstraintError::WrongWindowType {
+                kind: WindowKind::Required,
+                index: 0,
+                expected: "fractional",
+            })
+        );
+        assert_eq!(
+            windows(false, vec![fractional(0.1, 0.2)], vec![]),
+            Err(ConstraintError::WrongWindowType {
+                kind: WindowKind::Ideal,
+                index: 0,
+                expected: "absolute",
+            })
+        );
+    }
+
+    #[test]
+    fn test_chunk_size_and_defaults() {
+        let mut invalid = scheduling(vec![], vec![]);
+        invalid.chunk_size = Some(ChunkSizePrelim {
+            min: Duration::hours(2).into(),
+            max: Duration::hours(1).into(),
+        });
+        assert_eq!(
+            constraints(false, Some(invalid)),
+            Err(ConstraintError::InvalidChunkSize)
+        );
+
+        assert_eq!(
+            constraints(true, None),
+            Ok(Constraints {
+                activity: Activity::GoalBased,
+                estimated_duration: Duration::hours(4),
+                chunk_size: None,
+                windows: TodoWindows::Recurring(Windows::default()),
+            })
+        );
+    }
+}
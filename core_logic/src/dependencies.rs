@@ -0,0 +1,310 @@
+//! The graph formed by the `blocked_by` edges between todos: a todo can't start until the todos
+//! blocking it are completed.
+//!
+//! Unlike `child_todos`, the edges don't form a tree, and nothing in the document prevents them
+//! from forming cycles: even though `Todo::add_blocker` refuses to close one, two replicas can
+//! each add half of a cycle concurrently. So cycles are detected here, whenever the graph is used.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+use wire::state::Todo;
+use yrs_wrappers::{yref::YIndex, yrs_wrapper_error::YrsResult};
+
+#[derive(Debug, thiserror::Error, Clone, PartialEq, Eq)]
+pub enum DependencyError {
+    #[error("{} todos block each other", .todos.len())]
+    Cycle {
+        /// Every todo that's part of a cycle, ordered by ID.
+        todos: Vec<Uuid>,
+    },
+}
+
+/// A planned execution that starts before one of the todos blocking its todo is planned to end.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockedExecution {
+    pub todo: Uuid,
+    /// The position of the execution in the `planned_executions` of the todo.
+    pub execution: u32,
+    pub blocker: Uuid,
+    /// The end of the last planned execution of the blocker.
+    pub blocker_end: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Dependencies {
+    /// Every todo, with the todos blocking it.
+    blockers: BTreeMap<Uuid, BTreeSet<Uuid>>,
+}
+
+impl Dependencies {
+    /// The dependencies between the todos in `index`. Blockers that aren't in it (anymore) are
+    /// left out.
+    pub fn new(index: &YIndex<Todo>, txn: &impl yrs::ReadTxn) -> YrsResult<Self> {
+        let blockers = index
+            .iter()
+            .map(|(id, todo)| {
+                let blockers = todo
+                    .blocker_ids(txn)?
+                    .into_iter()
+                    .filter(|blocker| index.contains(*blocker))
+                    .collect();
+                Ok((id, blockers))
+            })
+            .collect::<YrsResult<_>>()?;
+        Ok(Dependencies { blockers })
+    }
+
+    /// Like `new`, but without the completed todos, since they don't block anything anymore.
+    pub fn pending(index: &YIndex<Todo>, txn: &impl yrs::ReadTxn) -> YrsResult<Self> {
+        let mut completed = BTreeSet::new();
+        for (id, todo) in index.iter() {
+            if *todo.completed(txn)? {
+                completed.insert(id);
+            }
+        }
+
+        let mut dependencies = Self::new(index, txn)?;
+        dependencies.retain(|todo| !completed.contains(&todo));
+        Ok(dependencies)
+    }
+
+    /// Removes the todos for which `f` returns false, along with their edges.
+    pub fn retain(&mut self, mut f: impl FnMut(Uuid) -> bool) {
+        self.blockers.retain(|todo, _| f(*todo));
+        let kept = self.blockers.keys().copied().collect::<BTreeSet<_>>();
+        for blockers in self.blockers.values_mut() {
+            blockers.retain(|blocker| kept.contains(blocker));
+        }
+    }
+
+    /// Ordered by ID.
+    pub fn todos(&self) -> impl Iterator<Item = Uuid> + '_ {
+        self.blockers.keys().copied()
+    }
+
+    /// The todos blocking `todo` directly, ordered by ID.
+    pub fn blockers(&self, todo: Uuid) -> impl Iterator<Item = Uuid> + '_ {
+        self.blockers.get(&todo).into_iter().flatten().copied()
+    }
+
+    /// The todos `todo` blocks directly, ordered by ID.
+    pub fn blocked(&self, todo: Uuid) -> impl Iterator<Item = Uuid> + '_ {
+        self.blockers
+            .iter()
+            .filter(move |(_, blockers)| blockers.contains(&todo))
+            .map(|(blocked, _)| *blocked)
+    }
+
+    /// Whether `blocker` blocks `todo`, directly or through other todos.
+    pub fn blocks(&self, blocker: Uuid, todo: Uuid) -> bool {
+        let mut seen = BTreeSet::new();
+        let mut stack = vec![todo];
+        while let Some(current) = stack.pop() {
+            for next in self.blockers(current) {
+                if next == blocker {
+                    return true;
+                }
+                if seen.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+        false
+    }
+
+    /// The todos that block themselves, through the todos blocking them.
+    pub fn in_cycles(&self) -> BTreeSet<Uuid> {
+        self.todos()
+            .filter(|todo| self.blocks(*todo, *todo))
+            .collect()
+    }
+
+    /// Every todo, after the todos blocking it. Ties are broken by ID, so the order only depends
+    /// on the graph.
+    pub fn topological_order(&self) -> Result<Vec<Uuid>, DependencyError> {
+        let mut waiting = self
+            .blockers
+            .iter()
+            .map(|(todo, blockers)| (*todo, blockers.len()))
+            .collect::<BTreeMap<_, _>>();
+        let mut ready = waiting
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(todo, _)| *todo)
+            .collect::<BTreeSet<_>>();
+
+        let mut order = Vec::with_capacity(self.blockers.len());
+        while let Some(todo) = ready.iter().next().copied() {
+            ready.remove(&todo);
+            order.push(todo);
+            for blocked in self.blocked(todo) {
+                let count = waiting.get_mut(&blocked).expect("every todo is a key");
+                *count -= 1;
+                if *count == 0 {
+                    ready.insert(blocked);
+                }
+            }
+        }
+
+        if order.len() < self.blockers.len() {
+            return Err(DependencyError::Cycle {
+                todos: self.in_cycles().into_iter().collect(),
+            });
+        }
+        Ok(order)
+    }
+
+    /// The planned executions of the todos in `index` that start before the last planned
+    /// execution of one of their blockers ends, ordered by todo and position. An execution is
+    /// reported once, for the blocker that ends last.
+    pub fn blocked_executions(
+        &self,
+        index: &YIndex<Todo>,
+        txn: &impl yrs::ReadTxn,
+    ) -> YrsResult<Vec<BlockedExecution>> {
+        let mut last_ends = BTreeMap::new();
+        for (id, todo) in index.iter() {
+            for execution in todo.planned_executions(txn)?.iter(txn) {
+                let end = *execution?.end(txn)?;
+                let last_end = last_ends.entry(id).or_insert(end);
+                *last_end = (*last_end).max(end);
+            }
+        }
+
+        let mut blocked = Vec::new();
+        for (todo, blockers) in &self.blockers {
+            let latest = blockers
+                .iter()
+                .filter_map(|blocker| Some((*last_ends.get(blocker)?, *blocker)))
+                .max();
+            let ((blocker_end, blocker), executions) = match (latest, index.get(*todo)) {
+                (Some(latest), Some(blocked_todo)) => {
+                    (latest, blocked_todo.planned_executions(txn)?)
+                }
+                _ => continue,
+            };
+            for (position, execution) in executions.iter(txn).enumerate() {
+                if *execution?.start(txn)? < blocker_end {
+                    blocked.push(BlockedExecution {
+                        todo: *todo,
+                        execution: position as u32,
+                        blocker,
+                        blocker_end,
+                    });
+                }
+            }
+        }
+        Ok(blocked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveDate, NaiveDateTime};
+    use uuid::Uuid;
+    use wire::state::{PlannedExecutionPrelim, StatePrelim, TodoPrelim};
+    use yrs::{Doc, Map, Transact};
+    use yrs_wrappers::{yref::YRefPrelim, yrs_wrapper_error::YrsResult};
+
+    use super::{BlockedExecution, Dependencies, DependencyError};
+
+    fn at(hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2023, 5, 1)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    fn todo_prelim(id: u128, blocked_by: &[u128], planned: &[(u32, u32)]) -> TodoPrelim {
+        TodoPrelim {
+            id: Uuid::from_u128(id).into(),
+            planned_executions: planned
+                .iter()
+                .map(|(start, end)| PlannedExecutionPrelim {
+                    start: at(*start).into(),
+                    end: at(*end).into(),
+                })
+                .collect::<Vec<_>>()
+                .into(),
+            blocked_by: Some(
+                blocked_by
+                    .iter()
+                    .map(|blocker| YRefPrelim::new(Uuid::from_u128(*blocker)))
+                    .collect::<Vec<_>>()
+                    .into(),
+            ),
+            ..TodoPrelim::new("todo", at(0), Duration::hours(1))
+        }
+    }
+
+    fn graph(todos: Vec<TodoPrelim>) -> YrsResult<(Dependencies, Vec<BlockedExecution>)> {
+        let doc = Doc::new();
+        let map = doc.get_or_insert_map("state");
+        let state = map.insert(
+            &mut doc.transact_mut(),
+            "state",
+            StatePrelim {
+                todos: todos.into(),
+            },
+        );
+
+        let txn = doc.transact();
+        let index = state.todo_index(&txn)?;
+        let dependencies = Dependencies::pending(&index, &txn)?;
+        let blocked = dependencies.blocked_executions(&index, &txn)?;
+        Ok((dependencies, blocked))
+    }
+
+    #[test]
+    fn test_topological_order() -> YrsResult<()> {
+        // 4 is blocked by a todo that has been deleted.
+        let (dependencies, blocked) = graph(vec![
+            todo_prelim(1, &[3], &[(9, 10)]),
+            todo_prelim(2, &[], &[(8, 9)]),
+            todo_prelim(3, &[2], &[(10, 11)]),
+            todo_prelim(4, &[5], &[]),
+        ])?;
+        let id = Uuid::from_u128;
+
+        assert_eq!(
+            dependencies.topological_order(),
+            Ok(vec![id(2), id(3), id(1), id(4)])
+        );
+        assert!(dependencies.blocks(id(2), id(1)));
+        assert!(dependencies.in_cycles().is_empty());
+
+        // 1 is planned before 3, which is blocking it.
+        assert_eq!(
+            blocked,
+            vec![BlockedExecution {
+                todo: id(1),
+                execution: 0,
+                blocker: id(3),
+                blocker_end: at(11),
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cycles() -> YrsResult<()> {
+        let (dependencies, _) = graph(vec![
+            todo_prelim(1, &[2], &[]),
+            todo_prelim(2, &[1], &[]),
+            todo_prelim(3, &[1], &[]),
+            todo_prelim(4, &[4], &[]),
+        ])?;
+        let id = Uuid::from_u128;
+
+        let cycle = DependencyError::Cycle {
+            todos: vec![id(1), id(2), id(4)],
+        };
+        assert_eq!(dependencies.topological_order(), Err(cycle.clone()));
+        assert_eq!(cycle.to_string(), "3 todos block each other");
+
+        Ok(())
+    }
+}
@@ -1,7 +1,12 @@
 use nutype::nutype;
 use std::collections::HashMap;
 use std::time::Duration;
-use time::OffsetDateTime;
+
+pub mod constraints;
+pub mod dependencies;
+pub mod scheduler;
+
+pub use constraints::{PeriodForNonRecurring, PeriodForRecurring};
 
 #[derive(Debug, Eq, PartialEq, Hash)]
 pub enum ExternalTimedEntrySync {
@@ -28,20 +33,9 @@ pub struct TimedEntry {
     duration: Duration,
 }
 
-pub struct PeriodForRecurring {
-    start: Fraction,
-
-    /// The duration after the start of the period.
-    duration: Fraction,
-}
-
-pub struct PeriodForNonRecurring {
-    start: OffsetDateTime,
-    duration: Duration,
-}
-
 #[nutype(validate(with = |n| 0.0 <= *n && *n <= 1.0))]
-pub struct Fraction(f32);
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Fraction(f64);
 
 #[cfg(test)]
 mod tests {
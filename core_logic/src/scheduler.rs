@@ -0,0 +1,888 @@
+//! Proposes planned executions for the todos in a document.
+//!
+//! The scheduler is greedy and deterministic: the same document and settings always produce the
+//! same proposal. Todos are planned one after the other, earliest deadline first, each taking the
+//! earliest free time that fits its constraints, first within its ideal windows and then anywhere
+//! its required windows allow. A todo gets at most one chunk per stretch of free time, so that it
+//! is spread out rather than done in one go.
+//!
+//! What's left to plan of a todo is its estimated duration, minus the time already spent on it
+//! and the time already planned for it from now on. The occurrences of a recurring todo are
+//! planned per day or week (see `wire::state::recurrence::OccurrencePeriod`), before its end.
+//!
+//! A todo is only planned after the todos blocking it (see `crate::dependencies`), once all of
+//! them are, and after their last planned execution. Blockers inherit the deadlines of the todos
+//! they block, so that they go first when those are urgent.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{Duration, NaiveDateTime};
+use uuid::Uuid;
+use wire::state::recurrence::OccurrenceStatus;
+use wire::state::{todo_index, PlannedExecutionPrelim, Todo};
+use yrs::TransactionMut;
+use yrs_wrappers::{
+    yref::YIndex,
+    yrs_vec::YrsVec,
+    yrs_wrapper_error::{YrsResult, YrsWrapperError},
+};
+
+use crate::constraints::{
+    ChunkSize, ConstraintError, Constraints, PeriodForNonRecurring, PeriodForRecurring, TodoWindows,
+};
+use crate::dependencies::Dependencies;
+
+/// In seconds. Planning starts at the first multiple of this after `SchedulerSettings::now`, and
+/// todos without a chunk size aren't split into smaller chunks than this.
+const GRANULARITY: i64 = 15 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Interval {
+    /// In UTC, like all times in this module.
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+}
+
+impl Interval {
+    pub fn new(start: NaiveDateTime, end: NaiveDateTime) -> Self {
+        Interval { start, end }
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.end - self.start
+    }
+
+    fn overlap(&self, other: &Interval) -> Duration {
+        (self.end.min(other.end) - self.start.max(other.start)).max(Duration::zero())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchedulerSettings {
+    /// Nothing is planned before this.
+    pub now: NaiveDateTime,
+    /// How far after `now` to plan.
+    pub horizon: Duration,
+    /// When nothing can be planned, like nights or meetings.
+    pub busy: Vec<Interval>,
+}
+
+impl SchedulerSettings {
+    /// Plans the next two weeks, without any busy periods.
+    pub fn new(now: NaiveDateTime) -> Self {
+        SchedulerSettings {
+            now,
+            horizon: Duration::weeks(2),
+            busy: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProposedExecution {
+    pub todo: Uuid,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+}
+
+impl ProposedExecution {
+    pub fn prelim(&self) -> PlannedExecutionPrelim {
+        PlannedExecutionPrelim {
+            start: self.start.into(),
+            end: self.end.into(),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error, Clone, PartialEq)]
+pub enum Infeasibility {
+    #[error("Its scheduling constraints are invalid: {0}")]
+    InvalidConstraints(ConstraintError),
+
+    #[error("Its deadline has passed")]
+    DeadlinePassed,
+
+    #[error("{} minutes of it don't fit before its deadline", .unscheduled.num_minutes())]
+    NotEnoughTime { unscheduled: Duration },
+
+    #[error("It blocks itself, through the todos blocking it")]
+    DependencyCycle,
+
+    #[error("It's blocked by a todo that can't be planned completely")]
+    Blocked { blocker: Uuid },
+}
+
+/// A todo that couldn't be (completely) planned.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Infeasible {
+    pub todo: Uuid,
+    pub reason: Infeasibility,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Proposal {
+    /// Ordered by start.
+    pub executions: Vec<ProposedExecution>,
+    pub infeasible: Vec<Infeasible>,
+}
+
+impl Proposal {
+    /// Adds the proposed executions to the planned executions of their todos. Doing this in one
+    /// transaction makes it undoable as a whole.
+    pub fn commit(&self, index: &YIndex<Todo>, txn: &mut TransactionMut) -> YrsResult<()> {
+        for execution in &self.executions {
+            let todo = index
+                .get(execution.todo)
+                .ok_or(YrsWrapperError::DanglingRef { id: execution.todo })?;
+            todo.planned_executions(&*txn)?
+                .push(txn, execution.prelim());
+        }
+        Ok(())
+    }
+}
+
+/// What's left to plan of a todo, or of the occurrences of a recurring todo in one period.
+struct Task {
+    todo: Uuid,
+    /// Nothing is planned for the task before this.
+    earliest: NaiveDateTime,
+    deadline: Option<NaiveDateTime>,
+    remaining: Duration,
+    chunk_size: Option<ChunkSize>,
+    /// Empty means any time, like in `constraints::Windows`.
+    ideal: Vec<Interval>,
+    required: Vec<Interval>,
+}
+
+/// Plans `todos` and all their descendants.
+pub fn schedule(
+    todos: &YrsVec<Todo>,
+    txn: &impl yrs::ReadTxn,
+    settings: &SchedulerSettings,
+) -> YrsResult<Proposal> {
+    let horizon = Interval::new(round_up(settings.now), settings.now + settings.horizon);
+    let index = todo_index(todos, txn)?;
+
+    // Todos in a cycle can't be planned, and neither can the todos they block.
+    let mut dependencies = Dependencies::pending(&index, txn)?;
+    let in_cycles = dependencies.in_cycles();
+    let mut stuck = BTreeMap::new();
+    for todo in dependencies.todos() {
+        if in_cycles.contains(&todo) {
+            stuck.insert(todo, Infeasibility::DependencyCycle);
+        } else if let Some(blocker) = in_cycles.iter().find(|c| dependencies.blocks(**c, todo)) {
+            stuck.insert(todo, Infeasibility::Blocked { blocker: *blocker });
+        }
+    }
+    dependencies.retain(|todo| !stuck.contains_key(&todo));
+
+    let mut taken = settings.busy.clone();
+    let mut last_ends = BTreeMap::new();
+    let mut tasks = Vec::new();
+    let mut infeasible = Vec::new();
+    for (id, todo) in index.iter() {
+        let executions = executions_of(todo, txn, settings.now)?;
+        taken.extend(executions.planned.iter().copied());
+        if let Some(last_end) = executions.planned.iter().map(|e| e.end).max() {
+            last_ends.insert(id, last_end);
+        }
+        if *todo.completed(txn)? {
+            continue;
+        }
+        if let Some(reason) = stuck.remove(&id) {
+            infeasible.push(Infeasible { todo: id, reason });
+            continue;
+        }
+        match Constraints::from_todo(todo, txn) {
+            Ok(constraints) => {
+                tasks.extend(tasks_of(id, todo, txn, constraints, &executions, horizon)?)
+            }
+            Err(e) => infeasible.push(Infeasible {
+                todo: id,
+                reason: Infeasibility::InvalidConstraints(e),
+            }),
+        }
+    }
+    // The index is a hash map, so its order can't be relied on.
+    infeasible.sort_by_key(|infeasible| infeasible.todo);
+    // What couldn't be planned at all blocks the todos it blocks.
+    let mut incomplete = infeasible
+        .iter()
+        .map(|infeasible| infeasible.todo)
+        .collect::<BTreeSet<_>>();
+
+    let deadlines = inherited_deadlines(&tasks, &dependencies);
+    tasks.sort_by_key(|task| {
+        (
+            task.deadline
+                .into_iter()
+                .chain(deadlines.get(&task.todo).copied())
+                .min()
+                .unwrap_or(NaiveDateTime::MAX),
+            task.earliest,
+            task.todo,
+        )
+    });
+    let mut unplanned = BTreeMap::new();
+    for task in &tasks {
+        *unplanned.entry(task.todo).or_insert(0) += 1;
+    }
+
+    let mut free = subtract(&[horizon], &normalize(taken));
+    let mut executions = Vec::new();
+    while !tasks.is_empty() {
+        // There's always one, since there are no cycles left.
+        let next = tasks
+            .iter()
+            .position(|task| {
+                dependencies
+                    .blockers(task.todo)
+                    .all(|blocker| unplanned.get(&blocker).map_or(true, |count| *count == 0))
+            })
+            .expect("Cycles should have been left out");
+        let mut task = tasks.remove(next);
+        *unplanned
+            .get_mut(&task.todo)
+            .expect("Every task is counted") -= 1;
+
+        // Whatever doesn't fit before the horizon can still be planned later.
+        let deadline_within_horizon = task
+            .deadline
+            .map_or(false, |deadline| deadline <= horizon.end);
+
+        if let Some(blocker) = dependencies
+            .blockers(task.todo)
+            .find(|blocker| incomplete.contains(blocker))
+        {
+            if incomplete.insert(task.todo) && deadline_within_horizon {
+                infeasible.push(Infeasible {
+                    todo: task.todo,
+                    reason: Infeasibility::Blocked { blocker },
+                });
+            }
+            continue;
+        }
+        task.earliest = dependencies
+            .blockers(task.todo)
+            .filter_map(|blocker| last_ends.get(&blocker).copied())
+            .fold(task.earliest, NaiveDateTime::max);
+
+        if task
+            .deadline
+            .map_or(false, |deadline| deadline <= horizon.start)
+        {
+            incomplete.insert(task.todo);
+            infeasible.push(Infeasible {
+                todo: task.todo,
+                reason: Infeasibility::DeadlinePassed,
+            });
+            continue;
+        }
+
+        let chunks = place(&mut task, &free);
+        free = subtract(&free, &chunks);
+        if let Some(end) = chunks.iter().map(|chunk| chunk.end).max() {
+            let last_end = last_ends.entry(task.todo).or_insert(end);
+            *last_end = (*last_end).max(end);
+        }
+        executions.extend(chunks.into_iter().map(|chunk| ProposedExecution {
+            todo: task.todo,
+            start: chunk.start,
+            end: chunk.end,
+        }));
+
+        if task.remaining > Duration::zero() {
+            incomplete.insert(task.todo);
+            if deadline_within_horizon {
+                infeasible.push(Infeasible {
+                    todo: task.todo,
+                    reason: Infeasibility::NotEnoughTime {
+                        unscheduled: task.remaining,
+                    },
+                });
+            }
+        }
+    }
+    executions.sort_by_key(|execution| (execution.start, execution.todo));
+
+    Ok(Proposal {
+        executions,
+        infeasible,
+    })
+}
+
+/// For every todo blocking others, the earliest deadline of the tasks of the todos it blocks,
+/// directly or not.
+fn inherited_deadlines(
+    tasks: &[Task],
+    dependencies: &Dependencies,
+) -> BTreeMap<Uuid, NaiveDateTime> {
+    fn lower(deadlines: &mut BTreeMap<Uuid, NaiveDateTime>, todo: Uuid, deadline: NaiveDateTime) {
+        let earliest = deadlines.entry(todo).or_insert(deadline);
+        *earliest = (*earliest).min(deadline);
+    }
+
+    let mut own = BTreeMap::new();
+    for task in tasks {
+        if let Some(deadline) = task.deadline {
+            lower(&mut own, task.todo, deadline);
+        }
+    }
+
+    let order = dependencies
+        .topological_order()
+        .expect("Cycles should have been left out");
+    let mut inherited = BTreeMap::new();
+    // Blocked todos come after their blockers, so going backwards, what they inherited is
+    // complete by the time they pass it on.
+    for todo in order.into_iter().rev() {
+        let deadline = own.get(&todo).into_iter().chain(inherited.get(&todo)).min();
+        if let Some(deadline) = deadline.copied() {
+            for blocker in dependencies.blockers(todo) {
+                lower(&mut inherited, blocker, deadline);
+            }
+        }
+    }
+    inherited
+}
+
+struct Executions {
+    /// Open ones end at `now`.
+    actual: Vec<Interval>,
+    planned: Vec<Interval>,
+}
+
+fn executions_of(
+    todo: &Todo,
+    txn: &impl yrs::ReadTxn,
+    now: NaiveDateTime,
+) -> YrsResult<Executions> {
+    let actual = todo
+        .actual_executions(txn)?
+        .iter(txn)
+        .map(|execution| {
+            let execution = execution?;
+            let start = *execution.start(txn)?;
+            let end = match execution.end(txn) {
+                Some(end) => *end?,
+                None => now.max(start),
+            };
+            Ok(Interval::new(start, end))
+        })
+        .collect::<YrsResult<_>>()?;
+    let planned = todo
+        .planned_executions(txn)?
+        .iter(txn)
+        .map(|execution| {
+            let execution = execution?;
+            Ok(Interval::new(*execution.start(txn)?, *execution.end(txn)?))
+        })
+        .collect::<YrsResult<_>>()?;
+    Ok(Executions { actual, planned })
+}
+
+fn tasks_of(
+    id: Uuid,
+    todo: &Todo,
+    txn: &impl yrs::ReadTxn,
+    constraints: Constraints,
+    executions: &Executions,
+    horizon: Interval,
+) -> YrsResult<Vec<Task>> {
+    // Planned executions that are over only count if they were also actually executed.
+    let covered = |period: &Interval| {
+        let future = Interval::new(period.start.max(horizon.start), period.end);
+        let actual = executions.actual.iter().map(|e| e.overlap(period));
+        let planned = executions.planned.iter().map(|e| e.overlap(&future));
+        actual
+            .chain(planned)
+            .fold(Duration::zero(), |sum, d| sum + d)
+    };
+
+    match constraints.windows {
+        TodoWindows::NonRecurring(windows) => {
+            let everything = Interval::new(NaiveDateTime::MIN, NaiveDateTime::MAX);
+            let remaining = constraints.estimated_duration - covered(&everything);
+            if remaining <= Duration::zero() {
+                return Ok(Vec::new());
+            }
+
+            let to_interval = |p: &PeriodForNonRecurring| Interval::new(p.start, p.end());
+            Ok(vec![Task {
+                todo: id,
+                earliest: horizon.start,
+                deadline: todo.deadline(txn).transpose()?.map(|deadline| *deadline),
+                remaining,
+                chunk_size: constraints.chunk_size,
+                ideal: windows.ideal.iter().map(to_interval).collect(),
+                required: windows.required.iter().map(to_interval).collect(),
+            }])
+        }
+        TodoWindows::Recurring(windows) => {
+            let recurrence = match todo.recurrence(txn).transpose()? {
+                Some(recurrence) => recurrence,
+                None => return Ok(Vec::new()),
+            };
+
+            // Occurrences starting a week before the horizon can still be in a period that
+            // isn't over.
+            let mut pending_per_period = BTreeMap::new();
+            for occurrence in
+                todo.occurrences(txn, horizon.start - Duration::weeks(1), horizon.end)?
+            {
+                if occurrence.status != OccurrenceStatus::Pending {
+                    continue;
+                }
+                let period = recurrence.period_of(txn, &occurrence)?;
+                pending_per_period
+                    .entry(period.start())
+                    .or_insert((period, 0))
+                    .1 += 1;
+            }
+
+            Ok(pending_per_period
+                .into_values()
+                .filter_map(|(period, pending)| {
+                    let interval = Interval::new(period.start(), period.end());
+                    let to_interval = |p: &PeriodForRecurring| {
+                        Interval::new(period.at(p.start.into_inner()), period.at(p.end()))
+                    };
+                    let task = Task {
+                        todo: id,
+                        earliest: interval.start,
+                        deadline: Some(interval.end),
+                        remaining: constraints.estimated_duration * pending - covered(&interval),
+                        chunk_size: constraints.chunk_size,
+                        ideal: windows.ideal.iter().map(to_interval).collect(),
+                        required: windows.required.iter().map(to_interval).collect(),
+                    };
+                    (interval.end > horizon.start && task.remaining > Duration::zero())
+                        .then_some(task)
+                })
+                .collect())
+        }
+    }
+}
+
+/// Plans as much of `task` as fits in `free`, and returns the chunks it's planned in.
+fn place(task: &mut Task, free: &[Interval]) -> Vec<Interval> {
+    let bounds = [Interval::new(
+        task.earliest,
+        task.deadline.unwrap_or(NaiveDateTime::MAX),
+    )];
+    let required = if task.required.is_empty() {
+        bounds.to_vec()
+    } else {
+        intersect(&normalize(task.required.clone()), &bounds)
+    };
+    let mut passes = Vec::new();
+    if !task.ideal.is_empty() {
+        passes.push(intersect(&required, &normalize(task.ideal.clone())));
+    }
+    passes.push(required);
+
+    let mut chunks: Vec<Interval> = Vec::new();
+    for allowed in passes {
+        for gap in intersect(&subtract(free, &normalize(chunks.clone())), &allowed) {
+            if task.remaining <= Duration::zero() {
+                break;
+            }
+            // Only one chunk per stretch of free time.
+            if chunks
+                .iter()
+                .any(|chunk| chunk.end >= gap.start && chunk.start <= gap.end)
+            {
+                continue;
+            }
+
+            let (min, max) = match task.chunk_size {
+                Some(ChunkSize { min, max }) => (min, max),
+                None => (Duration::seconds(GRANULARITY), task.remaining),
+            };
+            let length = gap.duration().min(max).min(task.remaining);
+            if length < min.min(task.remaining) {
+                continue;
+            }
+            chunks.push(Interval::new(gap.start, gap.start + length));
+            task.remaining = task.remaining - length;
+        }
+    }
+    chunks.sort();
+    chunks
+}
+
+fn round_up(time: NaiveDateTime) -> NaiveDateTime {
+    let seconds = time.timestamp() + i64::from(time.timestamp_subsec_nanos() > 0);
+    let rounded = (seconds + GRANULARITY - 1).div_euclid(GRANULARITY) * GRANULARITY;
+    NaiveDateTime::from_timestamp_opt(rounded, 0).expect("Rounding shouldn't leave the range")
+}
+
+/// Sorts `intervals`, and merges the ones that overlap or touch.
+fn normalize(mut intervals: Vec<Interval>) -> Vec<Interval> {
+    intervals.sort();
+    let mut merged: Vec<Interval> = Vec::new();
+    for interval in intervals.into_iter().filter(|i| i.start < i.end) {
+        match merged.last_mut() {
+            Some(last) if interval.start <= last.end => last.end = last.end.max(interval.end),
+            _ => merged.push(interval),
+        }
+    }
+    merged
+}
+
+/// Both `from` and `removed` have to be normalized, and so is the result.
+fn subtract(from: &[Interval], removed: &[Interval]) -> Vec<Interval> {
+    let mut result = Vec::new();
+    for interval in from {
+        let mut start = interval.start;
+        for removed in removed
+            .iter()
+            .filter(|r| r.end > interval.start && r.start < interval.end)
+        {
+            if removed.start > start {
+                result.push(Interval::new(start, removed.start));
+            }
+            start = start.max(removed.end);
+        }
+        if start < interval.end {
+            result.push(Interval::new(start, interval.end));
+        }
+    }
+    result
+}
+
+/// Both `a` and `b` have to be normalized, and so is the result.
+fn intersect(a: &[Interval], b: &[Interval]) -> Vec<Interval> {
+    let mut result = Vec::new();
+    for x in a {
+        for y in b {
+            let (start, end) = (x.start.max(y.start), x.end.min(y.end));
+            if start < end {
+                result.push(Interval::new(start, end));
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveDate, NaiveDateTime, TimeZone};
+    use chrono_tz::Tz;
+    use uuid::Uuid;
+    use wire::state::recurrence::OccurrenceStatus;
+    use wire::state::{
+        ActivityKindPrelim, ActualExecutionPrelim, ChunkSizePrelim, FrequencyPrelim,
+        RecurrencePrelim, SchedulingPrelim, State, StatePrelim, TimeWindowPrelim, TodoPrelim,
+    };
+    use yrs::{Doc, Map, Transact};
+    use yrs_wrappers::{
+        yref::YRefPrelim, yrs_basic_types::YIdPrelim, yrs_wrapper_error::YrsResult,
+    };
+
+    use super::{
+        schedule, Infeasibility, Infeasible, Interval, ProposedExecution, SchedulerSettings,
+    };
+    use crate::constraints::ConstraintError;
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2023, 5, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn todo_prelim(id: YIdPrelim, estimated_duration: Duration) -> TodoPrelim {
+        TodoPrelim {
+            id,
+            ..TodoPrelim::new("todo", at(1, 0, 0), estimated_duration)
+        }
+    }
+
+    fn chunked(min: Duration, max: Duration) -> SchedulingPrelim {
+        SchedulingPrelim {
+            activity: ActivityKindPrelim::TimeBased,
+            chunk_size: Some(ChunkSizePrelim {
+                min: min.into(),
+                max: max.into(),
+            }),
+            ideal_windows: vec![].into(),
+            required_windows: vec![].into(),
+        }
+    }
+
+    fn state(doc: &Doc, todos: Vec<TodoPrelim>) -> State {
+        let map = doc.get_or_insert_map("state");
+        map.insert(
+            &mut doc.transact_mut(),
+            "state",
+            StatePrelim {
+                todos: todos.into(),
+            },
+        )
+    }
+
+    fn execution(todo: YIdPrelim, start: NaiveDateTime, end: NaiveDateTime) -> ProposedExecution {
+        ProposedExecution {
+            todo: todo.id(),
+            start,
+            end,
+        }
+    }
+
+    #[test]
+    fn test_schedule() -> YrsResult<()> {
+        let (a, b) = (YIdPrelim::new(), YIdPrelim::new());
+        let doc = Doc::new();
+        let state = state(
+            &doc,
+            vec![
+                TodoPrelim {
+                    deadline: Some(at(2, 12, 0).into()),
+                    actual_executions: vec![ActualExecutionPrelim {
+                        start: at(1, 6, 0).into(),
+                        end: Some(at(1, 6, 30).into()),
+                    }]
+                    .into(),
+                    scheduling: Some(chunked(Duration::hours(1), Duration::hours(2))),
+                    ..todo_prelim(a, Duration::hours(3))
+                },
+                todo_prelim(b, Duration::hours(1)),
+            ],
+        );
+        let settings = SchedulerSettings {
+            now: at(1, 7, 50),
+            horizon: Duration::days(2),
+            busy: vec![
+                Interval::new(at(1, 18, 0), at(2, 8, 0)),
+                Interval::new(at(2, 18, 0), at(3, 8, 0)),
+            ],
+        };
+
+        let txn = doc.transact();
+        let proposal = schedule(&state.todos(&txn)?, &txn, &settings)?;
+        // The earlier deadline goes first, and gets one chunk per day.
+        assert_eq!(
+            proposal.executions,
+            vec![
+                execution(a, at(1, 8, 0), at(1, 10, 0)),
+                execution(b, at(1, 10, 0), at(1, 11, 0)),
+                execution(a, at(2, 8, 0), at(2, 8, 30)),
+            ]
+        );
+        assert!(proposal.infeasible.is_empty());
+        assert_eq!(schedule(&state.todos(&txn)?, &txn, &settings)?, proposal);
+
+        let index = state.todo_index(&txn)?;
+        drop(txn);
+        proposal.commit(&index, &mut doc.transact_mut())?;
+
+        // Everything is planned now.
+        let txn = doc.transact();
+        let a_executions = index.get(a.id()).unwrap().planned_executions(&txn)?;
+        assert_eq!(a_executions.len(&txn), 2);
+        assert!(schedule(&state.todos(&txn)?, &txn, &settings)?
+            .executions
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_infeasible() -> YrsResult<()> {
+        let (tight, overdue, invalid, done) = (
+            YIdPrelim::new(),
+            YIdPrelim::new(),
+            YIdPrelim::new(),
+            YIdPrelim::new(),
+        );
+        let doc = Doc::new();
+        let state = state(
+            &doc,
+            vec![
+                TodoPrelim {
+                    deadline: Some(at(1, 12, 0).into()),
+                    ..todo_prelim(tight, Duration::hours(5))
+                },
+                TodoPrelim {
+                    deadline: Some(at(1, 6, 0).into()),
+                    ..todo_prelim(overdue, Duration::hours(1))
+                },
+                TodoPrelim {
+                    scheduling: Some(chunked(Duration::hours(2), Duration::hours(1))),
+                    ..todo_prelim(invalid, Duration::hours(1))
+                },
+                TodoPrelim {
+                    completed: true.into(),
+                    deadline: Some(at(1, 6, 0).into()),
+                    ..todo_prelim(done, Duration::hours(1))
+                },
+            ],
+        );
+        let settings = SchedulerSettings {
+            horizon: Duration::days(1),
+            ..SchedulerSettings::new(at(1, 8, 0))
+        };
+
+        let txn = doc.transact();
+        let proposal = schedule(&state.todos(&txn)?, &txn, &settings)?;
+        assert_eq!(
+            proposal.executions,
+            vec![execution(tight, at(1, 8, 0), at(1, 12, 0))]
+        );
+        assert_eq!(
+            proposal.infeasible,
+            vec![
+                Infeasible {
+                    todo: invalid.id(),
+                    reason: Infeasibility::InvalidConstraints(ConstraintError::InvalidChunkSize),
+                },
+                Infeasible {
+                    todo: overdue.id(),
+                    reason: Infeasibility::DeadlinePassed,
+                },
+                Infeasible {
+                    todo: tight.id(),
+                    reason: Infeasibility::NotEnoughTime {
+                        unscheduled: Duration::hours(1),
+                    },
+                },
+            ]
+        );
+        assert_eq!(
+            proposal.infeasible[2].reason.to_string(),
+            "60 minutes of it don't fit before its deadline"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recurring() -> YrsResult<()> {
+        let id = YIdPrelim::new();
+        let doc = Doc::new();
+        // Daily, ideally in the afternoon.
+        let state = state(
+            &doc,
+            vec![TodoPrelim {
+                actual_executions: vec![ActualExecutionPrelim {
+                    start: at(1, 7, 0).into(),
+                    end: Some(at(1, 7, 20).into()),
+                }]
+                .into(),
+                recurrence: Some(RecurrencePrelim {
+                    start: Tz::UTC.from_utc_datetime(&at(1, 9, 0)).into(),
+                    frequency: FrequencyPrelim::Daily,
+                    interval: 1.into(),
+                    by_weekday: vec![].into(),
+                    end: None,
+                    overrides: Default::default(),
+                }),
+                scheduling: Some(SchedulingPrelim {
+                    activity: ActivityKindPrelim::TimeBased,
+                    chunk_size: None,
+                    ideal_windows: vec![TimeWindowPrelim::Fractional {
+                        start: 0.5.into(),
+                        end: 0.75.into(),
+                    }]
+                    .into(),
+                    required_windows: vec![].into(),
+                }),
+                ..todo_prelim(id, Duration::hours(1))
+            }],
+        );
+        let settings = SchedulerSettings {
+            horizon: Duration::days(3),
+            ..SchedulerSettings::new(at(1, 8, 0))
+        };
+
+        let mut txn = doc.transact_mut();
+        let todo = state.todos(&txn)?.get(&txn, 0)?.unwrap();
+        let recurrence = todo.recurrence(&txn).unwrap()?;
+        recurrence.set_occurrence_status(
+            &mut txn,
+            NaiveDate::from_ymd_opt(2023, 5, 2).unwrap(),
+            OccurrenceStatus::Skipped,
+        )?;
+
+        // The time already spent on the first day counts, and the second day is skipped.
+        assert_eq!(
+            schedule(&state.todos(&txn)?, &txn, &settings)?.executions,
+            vec![
+                execution(id, at(1, 12, 0), at(1, 12, 40)),
+                execution(id, at(3, 12, 0), at(3, 13, 0)),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_dependencies() -> YrsResult<()> {
+        let [a, b, c, d, e, f] = [1, 2, 3, 4, 5, 6].map(|id| YIdPrelim::from(Uuid::from_u128(id)));
+        let blocked_by = |blocker: YIdPrelim| Some(vec![YRefPrelim::new(blocker.id())].into());
+        let doc = Doc::new();
+        let state = state(
+            &doc,
+            vec![
+                TodoPrelim {
+                    deadline: Some(at(1, 12, 0).into()),
+                    blocked_by: blocked_by(b),
+                    ..todo_prelim(a, Duration::hours(1))
+                },
+                todo_prelim(b, Duration::hours(2)),
+                TodoPrelim {
+                    deadline: Some(at(1, 10, 0).into()),
+                    ..todo_prelim(c, Duration::hours(1))
+                },
+                TodoPrelim {
+                    blocked_by: blocked_by(e),
+                    ..todo_prelim(d, Duration::hours(1))
+                },
+                TodoPrelim {
+                    blocked_by: blocked_by(d),
+                    ..todo_prelim(e, Duration::hours(1))
+                },
+                TodoPrelim {
+                    blocked_by: blocked_by(d),
+                    ..todo_prelim(f, Duration::hours(1))
+                },
+            ],
+        );
+        let settings = SchedulerSettings {
+            horizon: Duration::days(1),
+            ..SchedulerSettings::new(at(1, 8, 0))
+        };
+
+        let txn = doc.transact();
+        let proposal = schedule(&state.todos(&txn)?, &txn, &settings)?;
+        // b inherits the deadline of a, which has to wait for it.
+        assert_eq!(
+            proposal.executions,
+            vec![
+                execution(c, at(1, 8, 0), at(1, 9, 0)),
+                execution(b, at(1, 9, 0), at(1, 11, 0)),
+                execution(a, at(1, 11, 0), at(1, 12, 0)),
+            ]
+        );
+        assert_eq!(
+            proposal.infeasible,
+            vec![
+                Infeasible {
+                    todo: d.id(),
+                    reason: Infeasibility::DependencyCycle,
+                },
+                Infeasible {
+                    todo: e.id(),
+                    reason: Infeasibility::DependencyCycle,
+                },
+                Infeasible {
+                    todo: f.id(),
+                    reason: Infeasibility::Blocked { blocker: d.id() },
+                },
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 use leptos::*;
 use leptos_dom::html::div;
 use std::ops::Deref;
+use wire::state::recurrence::OccurrenceStatus;
 
 use super::length::TimeLength;
 
@@ -9,6 +10,12 @@ pub enum PeriodState {
     ActualUnbonded,
     Actual(TimeLength),
     Planned(TimeLength),
+    /// An occurrence of a recurring todo.
+    Occurrence(TimeLength, OccurrenceStatus),
+    /// A planned execution proposed by the scheduler, not committed yet.
+    Proposed(TimeLength),
+    /// A planned execution that starts before a todo blocking its todo is planned to end.
+    Blocked(TimeLength),
 }
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
@@ -18,10 +25,22 @@ pub struct PeriodProps {
 
 #[allow(non_snake_case)]
 pub fn Period(cx: Scope, props: PeriodProps) -> impl IntoView {
+    let colors = match &props.period {
+        PeriodState::Occurrence(_, OccurrenceStatus::Pending) => "bg-blue-300 text-white",
+        PeriodState::Occurrence(_, OccurrenceStatus::Completed) => "bg-green-500 text-white",
+        PeriodState::Occurrence(_, OccurrenceStatus::Skipped) => "bg-gray-300 text-gray-500",
+        PeriodState::Proposed(_) => "bg-blue-100 text-blue-700",
+        PeriodState::Blocked(_) => "bg-red-500 text-white",
+        _ => "bg-blue-500 text-white",
+    };
+
     let len = match props.period {
         PeriodState::ActualUnbonded => None,
         PeriodState::Actual(l) => Some(l),
         PeriodState::Planned(l) => Some(l),
+        PeriodState::Occurrence(l, _) => Some(l),
+        PeriodState::Proposed(l) => Some(l),
+        PeriodState::Blocked(l) => Some(l),
     };
 
     let style = match len {
@@ -29,12 +48,12 @@ pub fn Period(cx: Scope, props: PeriodProps) -> impl IntoView {
         Some(l) => format!("height: {}rem", l.deref()),
     };
 
-    div(cx).prop("style", style).classes(
-        "bg-blue-500 text-white
+    div(cx).prop("style", style).classes(format!(
+        "{colors}
 p-1
 rounded-md
 shadow-sm
 shadow-gray-400
-",
-    )
+"
+    ))
 }
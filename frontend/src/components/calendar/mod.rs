@@ -1,11 +1,17 @@
+use std::collections::HashSet;
 use std::iter::repeat;
 use std::ops::Deref;
 
+use crate::utils::date::utc_to_local;
 use crate::{components::calendar::day::Day, gui_error::GuiResult};
-use chrono::{NaiveDate, NaiveDateTime, Utc};
+use chrono::{NaiveDate, NaiveDateTime};
+use chrono_tz::Tz;
+use core_logic::dependencies::Dependencies;
+use core_logic::scheduler::Proposal;
 use leptos::*;
 use leptos_dom::html::div;
-use wire::state::Todo;
+use uuid::Uuid;
+use wire::state::{todo_index, Todo};
 use yrs_wrappers::{yrs_vec::YrsVec, yrs_wrapper_error::YrsResult};
 
 use self::day::{length::TimeLength, period::PeriodState, DayProps, PeriodWithOffset};
@@ -19,74 +25,86 @@ pub struct Calendar {
 }
 
 impl Calendar {
+    /// `start_day`, and the days and offsets of the periods, are in `tz`.
     pub fn days_prop_from_todo_datas_and_start_date(
         todos: &YrsVec<Todo>,
         txn: &impl yrs::ReadTxn,
         start_day: NaiveDate,
+        tz: Tz,
     ) -> YrsResult<Vec<Vec<PeriodWithOffset>>> {
         let mut days: Vec<Vec<PeriodWithOffset>> = repeat(Vec::new()).take(7).collect();
 
+        let index = todo_index(todos, txn)?;
+        let blocked = Dependencies::pending(&index, txn)?
+            .blocked_executions(&index, txn)?
+            .into_iter()
+            .map(|blocked| (blocked.todo, blocked.execution))
+            .collect();
+        Self::add_todos(&mut days, todos, txn, start_day, tz, &blocked)?;
+
+        Ok(days)
+    }
+
+    /// Adds the periods of `todos`, and of their descendants, to `days`. The planned executions
+    /// in `blocked`, by todo and position, are shown as blocked.
+    fn add_todos(
+        days: &mut [Vec<PeriodWithOffset>],
+        todos: &YrsVec<Todo>,
+        txn: &impl yrs::ReadTxn,
+        start_day: NaiveDate,
+        tz: Tz,
+        blocked: &HashSet<(Uuid, u32)>,
+    ) -> YrsResult<()> {
         let end_day = start_day + chrono::Duration::days(7);
         let within_week = |d| d >= start_day && d < end_day;
-        let midnight_before = |d: NaiveDateTime| -> NaiveDateTime {
-            d.date()
-                .and_hms_opt(0, 0, 0)
-                .unwrap()
-                .and_local_timezone(Utc)
-                .unwrap()
-                .naive_utc()
-        };
+        let local = |d: NaiveDateTime| utc_to_local(tz, d);
+        let midnight_before =
+            |d: NaiveDateTime| -> NaiveDateTime { d.date().and_hms_opt(0, 0, 0).unwrap() };
 
         for todo in todos.iter(txn) {
             let todo = todo?;
-            todo.planned_executions(txn)?
-                .iter(txn)
-                .map(|pe| {
-                    let pe = pe?;
-                    let start = pe.start(txn)?.date();
-                    Ok((pe, start))
-                })
-                .collect::<Result<Vec<_>, _>>()?
-                .into_iter()
-                .filter_map(|(pe, start)| if within_week(start) { Some(pe) } else { None })
-                .map(|ae| {
-                    let day_index = (ae.start(txn)?.date() - start_day).num_days() as usize;
-                    days[day_index].push({
-                        PeriodWithOffset {
-                            period: PeriodState::Planned(TimeLength::from(
-                                *ae.end(txn)? - *ae.start(txn)?,
-                            )),
-                            offset: TimeLength::from(
-                                *ae.start(txn)? - midnight_before(*ae.start(txn)?),
-                            ),
-                        }
-                    });
-                    Ok(())
-                })
-                .collect::<Result<(), _>>()?;
+            let id = *todo.id(txn)?;
+            for (position, pe) in todo.planned_executions(txn)?.iter(txn).enumerate() {
+                let pe = pe?;
+                let start = local(*pe.start(txn)?);
+                if !within_week(start.date()) {
+                    continue;
+                }
+                let day_index = (start.date() - start_day).num_days() as usize;
+                let length = TimeLength::from(*pe.end(txn)? - *pe.start(txn)?);
+                days[day_index].push(PeriodWithOffset {
+                    period: if blocked.contains(&(id, position as u32)) {
+                        PeriodState::Blocked(length)
+                    } else {
+                        PeriodState::Planned(length)
+                    },
+                    offset: TimeLength::from(start - midnight_before(start)),
+                });
+            }
 
             todo.actual_executions(txn)?
                 .iter(txn)
                 .map(|ae| {
                     let ae = ae?;
-                    let start = ae.start(txn)?.date();
+                    let start = local(*ae.start(txn)?).date();
                     Ok((ae, start))
                 })
                 .collect::<Result<Vec<_>, _>>()?
                 .into_iter()
                 .filter_map(|(pe, start)| if within_week(start) { Some(pe) } else { None })
                 .map(|ae| {
-                    let start = ae.start(txn)?;
-                    let day_index = (start.date() - start_day).num_days() as usize;
+                    let start = *ae.start(txn)?;
+                    let local_start = local(start);
+                    let day_index = (local_start.date() - start_day).num_days() as usize;
 
                     let period = match ae.end(txn) {
-                        Some(end) => PeriodState::Actual(TimeLength::from(*end? - *start)),
+                        Some(end) => PeriodState::Actual(TimeLength::from(*end? - start)),
                         None => PeriodState::ActualUnbonded,
                     };
                     days[day_index].push({
                         PeriodWithOffset {
                             period,
-                            offset: TimeLength::from(*start - midnight_before(*start)),
+                            offset: TimeLength::from(local_start - midnight_before(local_start)),
                         }
                     });
 
@@ -94,18 +112,60 @@ impl Calendar {
                 })
                 .collect::<Result<(), _>>()?;
 
-            let days_from_child_todos = Self::days_prop_from_todo_datas_and_start_date(
+            // A day of slack on both sides covers every time zone.
+            let from = (start_day - chrono::Duration::days(1))
+                .and_hms_opt(0, 0, 0)
+                .unwrap();
+            let to = (end_day + chrono::Duration::days(1))
+                .and_hms_opt(0, 0, 0)
+                .unwrap();
+            for occurrence in todo.occurrences(txn, from, to)? {
+                let start = local(occurrence.start);
+                if !within_week(start.date()) {
+                    continue;
+                }
+                let day_index = (start.date() - start_day).num_days() as usize;
+                days[day_index].push(PeriodWithOffset {
+                    period: PeriodState::Occurrence(
+                        TimeLength::from(occurrence.end - occurrence.start),
+                        occurrence.status,
+                    ),
+                    offset: TimeLength::from(start - midnight_before(start)),
+                });
+            }
+
+            Self::add_todos(
+                days,
                 todo.child_todos(txn)?.deref().deref(),
                 txn,
                 start_day,
-            );
-
-            days.iter_mut()
-                .zip(days_from_child_todos?)
-                .for_each(|(day, day_from_child_todo)| day.extend(day_from_child_todo))
+                tz,
+                blocked,
+            )?;
         }
 
-        Ok(days)
+        Ok(())
+    }
+
+    /// Adds the executions of a proposal that hasn't been committed yet to the `days` starting at
+    /// `start_day`.
+    pub fn add_proposal(
+        days: &mut [Vec<PeriodWithOffset>],
+        proposal: &Proposal,
+        start_day: NaiveDate,
+        tz: Tz,
+    ) {
+        for execution in &proposal.executions {
+            let start = utc_to_local(tz, execution.start);
+            let day_index = (start.date() - start_day).num_days();
+            if day_index < 0 || day_index >= days.len() as i64 {
+                continue;
+            }
+            days[day_index as usize].push(PeriodWithOffset {
+                period: PeriodState::Proposed(TimeLength::from(execution.end - execution.start)),
+                offset: TimeLength::from(start - start.date().and_hms_opt(0, 0, 0).unwrap()),
+            });
+        }
     }
 
     pub fn view(self, cx: Scope) -> GuiResult<impl IntoView> {
@@ -133,10 +193,19 @@ impl Calendar {
 
 #[cfg(test)]
 mod tests {
-    use chrono::{Duration, TimeZone, Timelike, Utc};
-    use wire::state::{ActualExecutionPrelim, PlannedExecutionPrelim, StatePrelim, TodoPrelim};
+    use chrono::{Duration, NaiveDate, TimeZone, Timelike, Utc};
+    use chrono_tz::{America::New_York, Tz};
+    use core_logic::scheduler::{Proposal, ProposedExecution};
+    use uuid::Uuid;
+    use wire::state::recurrence::OccurrenceStatus;
+    use wire::state::{
+        ActualExecutionPrelim, FrequencyPrelim, PlannedExecutionPrelim, RecurrenceEndPrelim,
+        RecurrencePrelim, StatePrelim, Todo, TodoPrelim,
+    };
     use yrs::{Map, TextPrelim, Transact};
-    use yrs_wrappers::{ybox::YBox, yrs_wrapper_error::YrsResult};
+    use yrs_wrappers::{
+        ybox::YBox, yref::YRefPrelim, yrs_basic_types::YIdPrelim, yrs_wrapper_error::YrsResult,
+    };
 
     use crate::components::calendar::day::{
         length::TimeLength, period::PeriodState, PeriodWithOffset,
@@ -150,11 +219,7 @@ mod tests {
 
         let state_prelim = StatePrelim {
             todos: vec![TodoPrelim {
-                title: TextPrelim::new("My only TODO".into()),
                 text: TextPrelim::new("My only TODO".into()),
-                completed: false.into(),
-                created_at: start_date.naive_utc().into(),
-                estimated_duration: Duration::hours(10).into(),
                 planned_executions: vec![PlannedExecutionPrelim {
                     start: start_date.naive_utc().into(),
                     end: (|| start_date.with_hour(9)?.with_minute(45))()
@@ -170,11 +235,7 @@ mod tests {
                 .into(),
                 child_todos: YBox::new(
                     vec![TodoPrelim {
-                        title: TextPrelim::new("My child TODO".into()),
                         text: TextPrelim::new("My child TODO".into()),
-                        completed: false.into(),
-                        created_at: (start_date + Duration::days(1)).naive_utc().into(),
-                        estimated_duration: Duration::hours(7).into(),
                         planned_executions: vec![PlannedExecutionPrelim {
                             start: (start_date + Duration::days(1)).naive_utc().into(),
                             end: ((|| start_date.with_hour(9)?.with_minute(45))().unwrap()
@@ -183,13 +244,15 @@ mod tests {
                             .into(),
                         }]
                         .into(),
-                        actual_executions: vec![].into(),
-                        child_todos: YBox::new(vec![].into()),
-                        deadline: None,
+                        ..TodoPrelim::new(
+                            "My child TODO",
+                            (start_date + Duration::days(1)).naive_utc(),
+                            Duration::hours(7),
+                        )
                     }]
                     .into(),
                 ),
-                deadline: None,
+                ..TodoPrelim::new("My only TODO", start_date.naive_utc(), Duration::hours(10))
             }]
             .into(),
         };
@@ -203,6 +266,7 @@ mod tests {
             &state.todos(&txn)?,
             &mut txn,
             start_date.naive_utc().date(),
+            Tz::UTC,
         )?;
 
         assert!(days[2..].iter().all(|day| day.is_empty()));
@@ -235,6 +299,147 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_calendar_shows_occurrences() -> YrsResult<()> {
+        let start_date = Utc.with_ymd_and_hms(2023, 5, 1, 8, 0, 0).unwrap();
+
+        let state_prelim = StatePrelim {
+            todos: vec![TodoPrelim {
+                recurrence: Some(RecurrencePrelim {
+                    start: start_date.with_timezone(&Tz::UTC).into(),
+                    frequency: FrequencyPrelim::Daily,
+                    interval: 1.into(),
+                    by_weekday: vec![].into(),
+                    end: Some(RecurrenceEndPrelim::Count(3.into())),
+                    overrides: Default::default(),
+                }),
+                ..TodoPrelim::new("Stretch", start_date.naive_utc(), Duration::minutes(30))
+            }]
+            .into(),
+        };
+
+        let doc = yrs::Doc::new();
+        let map = doc.get_or_insert_map("map");
+        let mut txn = doc.try_transact_mut().unwrap();
+        let state = map.insert(&mut txn, "state", state_prelim);
+
+        let days = Calendar::days_prop_from_todo_datas_and_start_date(
+            &state.todos(&txn)?,
+            &mut txn,
+            (start_date + Duration::days(1)).naive_utc().date(),
+            Tz::UTC,
+        )?;
+
+        let occurrence = PeriodWithOffset {
+            period: PeriodState::Occurrence(
+                TimeLength::from(Duration::minutes(30)),
+                OccurrenceStatus::Pending,
+            ),
+            offset: TimeLength::from(Duration::hours(8)),
+        };
+        assert_eq!(days[0], vec![occurrence.clone()]);
+        assert_eq!(days[1], vec![occurrence]);
+        assert!(days[2..].iter().all(|day| day.is_empty()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_calendar_shows_blocked_executions() -> YrsResult<()> {
+        let start_date = Utc.with_ymd_and_hms(2023, 5, 1, 8, 0, 0).unwrap();
+        let todo = |id: YIdPrelim, hour, blocked_by: Vec<YRefPrelim<Todo>>| TodoPrelim {
+            id,
+            planned_executions: vec![PlannedExecutionPrelim {
+                start: start_date.with_hour(hour).unwrap().naive_utc().into(),
+                end: start_date.with_hour(hour + 1).unwrap().naive_utc().into(),
+            }]
+            .into(),
+            blocked_by: Some(blocked_by.into()),
+            ..TodoPrelim::new("", start_date.naive_utc(), Duration::hours(1))
+        };
+        let (blocker, blocked) = (YIdPrelim::new(), YIdPrelim::new());
+
+        let doc = yrs::Doc::new();
+        let map = doc.get_or_insert_map("map");
+        let mut txn = doc.try_transact_mut().unwrap();
+        let state = map.insert(
+            &mut txn,
+            "state",
+            StatePrelim {
+                todos: vec![
+                    todo(blocker, 9, vec![]),
+                    todo(blocked, 8, vec![YRefPrelim::new(blocker.id())]),
+                ]
+                .into(),
+            },
+        );
+
+        let days = Calendar::days_prop_from_todo_datas_and_start_date(
+            &state.todos(&txn)?,
+            &mut txn,
+            start_date.naive_utc().date(),
+            Tz::UTC,
+        )?;
+
+        let length = TimeLength::from(Duration::hours(1));
+        assert_eq!(
+            days[0],
+            vec![
+                PeriodWithOffset {
+                    period: PeriodState::Planned(length.clone()),
+                    offset: TimeLength::from(Duration::hours(9)),
+                },
+                PeriodWithOffset {
+                    period: PeriodState::Blocked(length),
+                    offset: TimeLength::from(Duration::hours(8)),
+                },
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_calendar_shows_proposal() {
+        let start_day = NaiveDate::from_ymd_opt(2023, 5, 1).unwrap();
+        let at = |day, hour| {
+            (start_day + Duration::days(day))
+                .and_hms_opt(hour, 0, 0)
+                .unwrap()
+        };
+        let proposal = Proposal {
+            executions: vec![
+                ProposedExecution {
+                    todo: Uuid::nil(),
+                    start: at(-1, 9),
+                    end: at(-1, 10),
+                },
+                ProposedExecution {
+                    todo: Uuid::nil(),
+                    start: at(2, 3),
+                    end: at(2, 5),
+                },
+            ],
+            infeasible: vec![],
+        };
+
+        let mut days = vec![Vec::new(); 7];
+        // In New York, 3:00 UTC is still the day before.
+        Calendar::add_proposal(&mut days, &proposal, start_day, New_York);
+
+        assert_eq!(
+            days[1],
+            vec![PeriodWithOffset {
+                period: PeriodState::Proposed(TimeLength::from(Duration::hours(2))),
+                offset: TimeLength::from(Duration::hours(23)),
+            }]
+        );
+        assert!(days
+            .iter()
+            .enumerate()
+            .all(|(i, day)| i == 1 || day.is_empty()));
+    }
 }
 
 impl IntoView for Calendar {
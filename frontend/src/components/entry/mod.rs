@@ -1,5 +1,6 @@
 use crate::use_doc::use_doc;
-use crate::utils::date::parse_input_datetime;
+use crate::use_undo::use_undo;
+use crate::utils::date::{local_timezone, local_to_utc, parse_input_datetime};
 use chrono::NaiveDateTime;
 use yrs::GetString;
 use yrs::Transact;
@@ -11,6 +12,7 @@ use wire::state::{ActualExecutionPrelim, PlannedExecutionPrelim, Todo};
 use crate::components::button::Button;
 use leptos::html::*;
 use leptos::*;
+use yrs_wrappers::yref::YIndex;
 use yrs_wrappers::yrs_wrapper_error::YrsResult;
 
 use self::entry_type::{EntryType, EntryTypeState};
@@ -46,6 +48,7 @@ pub struct Entry {
     pub entry: DraftEntry,
     pub on_save: Rc<dyn Fn(DraftEntry)>,
     pub flattened_todos: Signal<YrsResult<Vec<Todo>>>,
+    pub todo_index: Signal<YrsResult<YIndex<Todo>>>,
 }
 
 impl Entry {
@@ -59,6 +62,13 @@ impl Entry {
             estimated_duration,
             parent_todo,
         } = self.entry;
+        let todo_index = self.todo_index;
+
+        // The parent is kept by ID, since its position can change while the form is open.
+        let resolved_parent_todo = move || -> Option<Todo> {
+            let id = parent_todo.get()?;
+            todo_index.get().ok()?.get(*id).cloned()
+        };
 
         let datetime_input_props = Some(
             [("type".to_string(), "datetime-local".to_string())]
@@ -66,12 +76,14 @@ impl Entry {
                 .collect(),
         );
 
+        // The inputs are in local time, while the document stores UTC.
+        let tz = local_timezone();
         let new_entry_to_save = Signal::derive(cx, move || {
             let data = (
                 type_.get(),
-                parse_input_datetime(&start_datetime.get()),
-                parse_input_datetime(&end_datetime.get()),
-                parent_todo.get(),
+                parse_input_datetime(&start_datetime.get()).map(|d| local_to_utc(tz, d)),
+                parse_input_datetime(&end_datetime.get()).map(|d| local_to_utc(tz, d)),
+                resolved_parent_todo(),
             );
 
             match data {
@@ -101,8 +113,12 @@ impl Entry {
                 EntryTypeState::PlannedExecution | EntryTypeState::ActualExecution => {
                     let select_el = select::Select {
                         options: self.flattened_todos.get()?.into(),
-                        selected: parent_todo.into(),
-                        on_select: Rc::new(move |item| parent_todo.set(Some(item.clone()))),
+                        selected: Signal::derive(cx, resolved_parent_todo),
+                        on_select: Rc::new(move |item| {
+                            let doc = use_doc(cx);
+                            let txn = doc.transact();
+                            parent_todo.set(item.id(&txn).ok());
+                        }),
                         render_option: Rc::new(move |i| {
                             let doc = use_doc(cx);
                             let txn = doc.transact();
@@ -170,44 +186,47 @@ impl Entry {
                     .child("Add")
                     .on(ev::click, move |_| {
                         let doc = use_doc(cx);
-                        let mut txn = doc.try_transact_mut().unwrap();
                         let new_entry_to_save = new_entry_to_save
                             .get()
                             .expect("Button should be disabled otherwise.");
-                        cx.batch(move || {
-                            match new_entry_to_save {
-                                NewEntryToSave::PlannedExecution {
-                                    start_datetime,
-                                    end_datetime,
-                                    parent_todo,
-                                } => {
-                                    let executions = parent_todo.planned_executions(&txn)?;
-                                    executions.push(
-                                        &mut txn,
-                                        PlannedExecutionPrelim {
-                                            start: start_datetime.into(),
-                                            end: end_datetime.into(),
-                                        },
-                                    );
-                                }
-                                NewEntryToSave::ActualExecution {
-                                    start_datetime,
-                                    end_datetime,
-                                    parent_todo,
-                                } => {
-                                    let executions = parent_todo.actual_executions(&txn)?;
-                                    executions.push(
-                                        &mut txn,
-                                        ActualExecutionPrelim {
-                                            start: start_datetime.into(),
-                                            end: Some(end_datetime.into()),
-                                        },
-                                    );
-                                }
-                            };
-                            YrsResult::Ok(())
-                        })
-                        .unwrap();
+                        let result = cx.batch(move || {
+                            use_undo(cx).action(&doc, |txn| {
+                                match new_entry_to_save {
+                                    NewEntryToSave::PlannedExecution {
+                                        start_datetime,
+                                        end_datetime,
+                                        parent_todo,
+                                    } => {
+                                        let executions = parent_todo.planned_executions(txn)?;
+                                        executions.push(
+                                            txn,
+                                            PlannedExecutionPrelim {
+                                                start: start_datetime.into(),
+                                                end: end_datetime.into(),
+                                            },
+                                        );
+                                    }
+                                    NewEntryToSave::ActualExecution {
+                                        start_datetime,
+                                        end_datetime,
+                                        parent_todo,
+                                    } => {
+                                        let executions = parent_todo.actual_executions(txn)?;
+                                        executions.push(
+                                            txn,
+                                            ActualExecutionPrelim {
+                                                start: start_datetime.into(),
+                                                end: Some(end_datetime.into()),
+                                            },
+                                        );
+                                    }
+                                };
+                                YrsResult::Ok(())
+                            })
+                        });
+                        if let Some(Err(err)) = result {
+                            tracing::error!("Couldn't add the entry: {}", err);
+                        }
                     }),
                 ),
             )
@@ -7,6 +7,8 @@ pub mod entry;
 pub mod navigate;
 pub mod page;
 pub mod popover;
+pub mod schedule_preview;
 pub mod select;
 pub mod text_input;
+pub mod todo_list;
 pub mod topbar;
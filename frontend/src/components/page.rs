@@ -1,8 +1,9 @@
-use wire::state::StatePrelim;
-use wire::state::Todo;
-use yrs::Map;
+use core_logic::scheduler::Proposal;
+use wire::state::migration::init_state;
+use wire::state::{todo_index, StatePrelim};
 use yrs::TextPrelim;
 use yrs::Transact;
+use yrs_wrappers::yrs_basic_types::YId;
 use yrs_wrappers::yrs_display::YrsDisplay;
 use yrs_wrappers::yrs_vec::YrsVecPrelim;
 use yrs_wrappers::yrs_wrapper_error::YrsResult;
@@ -12,15 +13,19 @@ use chrono::offset::TimeZone;
 use chrono::{Duration, Timelike, Utc};
 use leptos::html::*;
 use leptos::*;
+use wasm_bindgen::JsCast;
 use wire::state::{ActualExecutionPrelim, PlannedExecutionPrelim, TodoPrelim};
 
 use super::calendar::Calendar;
 use super::duration::{DurationState, DurationType};
 use super::entry::entry_type::EntryTypeState;
+use super::todo_list::TodoList;
 use super::topbar::TopBar;
 use crate::gui_error::GuiResult;
-use crate::leptos_utils::yrs::YrsSignal;
+use crate::leptos_utils::yrs::YrsListSignal;
 use crate::use_doc::use_doc;
+use crate::use_undo::Undo;
+use crate::utils::date::local_timezone;
 
 #[derive(Clone, Debug)]
 pub struct DraftEntry {
@@ -30,7 +35,7 @@ pub struct DraftEntry {
     pub end_datetime: RwSignal<String>,
     pub completed_at: RwSignal<String>,
     pub estimated_duration: DurationState,
-    pub parent_todo: RwSignal<Option<Todo>>,
+    pub parent_todo: RwSignal<Option<YId>>,
 }
 
 impl DraftEntry {
@@ -66,11 +71,7 @@ pub fn Page(cx: Scope) -> GuiResult<HtmlElement<Div>> {
 
     let state = StatePrelim {
         todos: vec![TodoPrelim {
-            title: TextPrelim::new("My only TODO".into()),
             text: TextPrelim::new("My only TODO".into()),
-            completed: false.into(),
-            created_at: test_start_date.into(),
-            estimated_duration: Duration::hours(10).into(),
             planned_executions: vec![PlannedExecutionPrelim {
                 start: test_start_date.with_hour(10).unwrap().into(),
                 end: test_start_date
@@ -94,33 +95,59 @@ pub fn Page(cx: Scope) -> GuiResult<HtmlElement<Div>> {
             }]
             .into(),
             child_todos: Box::new(YrsVecPrelim::from(vec![TodoPrelim {
-                title: TextPrelim::new("My child TODO".into()),
                 text: TextPrelim::new("My child TODO".into()),
-                completed: false.into(),
-                created_at: test_start_date.into(),
-                estimated_duration: Duration::hours(10).into(),
-                planned_executions: vec![].into(),
-                actual_executions: vec![].into(),
-                child_todos: Box::new(YrsVecPrelim::from(vec![])).into(),
-                deadline: None,
+                ..TodoPrelim::new("My child TODO", test_start_date, Duration::hours(10))
             }]))
             .into(),
-
-            deadline: None,
+            ..TodoPrelim::new("My only TODO", test_start_date, Duration::hours(10))
         }]
         .into(),
     };
 
     let doc = use_doc(cx);
     let mut txn = doc.try_transact_mut().unwrap();
-    let state = root.insert(&mut txn, "state", state);
-    // let todos = create_rw_signal(cx, state.todos(&txn)?);
-    let todos = YrsSignal::new(cx, use_doc(cx), state.todos(&txn)?);
+    let state = init_state(&root, &mut txn, state);
+    let todos = YrsListSignal::new(cx, use_doc(cx), state.todos(&txn)?, &txn)?;
     drop(txn);
 
+    let undo = Undo::new(cx, &doc, &state);
+    leptos::provide_context(cx, undo.clone());
+    window_event_listener("keydown", move |event| {
+        let event = event.unchecked_into::<web_sys::KeyboardEvent>();
+        if !(event.ctrl_key() || event.meta_key()) || !event.key().eq_ignore_ascii_case("z") {
+            return;
+        }
+        // Text fields have their own undo.
+        let in_text_field = event
+            .target()
+            .and_then(|target| target.dyn_into::<web_sys::Element>().ok())
+            .map_or(false, |el| {
+                matches!(el.tag_name().as_str(), "INPUT" | "TEXTAREA")
+            });
+        if in_text_field {
+            return;
+        }
+
+        event.prevent_default();
+        if event.shift_key() {
+            undo.redo();
+        } else {
+            undo.undo();
+        }
+    });
+
+    let tz = local_timezone();
+    let proposal = create_rw_signal(cx, None::<Proposal>);
     let seven_days = todos.derive(cx, move |todos, txn| {
         tracing::info!("{}", todos.fmt(txn).unwrap());
-        Calendar::days_prop_from_todo_datas_and_start_date(&todos, txn, start_day.get())
+        let mut days =
+            Calendar::days_prop_from_todo_datas_and_start_date(&todos, txn, start_day.get(), tz)?;
+        proposal.with(|proposal| {
+            if let Some(proposal) = proposal {
+                Calendar::add_proposal(&mut days, proposal, start_day.get(), tz);
+            }
+        });
+        YrsResult::Ok(days)
     });
 
     // Auto-fill the start and end datetime fields with the start date corresponding to the day
@@ -135,24 +162,25 @@ pub fn Page(cx: Scope) -> GuiResult<HtmlElement<Div>> {
 
     let flattened_todos = todos.derive(cx, |todos, txn| {
         let mut flattened_todos = vec![];
-        for todo in todos.iter(txn) {
-            let todo = todo?;
-            let child_todos = todo
-                .child_todos(txn)?
-                .iter(txn)
-                .collect::<YrsResult<Vec<_>>>()?;
+        for todo in todos.in_order(txn)? {
+            let child_todos = todo.child_todos(txn)?.in_order(txn)?;
             flattened_todos.push(todo);
             flattened_todos.extend(child_todos);
         }
         YrsResult::Ok(flattened_todos)
     });
+    let todo_index = todos.derive(cx, |todos, txn| todo_index(&todos, txn));
 
     Ok(div(cx)
         .child(TopBar {
             entry,
             start_day,
             flattened_todos,
+            todo_index,
+            todos: todos.clone(),
+            proposal,
         })
+        .child(TodoList { todos })
         .child(Calendar {
             seven_days,
             start_day: start_day.into(),
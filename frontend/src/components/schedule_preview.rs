@@ -0,0 +1,148 @@
+use chrono::{Duration, Utc};
+use chrono_tz::Tz;
+use core_logic::scheduler::{schedule, Interval, Proposal, SchedulerSettings};
+use leptos::html::*;
+use leptos::*;
+use wire::state::Todo;
+use yrs::{GetString, Transact};
+use yrs_wrappers::yref::YIndex;
+use yrs_wrappers::yrs_wrapper_error::YrsResult;
+
+use super::button::Button;
+use crate::leptos_utils::yrs::YrsListSignal;
+use crate::use_doc::use_doc;
+use crate::use_undo::use_undo;
+use crate::utils::date::{local_timezone, local_to_utc, utc_to_local};
+
+/// Lets the scheduler propose planned executions, which the calendar shows until they're applied
+/// or discarded.
+pub struct SchedulePreview {
+    pub todos: YrsListSignal<Todo>,
+    pub todo_index: Signal<YrsResult<YIndex<Todo>>>,
+    pub proposal: RwSignal<Option<Proposal>>,
+}
+
+impl SchedulePreview {
+    pub fn view(self, cx: Scope) -> impl IntoView {
+        let SchedulePreview {
+            todos,
+            todo_index,
+            proposal,
+        } = self;
+
+        let plan = move |_| {
+            let doc = use_doc(cx);
+            let txn = doc.transact();
+            let now = Utc::now().naive_utc();
+            let mut settings = SchedulerSettings::new(now);
+            settings.busy = nights(local_timezone(), &settings);
+            match schedule(&todos.get(), &txn, &settings) {
+                Ok(planned) => proposal.set(Some(planned)),
+                Err(err) => tracing::error!("Couldn't schedule: {}", err),
+            }
+        };
+
+        let apply = move |_| {
+            let planned = match proposal.get() {
+                Some(planned) => planned,
+                None => return,
+            };
+            let doc = use_doc(cx);
+            let result = todo_index.get().map(|index| {
+                cx.batch(|| use_undo(cx).action(&doc, |txn| planned.commit(&index, txn)))
+            });
+            match result {
+                // The action was dropped, so the proposal is kept to be applied again.
+                Ok(None) => return,
+                Ok(Some(Ok(()))) => {}
+                Ok(Some(Err(err))) | Err(err) => {
+                    tracing::error!("Couldn't apply the proposal: {}", err)
+                }
+            }
+            proposal.set(None);
+        };
+
+        let infeasible = move || -> YrsResult<Vec<String>> {
+            let doc = use_doc(cx);
+            let txn = doc.transact();
+            let index = todo_index.get()?;
+            proposal.with(|planned| {
+                planned
+                    .iter()
+                    .flat_map(|planned| &planned.infeasible)
+                    .map(|infeasible| {
+                        let title = match index.get(infeasible.todo) {
+                            Some(todo) => todo.title(&txn)?.get_string(&txn),
+                            None => infeasible.todo.to_string(),
+                        };
+                        Ok(format!("{}: {}", title, infeasible.reason))
+                    })
+                    .collect()
+            })
+        };
+
+        div(cx).classes("flex items-center gap-2").child(move || {
+            if proposal.with(Option::is_none) {
+                return button(cx)
+                    .classes("border border-gray-200 rounded-md px-2 py-1")
+                    .on(ev::click, plan.clone())
+                    .child("Plan Two Weeks")
+                    .into_view(cx);
+            }
+
+            let count = proposal.with(|planned| planned.as_ref().map_or(0, |p| p.executions.len()));
+            let problems = match infeasible() {
+                Ok(problems) => problems,
+                Err(err) => vec![err.to_string()],
+            };
+            div(cx)
+                .classes("flex items-center gap-2")
+                .child(format!("{} proposed executions", count))
+                .child(
+                    ul(cx).classes("text-sm text-red-700").child(
+                        problems
+                            .into_iter()
+                            .map(|problem| li(cx).child(problem))
+                            .collect::<Vec<_>>(),
+                    ),
+                )
+                .child(
+                    Button {
+                        disabled: false.into(),
+                    }
+                    .view(cx)
+                    .child("Apply")
+                    .on(ev::click, apply),
+                )
+                .child(
+                    button(cx)
+                        .classes("border border-gray-200 rounded-md px-2 py-1")
+                        .on(ev::click, move |_| proposal.set(None))
+                        .child("Discard"),
+                )
+                .into_view(cx)
+        })
+    }
+}
+
+impl IntoView for SchedulePreview {
+    fn into_view(self, cx: Scope) -> View {
+        self.view(cx).into_view(cx)
+    }
+}
+
+/// There's no way to enter busy periods yet, so for now the nights (22:00 to 8:00 in `tz`) are
+/// kept free.
+fn nights(tz: Tz, settings: &SchedulerSettings) -> Vec<Interval> {
+    let first = utc_to_local(tz, settings.now).date() - Duration::days(1);
+    let last = utc_to_local(tz, settings.now + settings.horizon).date();
+    first
+        .iter_days()
+        .take_while(|day| *day <= last)
+        .filter_map(|day| {
+            let start = local_to_utc(tz, day.and_hms_opt(22, 0, 0)?);
+            let end = local_to_utc(tz, day.succ_opt()?.and_hms_opt(8, 0, 0)?);
+            Some(Interval::new(start, end))
+        })
+        .collect()
+}
@@ -0,0 +1,154 @@
+use leptos::html::*;
+use leptos::*;
+use wire::state::Todo;
+use yrs::GetString;
+use yrs_wrappers::yrs_wrapper_error::YrsResult;
+
+use crate::leptos_utils::yrs::{YrsListSignal, YrsSignal};
+use crate::use_doc::use_doc;
+use crate::use_undo::use_undo;
+
+/// The todos, each followed by its child todos, in the order the user put them in. A todo can be
+/// moved by dragging it onto another one in the same list.
+///
+/// The rows are only rebuilt when todos are added, removed or moved. Other changes to a todo only
+/// rerender the rows of that todo.
+pub struct TodoList {
+    pub todos: YrsListSignal<Todo>,
+}
+
+/// Where a todo is: the index of its parent todo in the order of the todos, for child todos, and
+/// its index in the order of its list.
+type Place = (Option<u32>, u32);
+
+impl TodoList {
+    pub fn view(self, cx: Scope) -> impl IntoView {
+        let TodoList { todos } = self;
+
+        // The indices in the array of the todos, in order. Most changes to the todos leave it as
+        // it was, which the memo doesn't pass on.
+        let order = todos.derive(cx, |todos, txn| todos.order(txn));
+        let order = create_memo(cx, move |_| order.get());
+        let entries = todos.entries();
+
+        let dragged = create_rw_signal(cx, None::<Place>);
+
+        let rows = move || -> YrsResult<Vec<View>> {
+            let entries = entries.get();
+            let mut rows = vec![];
+            for (index, array_index) in (0..).zip(order.get()?) {
+                // Only missing while the elements of the list couldn't be read, which is logged.
+                let Some(entry) = entries.get(array_index as usize).cloned() else {
+                    continue;
+                };
+                let title = entry.derive(cx, |todo, txn| {
+                    YrsResult::Ok(todo.title(txn)?.get_string(txn))
+                });
+                let list = todos.clone();
+                let move_in_list = move |txn: &mut yrs::TransactionMut, from, to| {
+                    list.get().move_in_order(txn, from, to)
+                };
+                rows.push(row(cx, dragged, (None, index), title, move_in_list).into_view(cx));
+                rows.push(child_rows(cx, dragged, index, entry).into_view(cx));
+            }
+            Ok(rows)
+        };
+
+        ul(cx)
+            .classes("flex flex-col gap-1 px-4 py-2")
+            .child(move || match rows() {
+                Ok(rows) => rows.into_view(cx),
+                Err(err) => err.to_string().into_view(cx),
+            })
+    }
+}
+
+/// The rows of the child todos of `parent`, which is at `index` in the order of the todos. They
+/// are rebuilt whenever `parent` changes.
+fn child_rows(
+    cx: Scope,
+    dragged: RwSignal<Option<Place>>,
+    index: u32,
+    parent: YrsSignal<Todo>,
+) -> impl IntoView {
+    let titles = parent.derive(cx, |todo, txn| -> YrsResult<Vec<String>> {
+        todo.child_todos(txn)?
+            .in_order(txn)?
+            .iter()
+            .map(|child| Ok(child.title(txn)?.get_string(txn)))
+            .collect()
+    });
+
+    move || match titles.get() {
+        Ok(titles) => (0..)
+            .zip(titles)
+            .map(|(child_index, title)| {
+                let parent = parent.clone();
+                row(
+                    cx,
+                    dragged,
+                    (Some(index), child_index),
+                    Signal::derive(cx, move || Ok(title.clone())),
+                    move |txn, from, to| {
+                        parent
+                            .get()
+                            .child_todos(&*txn)?
+                            .move_in_order(txn, from, to)
+                    },
+                )
+            })
+            .collect::<Vec<_>>()
+            .into_view(cx),
+        Err(err) => err.to_string().into_view(cx),
+    }
+}
+
+/// A todo at `place`, which can be dragged onto the other ones in its list, and which they can be
+/// dragged onto. `move_in_list` moves the todo at one index in the order of the list to another.
+fn row(
+    cx: Scope,
+    dragged: RwSignal<Option<Place>>,
+    place: Place,
+    title: Signal<YrsResult<String>>,
+    move_in_list: impl Fn(&mut yrs::TransactionMut, u32, u32) -> YrsResult<()> + 'static,
+) -> HtmlElement<Li> {
+    let (parent, index) = place;
+    let drop_here = move || {
+        let (dragged_parent, source) = match dragged.get() {
+            Some(dragged) => dragged,
+            None => return,
+        };
+        dragged.set(None);
+        if dragged_parent != parent {
+            tracing::info!("Todos can only be moved within the list they're in.");
+            return;
+        }
+        // A todo dropped on a later one goes after it, and on an earlier one before it, so that
+        // it always ends up where the todo it was dropped on was.
+        let target = if index > source { index + 1 } else { index };
+        let doc = use_doc(cx);
+        let result = use_undo(cx).action(&doc, |txn| move_in_list(txn, source, target));
+        if let Some(Err(err)) = result {
+            tracing::error!("Couldn't move the todo: {}", err);
+        }
+    };
+
+    li(cx)
+        .classes(if parent.is_some() {
+            "pl-6 cursor-move"
+        } else {
+            "cursor-move"
+        })
+        .attr("draggable", "true")
+        .on(ev::dragstart, move |_| dragged.set(Some(place)))
+        // Dropping is only allowed on elements that cancel `dragover`.
+        .on(ev::dragover, |event| event.prevent_default())
+        .on(ev::drop, move |event| {
+            event.prevent_default();
+            drop_here();
+        })
+        .child(move || match title.get() {
+            Ok(title) => title,
+            Err(err) => err.to_string(),
+        })
+}
@@ -1,20 +1,27 @@
+use core_logic::scheduler::Proposal;
 use leptos::html::*;
 use leptos::*;
 use std::rc::Rc;
 use wire::state::Todo;
+use yrs_wrappers::yref::YIndex;
 use yrs_wrappers::yrs_wrapper_error::YrsResult;
 
 use crate::include_html;
+use crate::leptos_utils::yrs::YrsListSignal;
 
 use super::entry::Entry;
 use super::navigate::Navigate;
 use super::page::DraftEntry;
 use super::popover::Popover;
+use super::schedule_preview::SchedulePreview;
 
 pub struct TopBar {
     pub entry: DraftEntry,
     pub start_day: RwSignal<chrono::NaiveDate>,
     pub flattened_todos: Signal<YrsResult<Vec<Todo>>>,
+    pub todo_index: Signal<YrsResult<YIndex<Todo>>>,
+    pub todos: YrsListSignal<Todo>,
+    pub proposal: RwSignal<Option<Proposal>>,
 }
 
 impl TopBar {
@@ -23,6 +30,9 @@ impl TopBar {
             entry,
             start_day,
             flattened_todos,
+            todo_index,
+            todos,
+            proposal,
         } = self;
         div(cx)
             .classes(
@@ -37,10 +47,16 @@ bg-white z-10",
                 Entry {
                     entry,
                     flattened_todos,
+                    todo_index,
                     on_save: Rc::new(|_| {}),
                 }.view(cx)
                 ,
             ))
+            .child(SchedulePreview {
+                todos,
+                todo_index,
+                proposal,
+            })
             .child(Navigate { start_day })
     }
 }
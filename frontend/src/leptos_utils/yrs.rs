@@ -1,110 +1,240 @@
+use std::collections::HashMap;
 use std::{cell::RefCell, clone::Clone, rc::Rc};
+use yrs::types::{Branch, DeepObservable};
 use yrs::Transact;
 
 use leptos::*;
 use yrs::TransactionMut;
+use yrs_wrappers::try_from_yrs_value::TryFromYrsValue;
+use yrs_wrappers::yrs_struct::ObserveTyped;
+use yrs_wrappers::yrs_vec::YrsVec;
+use yrs_wrappers::yrs_wrapper_error::YrsResult;
 
+type DerivedUpdaters<T> = Rc<RefCell<Vec<Box<dyn Fn(T, &TransactionMut<'_>)>>>>;
+
+/// A yrs struct, which triggers rerendering when one of its fields changes, see `observe_typed`.
 #[derive(Clone)]
-pub struct YrsSignal<T: yrs::types::DeepObservable + Clone + 'static> {
+pub struct YrsSignal<T: ObserveTyped + Clone + 'static> {
     doc: yrs::Doc,
 
     inner: RwSignal<T>,
 
     /// I think, if I didn't want ot make YrsSignal::derive take &self, I could avoid using
     /// RefCell. But I want to just get it to work first.
-    derived_updaters: Rc<RefCell<Vec<Box<dyn Fn(T, &TransactionMut<'_>)>>>>,
+    derived_updaters: DerivedUpdaters<T>,
+
+    /// The value is observed until every clone of the signal is dropped.
+    _subscription: Rc<yrs::types::DeepEventsSubscription>,
 }
 
-impl<T: yrs::types::DeepObservable + Clone + 'static> YrsSignal<T> {
-    pub fn new(cx: Scope, doc: yrs::Doc, mut value: T) -> YrsSignal<T> {
+impl<T: ObserveTyped + Clone + 'static> YrsSignal<T> {
+    pub fn new(cx: Scope, doc: yrs::Doc, value: T) -> YrsSignal<T> {
+        Self::with_listener(cx, doc, value, |_| {})
+    }
+
+    /// Like `new`, but also calls `on_change` whenever the value changes.
+    fn with_listener(
+        cx: Scope,
+        doc: yrs::Doc,
+        mut value: T,
+        on_change: impl Fn(&TransactionMut<'_>) + 'static,
+    ) -> YrsSignal<T> {
         let inner = create_rw_signal(cx, value.clone());
-        let derived_updaters: Rc<RefCell<Vec<Box<dyn Fn(T, &TransactionMut<'_>)>>>> =
-            Rc::new(RefCell::new(vec![]));
+        let derived_updaters: DerivedUpdaters<T> = Rc::new(RefCell::new(vec![]));
 
-        // Call `update` on the signal to trigger rerendering when the yrs value changes.
+        // Call `update` on the signal to trigger rerendering when one of the fields changes.
         let derived_updaters2 = derived_updaters.clone();
         let value2 = value.clone();
-        let subscription = value.observe_deep(move |txn, _| {
-            tracing::info!("Yrs value changed, triggering rerender");
+        let subscription = value.observe_typed(move |txn, changes| {
+            match changes {
+                Ok(changes) if changes.is_empty() => return,
+                Ok(_) => tracing::info!("Yrs value changed, triggering rerender"),
+                // Rerender anyway, so that the error shows up wherever the value is read.
+                Err(err) => tracing::error!("Couldn't read the changes to a yrs value: {}", err),
+            }
             inner.update(|_| ());
             for updater in derived_updaters2.borrow().iter() {
                 updater(value2.clone(), txn);
             }
+            on_change(txn);
         });
 
-        // Maybe there's a way to tie the lifetime of the subscription to that of the signal, but
-        // for now we'll have to tie it to the life time of the Scope.
-        store_value(cx, subscription);
-
         YrsSignal {
             doc,
             inner,
             derived_updaters,
+            _subscription: Rc::new(subscription),
         }
     }
 
-    pub fn derive_yrs<N, F>(&self, cx: Scope, f: F) -> YrsSignal<N>
+    pub fn derive<N, F>(&self, cx: Scope, f: F) -> Signal<N>
     where
         F: Fn(T, &TransactionMut<'_>) -> N + 'static + Clone,
-        N: Clone + std::convert::AsMut<yrs::types::Branch>,
     {
-        let value = f(self.inner.get(), &mut self.doc.try_transact_mut().unwrap());
+        derive(cx, &self.doc, self.inner, &self.derived_updaters, f)
+    }
+
+    pub fn get(&self) -> T {
+        self.inner.get()
+    }
+
+    fn get_untracked(&self) -> T {
+        self.inner.get_untracked()
+    }
+}
 
-        let inner = create_rw_signal(cx, value);
+/// A `YrsVec` of yrs structs, with a `YrsSignal` for each of them, so that a change to one of
+/// them only reaches what was derived from it, or from the whole list. Only adding or removing
+/// elements changes `entries`.
+#[derive(Clone)]
+pub struct YrsListSignal<T>
+where
+    T: ObserveTyped + TryFromYrsValue + AsMut<Branch> + Clone + 'static,
+{
+    doc: yrs::Doc,
 
-        let f = Rc::new(f);
+    list: RwSignal<YrsVec<T>>,
 
-        let doc2 = self.doc.clone();
-        let original_inner = self.inner.clone();
-        let f2 = f.clone();
-        create_effect(cx, move |none_if_first| {
-            if let Some(_) = none_if_first {
-                let value = f2(original_inner.get(), &doc2.try_transact_mut().unwrap());
-                inner.set(value);
-            }
-        });
+    /// In the order of the array.
+    entries: RwSignal<Vec<YrsSignal<T>>>,
 
-        let signal = YrsSignal {
-            inner,
+    derived_updaters: DerivedUpdaters<YrsVec<T>>,
+}
+
+impl<T> YrsListSignal<T>
+where
+    T: ObserveTyped + TryFromYrsValue + AsMut<Branch> + Clone + 'static,
+{
+    pub fn new(
+        cx: Scope,
+        doc: yrs::Doc,
+        mut value: YrsVec<T>,
+        txn: &impl yrs::ReadTxn,
+    ) -> YrsResult<YrsListSignal<T>> {
+        let signal = YrsListSignal {
+            doc,
+            list: create_rw_signal(cx, value.clone()),
+            entries: create_rw_signal(cx, vec![]),
             derived_updaters: Rc::new(RefCell::new(vec![])),
-            doc: self.doc.clone(),
         };
+        signal.entries.set(signal.entries_of(cx, &value, txn)?);
+
+        // Only events on the array itself add or remove elements, the ones inside the elements
+        // are handled by their own signals.
+        let signal2 = signal.clone();
+        let subscription = value.observe_deep(move |txn, events| {
+            if !events.iter().any(|event| event.path().is_empty()) {
+                return;
+            }
+            tracing::info!("Elements were added to or removed from a yrs list");
+            let list = signal2.list.get();
+            match signal2.entries_of(cx, &list, txn) {
+                Ok(entries) => signal2.entries.set(entries),
+                Err(err) => tracing::error!("Couldn't read the elements of a yrs list: {}", err),
+            }
+            signal2.list.update(|_| ());
+            for updater in signal2.derived_updaters.borrow().iter() {
+                updater(list.clone(), txn);
+            }
+        });
 
-        self.derived_updaters
-            .borrow_mut()
-            .push(Box::new(move |new, txn| signal.inner.set(f(new, txn))));
+        // Maybe there's a way to tie the lifetime of the subscription to that of the signal, but
+        // for now we'll have to tie it to the life time of the Scope.
+        store_value(cx, subscription);
 
-        signal
+        Ok(signal)
     }
 
+    /// The signals of the elements of `list`, reusing the ones of the elements that were already
+    /// in it.
+    fn entries_of(
+        &self,
+        cx: Scope,
+        list: &YrsVec<T>,
+        txn: &impl yrs::ReadTxn,
+    ) -> YrsResult<Vec<YrsSignal<T>>> {
+        let mut existing = self
+            .entries
+            .get_untracked()
+            .into_iter()
+            .map(|entry| (branch_id(entry.get_untracked()), entry))
+            .collect::<HashMap<_, _>>();
+
+        list.iter(txn)
+            .map(|item| {
+                let item = item?;
+                if let Some(entry) = existing.remove(&branch_id(item.clone())) {
+                    return Ok(entry);
+                }
+                let list = self.list;
+                let derived_updaters = self.derived_updaters.clone();
+                Ok(YrsSignal::with_listener(
+                    cx,
+                    self.doc.clone(),
+                    item,
+                    move |txn| {
+                        list.update(|_| ());
+                        for updater in derived_updaters.borrow().iter() {
+                            updater(list.get_untracked(), txn);
+                        }
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    /// Rederived whenever any element changes.
     pub fn derive<N, F>(&self, cx: Scope, f: F) -> Signal<N>
     where
-        F: Fn(T, &TransactionMut<'_>) -> N + 'static + Clone,
+        F: Fn(YrsVec<T>, &TransactionMut<'_>) -> N + 'static + Clone,
     {
-        let value = f(self.inner.get(), &mut self.doc.try_transact_mut().unwrap());
+        derive(cx, &self.doc, self.list, &self.derived_updaters, f)
+    }
 
-        let signal = create_rw_signal(cx, value);
+    pub fn get(&self) -> YrsVec<T> {
+        self.list.get()
+    }
 
-        let f = Rc::new(f);
+    /// The signals of the elements, in the order of the array.
+    pub fn entries(&self) -> Signal<Vec<YrsSignal<T>>> {
+        self.entries.into()
+    }
+}
 
-        let doc2 = self.doc.clone();
-        let original_inner = self.inner.clone();
-        let f2 = f.clone();
-        create_effect(cx, move |none_if_first| {
-            if let Some(_) = none_if_first {
-                let value = f2(original_inner.get(), &doc2.try_transact_mut().unwrap());
-                signal.set(value);
-            }
-        });
+fn branch_id<T: AsMut<Branch>>(mut value: T) -> yrs::types::BranchID {
+    let branch: &mut Branch = value.as_mut();
+    branch.id()
+}
 
-        self.derived_updaters
-            .borrow_mut()
-            .push(Box::new(move |new, txn| signal.set(f(new, txn))));
+fn derive<T, N, F>(
+    cx: Scope,
+    doc: &yrs::Doc,
+    original_inner: RwSignal<T>,
+    derived_updaters: &DerivedUpdaters<T>,
+    f: F,
+) -> Signal<N>
+where
+    T: Clone + 'static,
+    F: Fn(T, &TransactionMut<'_>) -> N + 'static + Clone,
+{
+    let value = f(original_inner.get(), &mut doc.try_transact_mut().unwrap());
+
+    let signal = create_rw_signal(cx, value);
+
+    let f = Rc::new(f);
+
+    let doc2 = doc.clone();
+    let f2 = f.clone();
+    create_effect(cx, move |none_if_first| {
+        if let Some(_) = none_if_first {
+            let value = f2(original_inner.get(), &doc2.try_transact_mut().unwrap());
+            signal.set(value);
+        }
+    });
 
-        signal.into()
-    }
+    derived_updaters
+        .borrow_mut()
+        .push(Box::new(move |new, txn| signal.set(f(new, txn))));
 
-    pub fn get(&self) -> T {
-        self.inner.get()
-    }
+    signal.into()
 }
@@ -6,5 +6,6 @@ pub mod components;
 pub mod gui_error;
 pub mod leptos_utils;
 pub mod use_doc;
+pub mod use_undo;
 pub mod utils;
 mod yrs_persist;
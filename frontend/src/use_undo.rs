@@ -0,0 +1,71 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use leptos::*;
+use wire::state::undo::StateUndoManager;
+use wire::state::State;
+use yrs::TransactionMut;
+
+/// The undo manager of the page, along with whether there's anything to undo or redo.
+#[derive(Clone)]
+pub struct Undo {
+    manager: Rc<RefCell<StateUndoManager>>,
+    can_undo: RwSignal<bool>,
+    can_redo: RwSignal<bool>,
+}
+
+impl Undo {
+    pub fn new(cx: Scope, doc: &yrs::Doc, state: &State) -> Self {
+        Self {
+            manager: Rc::new(RefCell::new(StateUndoManager::new(doc, state))),
+            can_undo: create_rw_signal(cx, false),
+            can_redo: create_rw_signal(cx, false),
+        }
+    }
+
+    pub fn can_undo(&self) -> Signal<bool> {
+        self.can_undo.into()
+    }
+
+    pub fn can_redo(&self) -> Signal<bool> {
+        self.can_redo.into()
+    }
+
+    /// Runs `f` as one user action, which is undone and redone as a whole. Returns `None`, without
+    /// running `f`, if another transaction is open, which shouldn't happen while handling a user
+    /// action.
+    pub fn action<R>(&self, doc: &yrs::Doc, f: impl FnOnce(&mut TransactionMut) -> R) -> Option<R> {
+        let result = self.manager.borrow_mut().transact(doc, f);
+        if let Err(e) = &result {
+            tracing::error!("Failed to run the action: {e}");
+        }
+        self.refresh();
+        result.ok()
+    }
+
+    pub fn undo(&self) {
+        let undone = self.manager.borrow_mut().undo();
+        if let Err(e) = undone {
+            tracing::error!("Failed to undo: {e}");
+        }
+        self.refresh();
+    }
+
+    pub fn redo(&self) {
+        let redone = self.manager.borrow_mut().redo();
+        if let Err(e) = redone {
+            tracing::error!("Failed to redo: {e}");
+        }
+        self.refresh();
+    }
+
+    fn refresh(&self) {
+        let manager = self.manager.borrow();
+        self.can_undo.set(manager.can_undo());
+        self.can_redo.set(manager.can_redo());
+    }
+}
+
+pub fn use_undo(cx: Scope) -> Undo {
+    leptos::use_context::<Undo>(cx).unwrap()
+}
@@ -0,0 +1,42 @@
+use chrono::{NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+
+/// The IANA time zone of the browser, or UTC if it can't be determined.
+pub fn local_timezone() -> Tz {
+    let options = js_sys::Intl::DateTimeFormat::new(&js_sys::Array::new(), &js_sys::Object::new())
+        .resolved_options();
+
+    js_sys::Reflect::get(&options, &"timeZone".into())
+        .ok()
+        .and_then(|zone| zone.as_string())
+        .and_then(|zone| zone.parse().ok())
+        .unwrap_or(Tz::UTC)
+}
+
+/// Datetimes are stored in UTC, but are shown (and entered) in the time zone of the user. The
+/// reverse is `wire::time::local_to_utc`.
+pub fn utc_to_local(tz: Tz, utc: NaiveDateTime) -> NaiveDateTime {
+    tz.from_utc_datetime(&utc).naive_local()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use chrono_tz::{America::New_York, Tz};
+    use wire::time::local_to_utc;
+
+    use super::utc_to_local;
+
+    #[test]
+    fn test_round_trip() {
+        let utc = NaiveDate::from_ymd_opt(2023, 5, 1)
+            .unwrap()
+            .and_hms_opt(2, 30, 0)
+            .unwrap();
+        let local = utc_to_local(New_York, utc);
+
+        assert_eq!(local, utc - chrono::Duration::hours(4));
+        assert_eq!(local_to_utc(New_York, local), utc);
+        assert_eq!(utc_to_local(Tz::UTC, utc), utc);
+    }
+}
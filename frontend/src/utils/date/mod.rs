@@ -1,3 +1,6 @@
+mod local_timezone;
 mod parse_input_datetime;
 
+pub use local_timezone::{local_timezone, utc_to_local};
 pub use parse_input_datetime::parse_input_datetime;
+pub use wire::time::local_to_utc;
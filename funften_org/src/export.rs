@@ -0,0 +1,436 @@
+//! Exports todos as org text, the way `import` reads it back:
+//! - the TODO keywords are declared in a `#+TODO:` line first, unless they're the default ones;
+//! - every todo is a headline, with the keyword for its status, its priority and its tags,
+//!   nested like `child_todos` and in the order of their positions;
+//! - the first planned execution is `SCHEDULED:`, and the others are active timestamps on the
+//!   lines after the planning line and the property drawer;
+//! - the deadline is `DEADLINE:` and `completed_at` is `CLOSED:`;
+//! - the ID is the `:ID:` property, so that the headline can be matched with its todo later,
+//!   followed by the estimated duration as `:Effort:`, unless it's zero, and the other
+//!   `properties`;
+//! - the actual executions are `CLOCK:` lines in a `LOGBOOK` drawer, in the same order;
+//! - the text follows, with the lines that would start a headline escaped with a comma, like
+//!   org-mode does in blocks.
+//!
+//! Importing the result gives the same todos back, as long as the times are in
+//! whole minutes and the titles are on one line. Local times that happen twice, at the end of
+//! DST, are read back as the first one. Only what `import` reads is exported.
+
+use chrono::{Duration, NaiveDateTime, TimeZone, Timelike};
+use chrono_tz::Tz;
+use wire::state::{State, TodoSnapshot};
+use yrs_wrappers::{snapshot::ToSnapshot, yrs_wrapper_error::YrsResult};
+
+use crate::import::{is_headline, is_imported_property, TodoKeywords};
+
+/// `keywords` are the ones of the file the result goes to, see `TodoKeywords::keyword`.
+pub fn state_to_org(
+    state: &State,
+    txn: &impl yrs::ReadTxn,
+    tz: Tz,
+    keywords: &TodoKeywords,
+) -> YrsResult<String> {
+    Ok(todos_to_org(
+        &state.todos(txn)?.to_snapshot(txn)?,
+        tz,
+        keywords,
+    ))
+}
+
+/// Starts with the `#+TODO:` line for `keywords`, unless they're the default ones.
+pub fn todos_to_org(todos: &[TodoSnapshot], tz: Tz, keywords: &TodoKeywords) -> String {
+    let mut org = match keywords.declaration() {
+        Some(declaration) => format!("{}\n", declaration),
+        None => String::new(),
+    };
+    org.push_str(&headlines_to_org(todos, tz, keywords));
+    org
+}
+
+/// Like `todos_to_org`, but without the `#+TODO:` line, for files that already have one.
+pub(crate) fn headlines_to_org(todos: &[TodoSnapshot], tz: Tz, keywords: &TodoKeywords) -> String {
+    let mut org = String::new();
+    for todo in TodoSnapshot::in_order(todos) {
+        push_todo(&mut org, todo, 1, tz, keywords);
+    }
+    org
+}
+
+fn push_todo(org: &mut String, todo: &TodoSnapshot, level: usize, tz: Tz, keywords: &TodoKeywords) {
+    let keyword = keywords.keyword(&todo.current_status());
+    let title = todo.title.lines().collect::<Vec<_>>().join(" ");
+    org.push_str(&format!("{} {}", "*".repeat(level), keyword));
+    if let Some(priority) = &todo.priority {
+        org.push_str(&format!(" [#{}]", priority));
+    }
+    if !title.is_empty() {
+        org.push_str(&format!(" {}", title));
+    }
+    if !todo.tag_list().is_empty() {
+        org.push_str(&format!(" :{}:", todo.tag_list().join(":")));
+    }
+    org.push('\n');
+
+    let indent = " ".repeat(level + 1);
+    let mut planning = Vec::new();
+    let mut planned = todo.planned_executions.iter();
+    if let Some(first) = planned.next() {
+        planning.push(format!(
+            "SCHEDULED: {}",
+            range(local(first.start, tz), local(first.end, tz))
+        ));
+    }
+    if let Some(deadline) = todo.deadline {
+        planning.push(format!("DEADLINE: {}", end(local(deadline, tz))));
+    }
+    if let Some(completed_at) = todo.completed_at {
+        planning.push(format!("CLOSED: {}", inactive(local(completed_at, tz))));
+    }
+    if !planning.is_empty() {
+        org.push_str(&format!("{}{}\n", indent, planning.join(" ")));
+    }
+
+    let mut properties = vec![("ID", todo.id.to_string())];
+    if !todo.estimated_duration.is_zero() {
+        properties.push(("Effort", effort(todo.estimated_duration)));
+    }
+    let other_properties = todo.property_map();
+    properties.extend(
+        other_properties
+            .iter()
+            .filter(|(key, _)| !is_imported_property(key))
+            .map(|(key, value)| (key.as_str(), value.clone())),
+    );
+    org.push_str(&format!("{}:PROPERTIES:\n", indent));
+    for (key, value) in properties {
+        let key = format!(":{}:", key);
+        org.push_str(format!("{}{:<10} {}", indent, key, value).trim_end());
+        org.push('\n');
+    }
+    org.push_str(&format!("{}:END:\n", indent));
+    for execution in planned {
+        org.push_str(&format!(
+            "{}{}\n",
+            indent,
+            range(local(execution.start, tz), local(execution.end, tz))
+        ));
+    }
+
+    if !todo.actual_executions.is_empty() {
+        org.push_str(&format!("{}:LOGBOOK:\n", indent));
+        for execution in &todo.actual_executions {
+            let start = local(execution.start, tz);
+            let clock = match execution.end {
+                Some(end) => {
+                    let end = local(end, tz);
+                    let minutes = (end - start).num_minutes();
+                    format!(
+                        "{}--{} => {:>2}:{:02}",
+                        inactive(start),
+                        inactive(end),
+                        minutes / 60,
+                        minutes % 60
+                    )
+                }
+                None => inactive(start),
+            };
+            org.push_str(&format!("{}CLOCK: {}\n", indent, clock));
+        }
+        org.push_str(&format!("{}:END:\n", indent));
+    }
+
+    for line in todo.text.lines() {
+        if is_headline(line.trim_start_matches(',')) {
+            org.push(',');
+        }
+        org.push_str(line);
+        org.push('\n');
+    }
+
+    for child in TodoSnapshot::in_order(&todo.child_todos) {
+        push_todo(org, child, level + 1, tz, keywords);
+    }
+}
+
+/// In `H:MM`, which is how org-mode writes efforts.
+fn effort(duration: Duration) -> String {
+    let minutes = duration.num_minutes();
+    format!("{}:{:02}", minutes / 60, minutes % 60)
+}
+
+fn local(utc: NaiveDateTime, tz: Tz) -> NaiveDateTime {
+    tz.from_utc_datetime(&utc).naive_local()
+}
+
+fn is_midnight(local: NaiveDateTime) -> bool {
+    local.num_seconds_from_midnight() == 0
+}
+
+/// An active timestamp from `start` to `end`. Ranges covering whole days are written without
+/// times, since that's how `import` reads them.
+fn range(start: NaiveDateTime, end: NaiveDateTime) -> String {
+    if start == end {
+        format!("<{}>", date_time(start))
+    } else if is_midnight(start) && is_midnight(end) && start < end {
+        let last_day = end.date().pred_opt().unwrap_or(end.date());
+        if last_day == start.date() {
+            format!("<{}>", date(start))
+        } else {
+            format!(
+                "<{}>--<{}>",
+                date(start),
+                date(last_day.and_time(end.time()))
+            )
+        }
+    } else if start.date() == end.date() {
+        format!("<{}-{}>", date_time(start), end.format("%H:%M"))
+    } else {
+        format!("<{}>--<{}>", date_time(start), date_time(end))
+    }
+}
+
+/// An active timestamp ending at `end`, without a time if it's the end of a day.
+fn end(end: NaiveDateTime) -> String {
+    if is_midnight(end) {
+        let day = end - Duration::days(1);
+        format!("<{}>", date(day))
+    } else {
+        format!("<{}>", date_time(end))
+    }
+}
+
+fn inactive(local: NaiveDateTime) -> String {
+    format!("[{}]", date_time(local))
+}
+
+fn date(local: NaiveDateTime) -> String {
+    local.format("%Y-%m-%d %a").to_string()
+}
+
+fn date_time(local: NaiveDateTime) -> String {
+    local.format("%Y-%m-%d %a %H:%M").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveDate, NaiveDateTime};
+    use chrono_tz::Europe::Berlin;
+    use uuid::Uuid;
+    use wire::state::{
+        ActualExecutionSnapshot, PlannedExecutionSnapshot, StatePrelim, TodoSnapshot,
+        TodoStatusSnapshot,
+    };
+    use yrs::{Doc, Map, Transact};
+    use yrs_wrappers::{snapshot::ToSnapshot, yrs_wrapper_error::YrsResult};
+
+    use super::{state_to_org, todos_to_org};
+    use crate::import::{get_todos_from_org, ImportSettings, TodoKeywords};
+
+    fn utc(month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2023, month, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn settings() -> ImportSettings {
+        ImportSettings::new(Berlin, utc(11, 1, 0, 0))
+    }
+
+    /// Imports `contents` and exports it again.
+    fn round_trip(contents: &str) -> YrsResult<(Vec<TodoSnapshot>, String)> {
+        let imported = get_todos_from_org(contents, &settings());
+        assert!(imported.errors.is_empty());
+
+        let doc = Doc::new();
+        let map = doc.get_or_insert_map("state");
+        let state = map.insert(
+            &mut doc.transact_mut(),
+            "state",
+            StatePrelim {
+                todos: imported.todos.into(),
+            },
+        );
+        let txn = doc.transact();
+        let todos = state.todos(&txn)?.to_snapshot(&txn)?;
+        let keywords = TodoKeywords::new(contents, &settings());
+        Ok((todos, state_to_org(&state, &txn, Berlin, &keywords)?))
+    }
+
+    #[test]
+    fn test_round_trip() -> YrsResult<()> {
+        let (todos, exported) = round_trip(
+            "* DONE Allow ignoring rule action logs that have been undone
+  CLOSED: [2023-10-27 Fri 11:56]
+  :PROPERTIES:
+  :ID: 00000000-0000-0000-0000-000000000001
+  :END:
+* DONE Say what undo actually means in the UI.
+  SCHEDULED: <2023-10-29 Sun 19:00> CLOSED: [2023-10-30 Mon 10:38]
+  :PROPERTIES:
+  :ID: 00000000-0000-0000-0000-000000000002
+  :END:
+  <2023-11-02 Thu>--<2023-11-03 Fri>
+  :LOGBOOK:
+  CLOCK: [2023-10-31 Tue 14:35]
+  CLOCK: [2023-10-30 Mon 08:45]--[2023-10-30 Mon 10:37] =>  1:52
+  :END:
+
+  Undo only reverts local changes.
+,* Not a headline
+** Explain redo too
+   SCHEDULED: <2023-10-30 Mon 09:00-10:30> DEADLINE: <2023-11-03 Fri>
+   :PROPERTIES:
+   :ID: 00000000-0000-0000-0000-000000000003
+   :END:
+* TODO [#B] Build a lightweight version of the reports system. :work:reports:
+SCHEDULED: <2023-10-29 Sun 18:30>--<2023-10-30 Mon 09:00>
+:PROPERTIES:
+:CATEGORY: funften
+:ID: 00000000-0000-0000-0000-000000000004
+:Effort: 45min
+:END:",
+        )?;
+
+        assert_eq!(
+            exported,
+            "* DONE Allow ignoring rule action logs that have been undone
+  CLOSED: [2023-10-27 Fri 11:56]
+  :PROPERTIES:
+  :ID:       00000000-0000-0000-0000-000000000001
+  :END:
+* DONE Say what undo actually means in the UI.
+  SCHEDULED: <2023-10-29 Sun 19:00> CLOSED: [2023-10-30 Mon 10:38]
+  :PROPERTIES:
+  :ID:       00000000-0000-0000-0000-000000000002
+  :END:
+  <2023-11-02 Thu>--<2023-11-03 Fri>
+  :LOGBOOK:
+  CLOCK: [2023-10-31 Tue 14:35]
+  CLOCK: [2023-10-30 Mon 08:45]--[2023-10-30 Mon 10:37] =>  1:52
+  :END:
+  Undo only reverts local changes.
+,* Not a headline
+** TODO Explain redo too
+   SCHEDULED: <2023-10-30 Mon 09:00-10:30> DEADLINE: <2023-11-03 Fri>
+   :PROPERTIES:
+   :ID:       00000000-0000-0000-0000-000000000003
+   :END:
+* TODO [#B] Build a lightweight version of the reports system. :work:reports:
+  SCHEDULED: <2023-10-29 Sun 18:30>--<2023-10-30 Mon 09:00>
+  :PROPERTIES:
+  :ID:       00000000-0000-0000-0000-000000000004
+  :Effort:   0:45
+  :CATEGORY: funften
+  :END:
+"
+        );
+        assert_eq!(
+            todos[1].planned_executions[1],
+            PlannedExecutionSnapshot {
+                start: utc(11, 1, 23, 0),
+                end: utc(11, 3, 23, 0),
+            }
+        );
+        assert_eq!(
+            todos[1].text,
+            "  Undo only reverts local changes.\n* Not a headline"
+        );
+
+        let (again, _) = round_trip(&exported)?;
+        assert_eq!(again, todos);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_todos_to_org() {
+        let start = utc(10, 30, 8, 0);
+        let todo = TodoSnapshot {
+            id: Uuid::nil(),
+            planned_executions: vec![PlannedExecutionSnapshot {
+                start,
+                end: start + Duration::minutes(90),
+            }],
+            actual_executions: vec![ActualExecutionSnapshot {
+                start: start - Duration::days(1),
+                end: Some(start - Duration::days(1) + Duration::minutes(5)),
+            }],
+            deadline: Some(utc(10, 31, 12, 0)),
+            ..TodoSnapshot::new("Write\nthe exporter", start, Duration::hours(1))
+        };
+
+        // Berlin switched from CEST to CET on October 29.
+        assert_eq!(
+            todos_to_org(&[todo], Berlin, &TodoKeywords::new("", &settings())),
+            "* TODO Write the exporter
+  SCHEDULED: <2023-10-30 Mon 09:00-10:30> DEADLINE: <2023-10-31 Tue 13:00>
+  :PROPERTIES:
+  :ID:       00000000-0000-0000-0000-000000000000
+  :Effort:   1:00
+  :END:
+  :LOGBOOK:
+  CLOCK: [2023-10-29 Sun 09:00]--[2023-10-29 Sun 09:05] =>  0:05
+  :END:
+"
+        );
+    }
+
+    #[test]
+    fn test_keywords() -> YrsResult<()> {
+        let headlines = |org: &str| -> Vec<String> {
+            org.lines()
+                .filter(|line| line.starts_with('*'))
+                .map(String::from)
+                .collect()
+        };
+        let (todos, exported) = round_trip(
+            "#+TODO: TODO(t) NEXT WAIT(w@/!) | DONE CANCELLED(c@)
+* NEXT Write the changelog
+* WAIT Get a review
+* CANCELLED Write a blog post
+* DONE Fix the tests",
+        )?;
+
+        assert_eq!(
+            todos
+                .iter()
+                .map(|todo| todo.current_status())
+                .collect::<Vec<_>>(),
+            vec![
+                TodoStatusSnapshot::Next,
+                TodoStatusSnapshot::Waiting,
+                TodoStatusSnapshot::Cancelled,
+                TodoStatusSnapshot::Done,
+            ]
+        );
+        assert!(exported.starts_with("#+TODO: TODO NEXT WAIT | DONE CANCELLED\n"));
+        assert_eq!(
+            headlines(&exported),
+            vec![
+                "* NEXT Write the changelog",
+                "* WAIT Get a review",
+                "* CANCELLED Write a blog post",
+                "* DONE Fix the tests",
+            ]
+        );
+
+        // The result declares the keywords itself, so importing it gives the same todos back.
+        let (again, _) = round_trip(&exported)?;
+        assert_eq!(again, todos);
+
+        // A file without these keywords gets the closest ones it has.
+        let default_keywords = TodoKeywords::new("", &settings());
+        assert_eq!(
+            headlines(&todos_to_org(&todos, Berlin, &default_keywords)),
+            vec![
+                "* TODO Write the changelog",
+                "* TODO Get a review",
+                "* NOT_DONE Write a blog post",
+                "* DONE Fix the tests",
+            ]
+        );
+
+        Ok(())
+    }
+}
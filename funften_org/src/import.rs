@@ -0,0 +1,963 @@
+//! Imports org files as todos.
+//!
+//! Every headline becomes a todo, nested like the headlines are:
+//! - its ID is the `:ID:` property, or a new one if it has none;
+//! - its keyword gives its status (see `TodoKeywords`), which says whether it's completed, and
+//!   `CLOSED:` is when;
+//! - `SCHEDULED:` becomes a planned execution, from the start to the end of the range (a
+//!   timestamp with a time and no range gives an empty one, and a date without a time covers the
+//!   whole day), and so does every line right after the planning line (and the property drawer)
+//!   that's nothing but an active timestamp, called an appointment here;
+//! - every `CLOCK:` line becomes an actual execution, without an end if the clock is running;
+//! - `DEADLINE:` becomes the deadline, at the end of the day if it has no time;
+//! - the priority cookie (like `[#A]`) and the tags become the priority and the tags;
+//! - the `:Effort:` property becomes the estimated duration, and the other properties, except
+//!   for `:ID:`, are kept as they are in `properties`;
+//! - the rest of the section, without the planning line, the appointments and the `PROPERTIES`
+//!   and `LOGBOOK` drawers, becomes the text.
+//!
+//! Org timestamps have no time zone, so they're read in `ImportSettings::tz` (see
+//! `wire::time::local_to_utc` for the times DST transitions skip or repeat). Repeaters and warning
+//! delays are ignored.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::Result;
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use chrono_tz::Tz;
+use orgize::elements::{Clock, Datetime, Timestamp};
+use orgize::{Element, Event, Headline, Org, ParseConfig};
+use uuid::Uuid;
+use wire::state::{ActualExecutionPrelim, PlannedExecutionPrelim, TodoPrelim, TodoStatusSnapshot};
+use wire::time::local_to_utc;
+use yrs::TextPrelim;
+use yrs_wrappers::{
+    ybox::YBox,
+    yrs_basic_types::{YIdPrelim, YStringPrelim},
+};
+
+/// The keywords of the files that don't declare their own, besides the ones in
+/// `ImportSettings::statuses`.
+pub const TODO_KEYWORDS: &[&str] = &["TODO"];
+pub const DONE_KEYWORDS: &[&str] = &["DONE", "NOT_DONE"];
+
+/// The lines that declare the TODO keywords of a file.
+const KEYWORD_LINES: &[&str] = &["#+TODO:", "#+SEQ_TODO:", "#+TYP_TODO:"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportSettings {
+    /// The time zone of the timestamps in the org files.
+    pub tz: Tz,
+    /// The `created_at` of every imported todo, since org doesn't record it.
+    pub now: NaiveDateTime,
+    /// The status of TODO keywords, taking precedence over `default_status`. Like the
+    /// `org-todo-keywords` of Emacs, these are keywords in files that don't declare their own.
+    pub statuses: BTreeMap<String, TodoStatusSnapshot>,
+}
+
+impl ImportSettings {
+    pub fn new(tz: Tz, now: NaiveDateTime) -> Self {
+        ImportSettings {
+            tz,
+            now,
+            statuses: BTreeMap::new(),
+        }
+    }
+}
+
+/// The TODO keywords of a file, with the status of the todos they're on.
+///
+/// They're declared by lines like `#+TODO: TODO NEXT | DONE CANCELLED`, where the keywords after
+/// the bar (or the last one, without a bar) are for completed todos. Files without such lines get
+/// `TODO_KEYWORDS`, `DONE_KEYWORDS` and the keywords in `ImportSettings::statuses`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TodoKeywords {
+    /// In the order they're declared.
+    keywords: Vec<(String, TodoStatusSnapshot)>,
+}
+
+impl TodoKeywords {
+    pub fn new(contents: &str, settings: &ImportSettings) -> Self {
+        let status_of = |keyword: &str, closed: bool| {
+            settings
+                .statuses
+                .get(keyword)
+                .cloned()
+                .or_else(|| default_status(keyword))
+                .unwrap_or(if closed {
+                    TodoStatusSnapshot::Done
+                } else {
+                    TodoStatusSnapshot::Todo
+                })
+        };
+
+        let mut keywords = Vec::new();
+        for line in contents.lines() {
+            let Some(declared) = keyword_line(line) else {
+                continue;
+            };
+            let declared = declared
+                .split_whitespace()
+                // Without the fast access keys and the logging settings, like in `WAIT(w@/!)`.
+                .map(|keyword| keyword.split('(').next().unwrap_or(keyword))
+                .collect::<Vec<_>>();
+            let bar = declared
+                .iter()
+                .position(|keyword| *keyword == "|")
+                .unwrap_or(declared.len().saturating_sub(1));
+            for (index, keyword) in declared.iter().enumerate() {
+                if *keyword != "|" {
+                    keywords.push((keyword.to_string(), status_of(keyword, index >= bar)));
+                }
+            }
+        }
+
+        if keywords.is_empty() {
+            let defaults = TODO_KEYWORDS
+                .iter()
+                .map(|keyword| (*keyword, false))
+                .chain(DONE_KEYWORDS.iter().map(|keyword| (*keyword, true)));
+            for (keyword, closed) in defaults {
+                keywords.push((keyword.to_string(), status_of(keyword, closed)));
+            }
+            for (keyword, status) in &settings.statuses {
+                if !keywords.iter().any(|(declared, _)| declared == keyword) {
+                    keywords.push((keyword.clone(), status.clone()));
+                }
+            }
+        }
+        TodoKeywords { keywords }
+    }
+
+    pub fn parse_config(&self) -> ParseConfig {
+        let (closed, open): (Vec<_>, Vec<_>) = self
+            .keywords
+            .iter()
+            .partition(|(_, status)| status.is_closed());
+        let names = |keywords: Vec<&(String, TodoStatusSnapshot)>| -> Vec<String> {
+            keywords
+                .into_iter()
+                .map(|(keyword, _)| keyword.clone())
+                .collect()
+        };
+        ParseConfig {
+            todo_keywords: (names(open), names(closed)),
+        }
+    }
+
+    pub fn status(&self, keyword: &str) -> Option<TodoStatusSnapshot> {
+        self.keywords
+            .iter()
+            .find(|(declared, _)| declared == keyword)
+            .map(|(_, status)| status.clone())
+    }
+
+    /// The `#+TODO:` line that declares these keywords, unless they're just `TODO_KEYWORDS` and
+    /// `DONE_KEYWORDS`, which files without one get anyway.
+    pub fn declaration(&self) -> Option<String> {
+        let (closed, open): (Vec<_>, Vec<_>) = self
+            .keywords
+            .iter()
+            .partition(|(_, status)| status.is_closed());
+        let names = |keywords: Vec<&(String, TodoStatusSnapshot)>| -> Vec<&str> {
+            keywords
+                .into_iter()
+                .map(|(keyword, _)| keyword.as_str())
+                .collect()
+        };
+        let (open, closed) = (names(open), names(closed));
+        if open == TODO_KEYWORDS && closed == DONE_KEYWORDS {
+            return None;
+        }
+        Some(format!(
+            "{} {} | {}",
+            KEYWORD_LINES[0],
+            open.join(" "),
+            closed.join(" ")
+        ))
+    }
+
+    /// The first keyword with `status`, or else the first one for todos that are completed (or
+    /// not) like it, so that a status the file has no keyword for is at least read back as that.
+    pub fn keyword(&self, status: &TodoStatusSnapshot) -> &str {
+        let first = |matches: &dyn Fn(&TodoStatusSnapshot) -> bool| {
+            self.keywords
+                .iter()
+                .find(|(_, declared)| matches(declared))
+                .map(|(keyword, _)| keyword.as_str())
+        };
+        first(&|declared| declared == status)
+            .or_else(|| first(&|declared| declared.is_closed() == status.is_closed()))
+            .unwrap_or(if status.is_closed() {
+                DONE_KEYWORDS[0]
+            } else {
+                TODO_KEYWORDS[0]
+            })
+    }
+}
+
+/// The status of keywords with common names, for when `ImportSettings::statuses` doesn't have
+/// them.
+pub fn default_status(keyword: &str) -> Option<TodoStatusSnapshot> {
+    match keyword {
+        "TODO" => Some(TodoStatusSnapshot::Todo),
+        "NEXT" => Some(TodoStatusSnapshot::Next),
+        "WAITING" | "WAIT" | "HOLD" => Some(TodoStatusSnapshot::Waiting),
+        "DONE" => Some(TodoStatusSnapshot::Done),
+        "CANCELLED" | "CANCELED" | "NOT_DONE" => Some(TodoStatusSnapshot::Cancelled),
+        _ => None,
+    }
+}
+
+/// Whether `contents` has a line like `#+TODO:`.
+pub(crate) fn declares_keywords(contents: &str) -> bool {
+    contents.lines().any(|line| keyword_line(line).is_some())
+}
+
+/// What comes after `#+TODO:` and the like.
+fn keyword_line(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    KEYWORD_LINES.iter().find_map(|prefix| {
+        let start = line.get(..prefix.len())?;
+        start
+            .eq_ignore_ascii_case(prefix)
+            .then(|| &line[prefix.len()..])
+    })
+}
+
+#[derive(Debug, thiserror::Error, Clone, PartialEq, Eq)]
+pub enum ImportError {
+    #[error("{value} is not a valid date or time")]
+    InvalidDate { value: String },
+
+    #[error("Diary timestamps aren't supported: {value}")]
+    DiaryTimestamp { value: String },
+
+    #[error("The range ends at {end}, before it starts at {start}")]
+    EndsBeforeStart {
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    },
+
+    #[error("The ID {value} is not a UUID")]
+    InvalidId { value: String },
+
+    #[error("The effort {value} is not a duration")]
+    InvalidEffort { value: String },
+}
+
+/// A headline that couldn't be imported. It's left out, and its children take its place.
+#[derive(Debug, thiserror::Error, Clone, PartialEq, Eq)]
+#[error("Line {line} ({title}): {error}")]
+pub struct HeadlineError {
+    /// Of the headline, starting at 1.
+    pub line: usize,
+    pub title: String,
+    #[source]
+    pub error: ImportError,
+}
+
+#[derive(Default)]
+pub struct OrgTodos {
+    pub todos: Vec<TodoPrelim>,
+    pub errors: Vec<HeadlineError>,
+}
+
+pub fn get_todos_from_org_file(org_file: &Path, settings: &ImportSettings) -> Result<OrgTodos> {
+    let contents = std::fs::read_to_string(org_file)?;
+    Ok(get_todos_from_org(&contents, settings))
+}
+
+pub fn get_todos_from_org(contents: &str, settings: &ImportSettings) -> OrgTodos {
+    let keywords = TodoKeywords::new(contents, settings);
+    let org_parse = Org::parse_custom(contents, &keywords.parse_config());
+    get_todos_from_org_parse(&org_parse, contents, &keywords, settings)
+}
+
+/// `contents` is what `org_parse` was parsed from. It's needed for the text of the headlines,
+/// which orgize only keeps as elements. `org_parse` has to be parsed with
+/// `keywords.parse_config()`.
+pub fn get_todos_from_org_parse(
+    org_parse: &Org,
+    contents: &str,
+    keywords: &TodoKeywords,
+    settings: &ImportSettings,
+) -> OrgTodos {
+    let mut sections = sections(contents).into_iter();
+    let mut errors = Vec::new();
+    let todos = get_todos_from_headlines(
+        org_parse.document().children(org_parse),
+        org_parse,
+        &mut sections,
+        keywords,
+        settings,
+        &mut errors,
+    );
+    OrgTodos { todos, errors }
+}
+
+/// `sections` has to be at the section of the first of `headlines`. Since it's in the order of
+/// the file, it's consumed depth first.
+fn get_todos_from_headlines<'a>(
+    headlines: impl Iterator<Item = Headline>,
+    org_parse: &Org,
+    sections: &mut impl Iterator<Item = Section<'a>>,
+    keywords: &TodoKeywords,
+    settings: &ImportSettings,
+    errors: &mut Vec<HeadlineError>,
+) -> Vec<TodoPrelim> {
+    let mut todos = Vec::new();
+    for headline in headlines {
+        let section = sections.next().unwrap_or_default();
+        let todo = get_todo_from_headline(headline, org_parse, &section, keywords, settings);
+        let child_todos = get_todos_from_headlines(
+            headline.children(org_parse),
+            org_parse,
+            sections,
+            keywords,
+            settings,
+            errors,
+        );
+        match todo {
+            Ok(mut todo) => {
+                todo.child_todos = YBox::new(child_todos.into());
+                todos.push(todo);
+            }
+            Err(error) => {
+                errors.push(HeadlineError {
+                    line: section.line,
+                    title: headline.title(org_parse).raw.to_string(),
+                    error,
+                });
+                todos.extend(child_todos);
+            }
+        }
+    }
+    todos
+}
+
+/// Without its children, which are left to the caller.
+fn get_todo_from_headline(
+    headline: Headline,
+    org_parse: &Org,
+    section: &Section,
+    keywords: &TodoKeywords,
+    settings: &ImportSettings,
+) -> Result<TodoPrelim, ImportError> {
+    let title = headline.title(org_parse);
+    let tz = settings.tz;
+
+    let scheduled = title.scheduled().map(bounds);
+    let appointments = section.appointments().map(|timestamp| bounds(&timestamp));
+    let planned_executions = scheduled
+        .into_iter()
+        .chain(appointments)
+        .map(|bounds| {
+            let (start, end) = bounds?;
+            Ok(PlannedExecutionPrelim {
+                start: local_to_utc(tz, start).into(),
+                end: local_to_utc(tz, end).into(),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let deadline = match title.deadline() {
+        Some(deadline) => Some(local_to_utc(tz, bounds(deadline)?.1).into()),
+        None => None,
+    };
+    let completed_at = match title.closed() {
+        Some(closed) => Some(local_to_utc(tz, bounds(closed)?.0).into()),
+        None => None,
+    };
+
+    let actual_executions = clocks(headline, org_parse)
+        .map(|clock| {
+            let (start, end) = match clock {
+                Clock::Closed { start, end, .. } => (start, Some(end)),
+                Clock::Running { start, .. } => (start, None),
+            };
+            let start = local_to_utc(tz, local(start)?);
+            let end = match end {
+                Some(end) => Some(local_to_utc(tz, local(end)?)),
+                None => None,
+            };
+            if let Some(end) = end.filter(|end| *end < start) {
+                return Err(ImportError::EndsBeforeStart { start, end });
+            }
+            Ok(ActualExecutionPrelim {
+                start: start.into(),
+                end: end.map(Into::into),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let status = title
+        .keyword
+        .as_deref()
+        .and_then(|keyword| keywords.status(keyword))
+        .unwrap_or(TodoStatusSnapshot::Todo);
+
+    let id = match section.property("ID") {
+        Some(id) => Uuid::parse_str(id)
+            .map_err(|_| ImportError::InvalidId { value: id.into() })?
+            .into(),
+        None => YIdPrelim::new(),
+    };
+    let estimated_duration = match section.property("Effort") {
+        Some(effort) => duration(effort).ok_or_else(|| ImportError::InvalidEffort {
+            value: effort.into(),
+        })?,
+        None => Duration::zero(),
+    };
+    let properties = section
+        .properties()
+        .filter(|(key, _)| !is_imported_property(key))
+        .map(|(key, value)| (key, YStringPrelim::from(value)))
+        .collect();
+
+    Ok(TodoPrelim {
+        id,
+        text: TextPrelim::new(section.text()),
+        completed: status.is_closed().into(),
+        status: Some(status.into()),
+        completed_at,
+        planned_executions: planned_executions.into(),
+        actual_executions: actual_executions.into(),
+        deadline,
+        priority: title
+            .priority
+            .map(|priority| YStringPrelim::from(priority.to_string())),
+        tags: Some(
+            title
+                .tags
+                .iter()
+                .map(|tag| YStringPrelim::from(tag.to_string()))
+                .collect::<Vec<_>>()
+                .into(),
+        ),
+        properties: Some(properties),
+        ..TodoPrelim::new(&title.raw, settings.now, estimated_duration)
+    })
+}
+
+/// The `CLOCK:` lines in the section of `headline`, wherever they are (usually in the logbook).
+fn clocks<'a>(
+    headline: Headline,
+    org_parse: &'a Org<'a>,
+) -> impl Iterator<Item = &'a Clock<'a>> + 'a {
+    headline
+        .section_node()
+        .into_iter()
+        .flat_map(move |section| section.descendants(org_parse.arena()))
+        .filter_map(move |node| match &org_parse[node] {
+            Element::Clock(clock) => Some(clock),
+            _ => None,
+        })
+}
+
+/// The local start and end of a timestamp. A date without a time lasts the whole day.
+fn bounds(timestamp: &Timestamp) -> Result<(NaiveDateTime, NaiveDateTime), ImportError> {
+    let (start, end) = match timestamp {
+        Timestamp::Active { start, .. } | Timestamp::Inactive { start, .. } => (start, start),
+        Timestamp::ActiveRange { start, end, .. } | Timestamp::InactiveRange { start, end, .. } => {
+            (start, end)
+        }
+        Timestamp::Diary { value } => {
+            return Err(ImportError::DiaryTimestamp {
+                value: value.to_string(),
+            })
+        }
+    };
+
+    let (start, end) = (local(start)?, local(end)?);
+    let end = if end_is_day(timestamp) {
+        end + Duration::days(1)
+    } else {
+        end
+    };
+    if end < start {
+        return Err(ImportError::EndsBeforeStart { start, end });
+    }
+    Ok((start, end))
+}
+
+/// Whether the end of `timestamp` is a date without a time.
+fn end_is_day(timestamp: &Timestamp) -> bool {
+    match timestamp {
+        Timestamp::Active { start, .. } | Timestamp::Inactive { start, .. } => start.hour.is_none(),
+        Timestamp::ActiveRange { end, .. } | Timestamp::InactiveRange { end, .. } => {
+            end.hour.is_none()
+        }
+        Timestamp::Diary { .. } => false,
+    }
+}
+
+/// A date without a time is at its start.
+fn local(datetime: &Datetime) -> Result<NaiveDateTime, ImportError> {
+    let invalid = || ImportError::InvalidDate {
+        value: format!(
+            "{}-{:02}-{:02} {:02}:{:02}",
+            datetime.year,
+            datetime.month,
+            datetime.day,
+            datetime.hour.unwrap_or(0),
+            datetime.minute.unwrap_or(0)
+        ),
+    };
+    NaiveDate::from_ymd_opt(
+        datetime.year.into(),
+        datetime.month.into(),
+        datetime.day.into(),
+    )
+    .and_then(|date| {
+        date.and_hms_opt(
+            datetime.hour.unwrap_or(0).into(),
+            datetime.minute.unwrap_or(0).into(),
+            0,
+        )
+    })
+    .ok_or_else(invalid)
+}
+
+/// Whether the property has a field of its own, rather than a place in `properties`.
+pub(crate) fn is_imported_property(key: &str) -> bool {
+    ["ID", "Effort"]
+        .iter()
+        .any(|imported| key.eq_ignore_ascii_case(imported))
+}
+
+/// An org duration, like the value of `:Effort:`: either `H:MM`, or amounts with units like
+/// `1d 2h 30min`, or a mix of both, like `1d 2:30`.
+fn duration(value: &str) -> Option<Duration> {
+    if value.trim().is_empty() {
+        return None;
+    }
+    value
+        .split_whitespace()
+        .try_fold(Duration::zero(), |total, part| {
+            Some(total + duration_part(part)?)
+        })
+}
+
+fn duration_part(part: &str) -> Option<Duration> {
+    if let Some((hours, minutes)) = part.split_once(':') {
+        if minutes.len() != 2 {
+            return None;
+        }
+        return Some(
+            Duration::hours(hours.parse().ok()?) + Duration::minutes(minutes.parse().ok()?),
+        );
+    }
+    let unit = part.trim_start_matches(|c: char| c.is_ascii_digit());
+    let amount = part[..part.len() - unit.len()].parse().ok()?;
+    match unit {
+        "min" => Some(Duration::minutes(amount)),
+        "h" => Some(Duration::hours(amount)),
+        "d" => Some(Duration::days(amount)),
+        "w" => Some(Duration::weeks(amount)),
+        _ => None,
+    }
+}
+
+/// The lines of a headline, up to the next headline.
+#[derive(Debug, Default)]
+struct Section<'a> {
+    /// Of the headline, starting at 1.
+    line: usize,
+    body: Vec<&'a str>,
+}
+
+impl<'a> Section<'a> {
+    /// The `:KEY: value` lines of the property drawer, which org-mode only recognizes right after
+    /// the headline and its planning line.
+    fn properties(&self) -> impl Iterator<Item = (&'a str, &'a str)> + '_ {
+        let planning = self.planning_line_len();
+        self.body[planning..self.properties_end()]
+            .iter()
+            .skip(1)
+            .filter_map(|line| {
+                let (key, value) = line.trim().strip_prefix(':')?.split_once(':')?;
+                Some((key, value.trim()))
+            })
+            .filter(|(key, _)| !key.eq_ignore_ascii_case("END"))
+    }
+
+    fn property(&self, key: &str) -> Option<&'a str> {
+        self.properties()
+            .find(|(property, _)| property.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value)
+    }
+
+    /// The lines after the planning line and the property drawer that are nothing but an active
+    /// timestamp. Org-mode shows them in the agenda like `SCHEDULED:`, so they're planned
+    /// executions too.
+    fn appointments(&self) -> impl Iterator<Item = Timestamp<'a>> + '_ {
+        self.body[self.properties_end()..self.prelude_len()]
+            .iter()
+            .copied()
+            .filter_map(appointment)
+    }
+
+    /// The body, without what's imported separately: the planning line, the property drawer,
+    /// the appointments, and the `LOGBOOK` drawer. Blank lines around it are left out too.
+    fn text(&self) -> String {
+        let mut text = Vec::new();
+        let mut in_drawer = false;
+        for line in &self.body[self.prelude_len()..] {
+            let trimmed = line.trim();
+            if in_drawer {
+                in_drawer = !trimmed.eq_ignore_ascii_case(":END:");
+            } else if trimmed.eq_ignore_ascii_case(":PROPERTIES:")
+                || trimmed.eq_ignore_ascii_case(":LOGBOOK:")
+            {
+                in_drawer = true;
+            } else {
+                text.push(unescape(line));
+            }
+        }
+
+        let start = text.iter().position(|line| !line.trim().is_empty());
+        let end = text.iter().rposition(|line| !line.trim().is_empty());
+        match (start, end) {
+            (Some(start), Some(end)) => text[start..=end].join("\n"),
+            _ => String::new(),
+        }
+    }
+
+    fn planning_line_len(&self) -> usize {
+        match self.body.first() {
+            Some(line) if is_planning(line) => 1,
+            _ => 0,
+        }
+    }
+
+    /// The index of the first line after the planning line and the property drawer.
+    fn properties_end(&self) -> usize {
+        let planning = self.planning_line_len();
+        let drawer = &self.body[planning..];
+        match drawer.first() {
+            Some(line) if line.trim().eq_ignore_ascii_case(":PROPERTIES:") => drawer
+                .iter()
+                .position(|line| line.trim().eq_ignore_ascii_case(":END:"))
+                .map_or(planning, |end| planning + end + 1),
+            _ => planning,
+        }
+    }
+
+    /// The number of lines taken by the planning line, the property drawer and the appointments
+    /// after them. Without a planning line, there are no appointments: the timestamps are just
+    /// part of the text.
+    fn prelude_len(&self) -> usize {
+        let properties_end = self.properties_end();
+        if self.planning_line_len() == 0 {
+            return properties_end;
+        }
+        properties_end
+            + self.body[properties_end..]
+                .iter()
+                .take_while(|line| appointment(line).is_some())
+                .count()
+    }
+}
+
+/// Splits `contents` at its headlines. Like orgize, doesn't look at blocks, so that the result
+/// lines up with `Org::headlines`.
+fn sections(contents: &str) -> Vec<Section> {
+    let mut sections = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        if is_headline(line) {
+            sections.push(Section {
+                line: index + 1,
+                body: Vec::new(),
+            });
+        } else if let Some(section) = sections.last_mut() {
+            section.body.push(line);
+        }
+    }
+    sections
+}
+
+/// Stars at the very start of the line, followed by a space or nothing.
+pub(crate) fn is_headline(line: &str) -> bool {
+    let rest = line.trim_start_matches('*');
+    rest.len() < line.len() && (rest.is_empty() || rest.starts_with(' '))
+}
+
+/// Removes the comma `export` puts in front of lines that would otherwise start a headline.
+pub(crate) fn unescape(line: &str) -> &str {
+    match line.strip_prefix(',') {
+        Some(rest) if is_headline(rest.trim_start_matches(',')) => rest,
+        _ => line,
+    }
+}
+
+fn is_planning(line: &str) -> bool {
+    let line = line.trim_start();
+    ["SCHEDULED:", "DEADLINE:", "CLOSED:"]
+        .iter()
+        .any(|keyword| line.starts_with(keyword))
+}
+
+/// The timestamp, if `line` is nothing but an active one.
+fn appointment(line: &str) -> Option<Timestamp> {
+    let line = line.trim();
+    if !(line.starts_with('<') && line.ends_with('>')) {
+        return None;
+    }
+    let org_parse = Org::parse(line);
+    let timestamp = org_parse.iter().find_map(|event| match event {
+        Event::Start(Element::Timestamp(
+            timestamp @ (Timestamp::Active { .. } | Timestamp::ActiveRange { .. }),
+        )) => Some(timestamp.clone()),
+        _ => None,
+    });
+    timestamp
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use chrono::{Duration, NaiveDate, NaiveDateTime};
+    use chrono_tz::Europe::Berlin;
+    use wire::state::{
+        ActualExecutionSnapshot, PlannedExecutionSnapshot, StatePrelim, TodoPrelim, TodoSnapshot,
+        TodoStatusSnapshot,
+    };
+    use yrs::{Doc, Map, Transact};
+    use yrs_wrappers::{snapshot::ToSnapshot, yrs_wrapper_error::YrsResult};
+
+    use super::{get_todos_from_org, HeadlineError, ImportError, ImportSettings};
+
+    fn utc(month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2023, month, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn settings() -> ImportSettings {
+        ImportSettings::new(Berlin, utc(11, 1, 0, 0))
+    }
+
+    fn snapshots(todos: Vec<TodoPrelim>) -> YrsResult<Vec<TodoSnapshot>> {
+        let doc = Doc::new();
+        let map = doc.get_or_insert_map("state");
+        let state = map.insert(
+            &mut doc.transact_mut(),
+            "state",
+            StatePrelim {
+                todos: todos.into(),
+            },
+        );
+        let txn = doc.transact();
+        state.todos(&txn)?.to_snapshot(&txn)
+    }
+
+    #[test]
+    fn test_get_todos_from_org() -> YrsResult<()> {
+        let imported = get_todos_from_org(
+            "* DONE Allow ignoring rule action logs that have been undone
+  CLOSED: [2023-10-27 Fri 11:56]
+* DONE Say what undo actually means in the UI.
+  SCHEDULED: <2023-10-29 Sun 19:00> CLOSED: [2023-10-30 Mon 10:38]
+  :LOGBOOK:
+  CLOCK: [2023-10-31 Tue 14:35]
+  CLOCK: [2023-10-30 Mon 08:45]--[2023-10-30 Mon 10:37] =>  1:52
+  :END:
+  Undo only reverts local changes.
+** TODO Explain redo too
+   SCHEDULED: <2023-10-30 Mon 09:00-10:30> DEADLINE: <2023-11-03 Fri>
+** TODO Broken
+   SCHEDULED: <2023-02-30 Thu>
+*** TODO Orphan
+* TODO Build a lightweight version of the reports system.
+SCHEDULED: <2023-10-29 Sun 18:30>",
+            &settings(),
+        );
+
+        assert_eq!(
+            imported.errors,
+            vec![HeadlineError {
+                line: 12,
+                title: "Broken".into(),
+                error: ImportError::InvalidDate {
+                    value: "2023-02-30 00:00".into()
+                },
+            }]
+        );
+
+        let todos = snapshots(imported.todos)?;
+
+        let titles = |todos: &[TodoSnapshot]| -> Vec<String> {
+            todos.iter().map(|todo| todo.title.clone()).collect()
+        };
+        assert_eq!(
+            titles(&todos),
+            vec![
+                "Allow ignoring rule action logs that have been undone",
+                "Say what undo actually means in the UI.",
+                "Build a lightweight version of the reports system.",
+            ]
+        );
+        assert_eq!(
+            titles(&todos[1].child_todos),
+            vec!["Explain redo too", "Orphan"]
+        );
+
+        // Berlin switched from CEST to CET on October 29.
+        assert!(todos[0].completed);
+        assert_eq!(todos[0].completed_at, Some(utc(10, 27, 9, 56)));
+
+        let undo = &todos[1];
+        assert_eq!(undo.completed_at, Some(utc(10, 30, 9, 38)));
+        assert_eq!(
+            undo.planned_executions,
+            vec![PlannedExecutionSnapshot {
+                start: utc(10, 29, 18, 0),
+                end: utc(10, 29, 18, 0),
+            }]
+        );
+        assert_eq!(
+            undo.actual_executions,
+            vec![
+                ActualExecutionSnapshot {
+                    start: utc(10, 31, 13, 35),
+                    end: None,
+                },
+                ActualExecutionSnapshot {
+                    start: utc(10, 30, 7, 45),
+                    end: Some(utc(10, 30, 9, 37)),
+                },
+            ]
+        );
+        assert_eq!(undo.text, "  Undo only reverts local changes.");
+
+        let redo = &undo.child_todos[0];
+        assert!(!redo.completed);
+        assert_eq!(
+            redo.planned_executions,
+            vec![PlannedExecutionSnapshot {
+                start: utc(10, 30, 8, 0),
+                end: utc(10, 30, 9, 30),
+            }]
+        );
+        assert_eq!(redo.deadline, Some(utc(11, 3, 23, 0)));
+
+        assert_eq!(todos[2].planned_executions[0].start, utc(10, 29, 17, 30));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_metadata() -> YrsResult<()> {
+        let imported = get_todos_from_org(
+            "* TODO [#A] Release the sync tool :work:org:
+  :PROPERTIES:
+  :ID:       00000000-0000-0000-0000-000000000001
+  :Effort:   1:30
+  :CATEGORY: funften
+  :END:
+** TODO Write the changelog
+   :PROPERTIES:
+   :EFFORT:   1d 2h 15min
+   :END:
+** TODO Guess
+   :PROPERTIES:
+   :Effort:   a while
+   :END:",
+            &settings(),
+        );
+
+        assert_eq!(
+            imported.errors,
+            vec![HeadlineError {
+                line: 11,
+                title: "Guess".into(),
+                error: ImportError::InvalidEffort {
+                    value: "a while".into()
+                },
+            }]
+        );
+
+        let todos = snapshots(imported.todos)?;
+        let release = &todos[0];
+        assert_eq!(release.title, "Release the sync tool");
+        assert_eq!(release.priority.as_deref(), Some("A"));
+        assert_eq!(release.tag_list(), ["work", "org"]);
+        assert_eq!(release.estimated_duration, Duration::minutes(90));
+        assert_eq!(
+            *release.property_map(),
+            BTreeMap::from([("CATEGORY".to_string(), "funften".to_string())])
+        );
+
+        let changelog = &release.child_todos[0];
+        assert_eq!(changelog.priority, None);
+        assert!(changelog.tag_list().is_empty());
+        assert_eq!(
+            changelog.estimated_duration,
+            Duration::days(1) + Duration::hours(2) + Duration::minutes(15)
+        );
+        assert!(changelog.property_map().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_keywords() -> YrsResult<()> {
+        let statuses = |todos: &[TodoSnapshot]| -> Vec<(String, TodoStatusSnapshot)> {
+            todos
+                .iter()
+                .map(|todo| (todo.title.clone(), todo.current_status()))
+                .collect()
+        };
+
+        // Keywords that files don't declare come from the settings.
+        let mut settings = settings();
+        settings
+            .statuses
+            .insert("SOMEDAY".into(), TodoStatusSnapshot::Waiting);
+        let imported = get_todos_from_org(
+            "* SOMEDAY Learn Emacs Lisp
+* NOT_DONE Rewrite it in Rust
+* NEXT Write tests",
+            &settings,
+        );
+        assert_eq!(
+            statuses(&snapshots(imported.todos)?),
+            vec![
+                ("Learn Emacs Lisp".into(), TodoStatusSnapshot::Waiting),
+                ("Rewrite it in Rust".into(), TodoStatusSnapshot::Cancelled),
+                ("NEXT Write tests".into(), TodoStatusSnapshot::Todo),
+            ]
+        );
+
+        // Declared keywords replace them. Unknown ones are open before the bar and completed
+        // after it, unless the settings say otherwise.
+        let imported = get_todos_from_org(
+            "#+TITLE: Release
+#+SEQ_TODO: TODO NEXT(n) REVIEW | MERGED
+#+todo: SOMEDAY | DROPPED(d@)
+* REVIEW Check the sync
+* MERGED Keywords
+* DROPPED Diary timestamps
+* SOMEDAY Agenda views
+* DONE Not a keyword here",
+            &settings,
+        );
+        let todos = snapshots(imported.todos)?;
+        assert_eq!(
+            statuses(&todos),
+            vec![
+                ("Check the sync".into(), TodoStatusSnapshot::Todo),
+                ("Keywords".into(), TodoStatusSnapshot::Done),
+                ("Diary timestamps".into(), TodoStatusSnapshot::Done),
+                ("Agenda views".into(), TodoStatusSnapshot::Waiting),
+                ("DONE Not a keyword here".into(), TodoStatusSnapshot::Todo),
+            ]
+        );
+        assert!(todos[1].completed);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+//! Syncs a directory of org files with a document, once or whenever the files change:
+//! `funften_org <dir> <time zone> [--watch] [--keyword=<KEYWORD>:<status>]...`.
+//!
+//! Every `--keyword` gives the status of a TODO keyword, see `ImportSettings::statuses`.
+
+use anyhow::{anyhow, bail, Result};
+use chrono::Utc;
+use chrono_tz::Tz;
+use funften_org::{sync, watch, SyncError, SyncReport, SyncSettings};
+use wire::state::TodoStatusSnapshot;
+
+fn main() -> Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let watching = args.iter().any(|arg| arg == "--watch");
+    let (dir, tz) = match args
+        .iter()
+        .filter(|arg| !arg.starts_with("--"))
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [dir, tz] => (dir.to_string(), tz.to_string()),
+        _ => bail!(
+            "Usage: funften_org <dir> <time zone> [--watch] [--keyword=<KEYWORD>:<status>]..."
+        ),
+    };
+    let tz = tz
+        .parse::<Tz>()
+        .map_err(|err| anyhow!("Unknown time zone {}: {}", tz, err))?;
+    let mut settings = SyncSettings::new(dir, tz);
+    for arg in &args {
+        if let Some(mapping) = arg.strip_prefix("--keyword=") {
+            let (keyword, status) = keyword_status(mapping)?;
+            settings.statuses.insert(keyword, status);
+        }
+    }
+
+    if watching {
+        watch(&settings, |result| {
+            if let Err(err) = print_report(result) {
+                eprintln!("Couldn't sync: {}", err);
+            }
+        })?;
+    } else {
+        print_report(sync(&settings, Utc::now().naive_utc()))?;
+    }
+    Ok(())
+}
+
+/// Like `NEXT:next`.
+fn keyword_status(mapping: &str) -> Result<(String, TodoStatusSnapshot)> {
+    let Some((keyword, status)) = mapping.split_once(':') else {
+        bail!("Expected <KEYWORD>:<status>, got {}", mapping);
+    };
+    let status = match status.to_lowercase().as_str() {
+        "todo" => TodoStatusSnapshot::Todo,
+        "next" => TodoStatusSnapshot::Next,
+        "waiting" => TodoStatusSnapshot::Waiting,
+        "done" => TodoStatusSnapshot::Done,
+        "cancelled" => TodoStatusSnapshot::Cancelled,
+        _ => bail!(
+            "Unknown status {}, expected todo, next, waiting, done or cancelled",
+            status
+        ),
+    };
+    Ok((keyword.to_string(), status))
+}
+
+fn print_report(result: Result<SyncReport, SyncError>) -> Result<(), SyncError> {
+    let report = match result {
+        Ok(report) => report,
+        Err(SyncError::Import { errors }) => {
+            for (path, err) in &errors {
+                eprintln!("{}: {}", path.display(), err);
+            }
+            return Err(SyncError::Import { errors });
+        }
+        Err(err) => return Err(err),
+    };
+    for path in &report.written {
+        println!("Wrote {}", path.display());
+    }
+    for conflict in &report.conflicts {
+        println!("Conflict: {}", conflict);
+    }
+    Ok(())
+}
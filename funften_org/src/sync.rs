@@ -0,0 +1,957 @@
+//! Keeps a directory of org files and a `yrs::Doc` in sync, in both directions.
+//!
+//! The document is persisted next to the org files, along with a copy of it as of the last sync,
+//! the synced document. Exporting the synced document gives what the org files looked like after
+//! the last sync, so on every sync:
+//! 1. the org files are imported, and the headlines are matched with the todos by their `:ID:`;
+//! 2. what changed in the files since the last sync is made into edits of the synced document,
+//!    which are merged into the document. They're concurrent with the edits made in the app in
+//!    the meantime, so the CRDT merges the two: edits to different parts of a text are all kept,
+//!    for example;
+//! 3. the document is exported to the files, and becomes the synced document.
+//!
+//! A true conflict is when both sides changed the same field of the same todo to different
+//! values. Texts are merged anyway, while for other fields the app's value is kept. A todo that
+//! was deleted on one side and edited on the other is a conflict too: deleting it in org is
+//! ignored, while deleting it in the app wins. Either way, conflicts are reported.
+//!
+//! Top-level todos stay in the file they're in, and the ones added in the app go to
+//! `SyncSettings::inbox`. Files are rewritten from the document, so what the import doesn't read
+//! in the headlines is lost, but what comes before the first headline is kept. A headline with
+//! the same ID as an earlier one, like a copy, gets a new ID.
+//!
+//! On the first sync, when there's no synced document yet, the todos that are only in the
+//! document are kept, instead of being taken as deleted in org.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use chrono::{NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use notify::{RecursiveMode, Watcher};
+use uuid::Uuid;
+use wire::state::migration::{init_state, load_state, MigrationError, STATE_KEY};
+use wire::state::{
+    ActualExecutionPrelim, PlannedExecutionPrelim, State, StatePrelim, Todo, TodoPrelim,
+    TodoSnapshot, TodoStatusSnapshot,
+};
+use yrs::updates::decoder::Decode;
+use yrs::{
+    Doc, GetString, Map, ReadTxn, StateVector, Text, TextRef, Transact, TransactionMut, Update,
+};
+use yrs_wrappers::{
+    snapshot::ToSnapshot,
+    yrs_basic_types::YStringPrelim,
+    yrs_vec::YrsVec,
+    yrs_wrapper_error::{YrsResult, YrsWrapperError},
+};
+
+use crate::export::{headlines_to_org, todos_to_org};
+use crate::get_org_files_in_dir;
+use crate::import::{
+    declares_keywords, get_todos_from_org, is_headline, HeadlineError, ImportSettings, TodoKeywords,
+};
+
+/// The key of the root map of the document, like in the frontend.
+const ROOT: &str = "root";
+
+/// How long the files have to stay untouched before the watcher syncs, since editors save in
+/// several steps.
+const SETTLE: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncSettings {
+    pub dir: PathBuf,
+    pub doc: PathBuf,
+    /// The document as of the last sync.
+    pub synced: PathBuf,
+    /// Where top-level todos that aren't in any file yet go.
+    pub inbox: PathBuf,
+    /// The time zone of the timestamps in the org files.
+    pub tz: Tz,
+    /// See `ImportSettings::statuses`.
+    pub statuses: BTreeMap<String, TodoStatusSnapshot>,
+}
+
+impl SyncSettings {
+    /// Keeps the documents in `.funften` in `dir`, and new todos in `inbox.org`.
+    pub fn new(dir: impl Into<PathBuf>, tz: Tz) -> Self {
+        let dir = dir.into();
+        SyncSettings {
+            doc: dir.join(".funften").join("doc.ydoc"),
+            synced: dir.join(".funften").join("synced.ydoc"),
+            inbox: dir.join("inbox.org"),
+            dir,
+            tz,
+            statuses: BTreeMap::new(),
+        }
+    }
+}
+
+/// The fields of a todo that are in the org files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Title,
+    Text,
+    /// `completed` and `status`, which both come from the keyword.
+    Status,
+    CompletedAt,
+    Deadline,
+    EstimatedDuration,
+    Priority,
+    Tags,
+    Properties,
+    PlannedExecutions,
+    ActualExecutions,
+}
+
+impl Field {
+    pub const ALL: [Field; 11] = [
+        Field::Title,
+        Field::Text,
+        Field::Status,
+        Field::CompletedAt,
+        Field::Deadline,
+        Field::EstimatedDuration,
+        Field::Priority,
+        Field::Tags,
+        Field::Properties,
+        Field::PlannedExecutions,
+        Field::ActualExecutions,
+    ];
+
+    fn is_text(self) -> bool {
+        matches!(self, Field::Title | Field::Text)
+    }
+
+    fn same(self, a: &TodoSnapshot, b: &TodoSnapshot) -> bool {
+        match self {
+            Field::Title => a.title == b.title,
+            Field::Text => a.text == b.text,
+            Field::Status => a.completed == b.completed && a.status == b.status,
+            Field::CompletedAt => a.completed_at == b.completed_at,
+            Field::Deadline => a.deadline == b.deadline,
+            Field::EstimatedDuration => a.estimated_duration == b.estimated_duration,
+            Field::Priority => a.priority == b.priority,
+            Field::Tags => a.tag_list() == b.tag_list(),
+            Field::Properties => a.property_map() == b.property_map(),
+            Field::PlannedExecutions => a.planned_executions == b.planned_executions,
+            Field::ActualExecutions => a.actual_executions == b.actual_executions,
+        }
+    }
+
+    /// Copies the field from `from` to `to`.
+    fn copy(self, from: &TodoSnapshot, to: &mut TodoSnapshot) {
+        match self {
+            Field::Title => to.title = from.title.clone(),
+            Field::Text => to.text = from.text.clone(),
+            Field::Status => {
+                to.completed = from.completed;
+                to.status = from.status.clone();
+            }
+            Field::CompletedAt => to.completed_at = from.completed_at,
+            Field::Deadline => to.deadline = from.deadline,
+            Field::EstimatedDuration => to.estimated_duration = from.estimated_duration,
+            Field::Priority => to.priority = from.priority.clone(),
+            Field::Tags => to.tags = from.tags.clone(),
+            Field::Properties => to.properties = from.properties.clone(),
+            Field::PlannedExecutions => to.planned_executions = from.planned_executions.clone(),
+            Field::ActualExecutions => to.actual_executions = from.actual_executions.clone(),
+        }
+    }
+
+    /// Like `copy`, but into the document.
+    fn set(self, from: &TodoSnapshot, to: &Todo, txn: &mut TransactionMut) -> YrsResult<()> {
+        match self {
+            Field::Title => replace_text(&to.title(&*txn)?, txn, &from.title),
+            Field::Text => replace_text(&to.text(&*txn)?, txn, &from.text),
+            Field::Status => {
+                to.set_completed(txn, from.completed.into());
+                match &from.status {
+                    Some(status) => {
+                        to.set_status(txn, status.clone().into());
+                    }
+                    None => to.clear_status(txn),
+                }
+            }
+            Field::CompletedAt => match from.completed_at {
+                Some(completed_at) => {
+                    to.set_completed_at(txn, completed_at.into());
+                }
+                None => to.clear_completed_at(txn),
+            },
+            Field::Deadline => match from.deadline {
+                Some(deadline) => {
+                    to.set_deadline(txn, deadline.into());
+                }
+                None => to.clear_deadline(txn),
+            },
+            Field::EstimatedDuration => {
+                to.set_estimated_duration(txn, from.estimated_duration.into());
+            }
+            Field::Priority => match &from.priority {
+                Some(priority) => {
+                    to.set_priority(txn, priority.clone().into());
+                }
+                None => to.clear_priority(txn),
+            },
+            Field::Tags => {
+                let tags = from.tag_list().iter().cloned().map(YStringPrelim::from);
+                match to.tags(&*txn) {
+                    Some(current) => {
+                        current?.splice(txn, .., tags)?;
+                    }
+                    None => {
+                        to.set_tags(txn, tags.collect::<Vec<_>>().into());
+                    }
+                }
+            }
+            Field::Properties => {
+                let wanted = from.property_map();
+                let Some(properties) = to.properties(&*txn) else {
+                    let properties = wanted
+                        .iter()
+                        .map(|(key, value)| (key.as_str(), YStringPrelim::from(value.clone())))
+                        .collect();
+                    to.set_properties(txn, properties);
+                    return Ok(());
+                };
+                let properties = properties?;
+                let current = properties.to_snapshot(&*txn)?;
+                for key in current.keys() {
+                    if !wanted.contains_key(key) {
+                        properties.remove(txn, key);
+                    }
+                }
+                for (key, value) in wanted.iter() {
+                    if current.get(key) != Some(value) {
+                        properties.insert(txn, key.as_str(), YStringPrelim::from(value.clone()));
+                    }
+                }
+            }
+            Field::PlannedExecutions => {
+                let executions = from.planned_executions.iter().cloned();
+                to.planned_executions(&*txn)?.splice(
+                    txn,
+                    ..,
+                    executions.map(PlannedExecutionPrelim::from),
+                )?;
+            }
+            Field::ActualExecutions => {
+                let executions = from.actual_executions.iter().cloned();
+                to.actual_executions(&*txn)?.splice(
+                    txn,
+                    ..,
+                    executions.map(ActualExecutionPrelim::from),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn changed(a: &TodoSnapshot, b: &TodoSnapshot) -> Vec<Field> {
+        Field::ALL
+            .into_iter()
+            .filter(|field| !field.same(a, b))
+            .collect()
+    }
+}
+
+impl std::fmt::Display for Field {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Field::Title => write!(f, "title"),
+            Field::Text => write!(f, "text"),
+            Field::Status => write!(f, "status"),
+            Field::CompletedAt => write!(f, "completion time"),
+            Field::Deadline => write!(f, "deadline"),
+            Field::EstimatedDuration => write!(f, "effort"),
+            Field::Priority => write!(f, "priority"),
+            Field::Tags => write!(f, "tags"),
+            Field::Properties => write!(f, "properties"),
+            Field::PlannedExecutions => write!(f, "planned executions"),
+            Field::ActualExecutions => write!(f, "actual executions"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictKind {
+    /// Both sides changed the field. The app's value was kept.
+    Changed(Field),
+    /// Both sides changed the text, and the edits were merged.
+    Merged(Field),
+    /// Deleted in org, but edited in the app, so the todo was kept.
+    DeletedInOrg,
+    /// Deleted in the app, but edited in org. The todo stays deleted.
+    DeletedInApp,
+}
+
+impl std::fmt::Display for ConflictKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConflictKind::Changed(field) => {
+                write!(f, "the {} changed on both sides, kept the app's", field)
+            }
+            ConflictKind::Merged(field) => write!(f, "the {} changed on both sides, merged", field),
+            ConflictKind::DeletedInOrg => write!(f, "deleted in org but edited in the app, kept"),
+            ConflictKind::DeletedInApp => write!(f, "deleted in the app but edited in org"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub todo: Uuid,
+    pub title: String,
+    pub kind: ConflictKind,
+}
+
+impl std::fmt::Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "\"{}\": {}", self.title, self.kind)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SyncReport {
+    pub conflicts: Vec<Conflict>,
+    /// The org files that were rewritten.
+    pub written: Vec<PathBuf>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SyncError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("{} is not a document: {message}", .path.display())]
+    Decode { path: PathBuf, message: String },
+
+    #[error("YrsWrapperError: {0}")]
+    Yrs(#[from] YrsWrapperError),
+
+    #[error("{0}")]
+    Migration(#[from] MigrationError),
+
+    #[error("{} headlines couldn't be imported, so nothing was synced", .errors.len())]
+    Import {
+        errors: Vec<(PathBuf, HeadlineError)>,
+    },
+}
+
+/// An empty document if there's nothing at `path` yet.
+pub fn load_doc(path: &Path) -> Result<Doc, SyncError> {
+    let doc = Doc::new();
+    if !path.exists() {
+        return Ok(doc);
+    }
+    let update = Update::decode_v1(&fs::read(path)?).map_err(|err| SyncError::Decode {
+        path: path.to_owned(),
+        message: err.to_string(),
+    })?;
+    doc.transact_mut().apply_update(update);
+    Ok(doc)
+}
+
+pub fn save_doc(doc: &Doc, path: &Path) -> Result<(), SyncError> {
+    let update = doc
+        .transact()
+        .encode_state_as_update_v1(&StateVector::default());
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Renaming is atomic, so a crash can't leave half a document behind.
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, update)?;
+    fs::rename(temporary, path)?;
+    Ok(())
+}
+
+/// The state in `doc`, migrated, or created if `doc` is empty.
+pub fn open_state(doc: &Doc) -> Result<State, SyncError> {
+    let root = doc.get_or_insert_map(ROOT);
+    let mut txn = doc.transact_mut();
+    if root.get(&txn, STATE_KEY).is_none() {
+        let todos = Vec::<TodoPrelim>::new().into();
+        return Ok(init_state(&root, &mut txn, StatePrelim { todos }));
+    }
+    Ok(load_state(&root, &mut txn)?)
+}
+
+/// Syncs once. `now` is the `created_at` of the todos added in org.
+pub fn sync(settings: &SyncSettings, now: NaiveDateTime) -> Result<SyncReport, SyncError> {
+    let import_settings = ImportSettings {
+        statuses: settings.statuses.clone(),
+        ..ImportSettings::new(settings.tz, now)
+    };
+    let files = read_org_files(settings, &import_settings)?;
+
+    let first_sync = !settings.doc.exists() || !settings.synced.exists();
+    let doc = load_doc(&settings.doc)?;
+    let state = open_state(&doc)?;
+    let synced_doc = if first_sync {
+        fork(&doc)
+    } else {
+        load_doc(&settings.synced)?
+    };
+    let synced_state = open_state(&synced_doc)?;
+    let synced_state_vector = synced_doc.transact().state_vector();
+
+    let app = flat_todos(&state, &doc.transact())?;
+    let synced_todos = {
+        let txn = synced_doc.transact();
+        synced_state.todos(&txn)?.to_snapshot(&txn)?
+    };
+    let mut synced = HashMap::new();
+    flatten(&synced_todos, &mut synced);
+    let mut exported = HashMap::new();
+    for (path, todos) in by_file(settings, &files, synced_todos) {
+        let contents = org_file_contents(path, &files, &todos, &import_settings);
+        let reimported = get_todos_from_org(&contents, &import_settings);
+        flatten(&snapshots(reimported.todos)?, &mut exported);
+    }
+
+    let org_todos = files
+        .iter()
+        .flat_map(|file| file.todos.iter().cloned())
+        .collect::<Vec<_>>();
+    let mut org = HashMap::new();
+    flatten(&org_todos, &mut org);
+
+    let mut merge = Merge {
+        synced: &synced,
+        exported: &exported,
+        app: &app,
+        org: &org,
+        first_sync,
+        conflicts: Vec::new(),
+    };
+    {
+        let mut txn = synced_doc.transact_mut();
+        let todos = synced_state.todos(&txn)?;
+        merge.reconcile(&todos, &org_todos, &mut txn)?;
+    }
+    let conflicts = merge.conflicts;
+
+    let update = synced_doc
+        .transact()
+        .encode_state_as_update_v1(&synced_state_vector);
+    doc.transact_mut()
+        .apply_update(Update::decode_v1(&update).expect("encoded just now"));
+
+    let written = write_org_files(settings, &import_settings, &files, &state, &doc)?;
+    save_doc(&doc, &settings.doc)?;
+    save_doc(&doc, &settings.synced)?;
+    Ok(SyncReport { conflicts, written })
+}
+
+/// Syncs, and then syncs again whenever an org file in `settings.dir` changes, until watching
+/// fails. Changes to the document are picked up by the next sync. The files written by a sync
+/// trigger another one, which has nothing to do.
+pub fn watch(
+    settings: &SyncSettings,
+    mut on_sync: impl FnMut(Result<SyncReport, SyncError>),
+) -> notify::Result<()> {
+    let (sender, receiver) = mpsc::channel::<notify::Result<notify::Event>>();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    watcher.watch(&settings.dir, RecursiveMode::Recursive)?;
+
+    on_sync(sync(settings, Utc::now().naive_utc()));
+    while let Ok(event) = receiver.recv() {
+        let is_org = |path: &PathBuf| path.extension().map_or(false, |ext| ext == "org");
+        if !event?.paths.iter().any(is_org) {
+            continue;
+        }
+        while receiver.recv_timeout(SETTLE).is_ok() {}
+        on_sync(sync(settings, Utc::now().naive_utc()));
+    }
+    Ok(())
+}
+
+/// Turns what changed in org into edits of the synced document.
+struct Merge<'a> {
+    /// Every todo in the synced document, without its children.
+    synced: &'a HashMap<Uuid, TodoSnapshot>,
+    /// The same, as read back from the org files after the last sync.
+    exported: &'a HashMap<Uuid, TodoSnapshot>,
+    /// Every todo in the document, with the changes made in the app.
+    app: &'a HashMap<Uuid, TodoSnapshot>,
+    /// Every todo in the org files.
+    org: &'a HashMap<Uuid, TodoSnapshot>,
+    first_sync: bool,
+    conflicts: Vec<Conflict>,
+}
+
+impl Merge<'_> {
+    /// Makes `todos`, which are in the synced document, look like `org`.
+    fn reconcile(
+        &mut self,
+        todos: &YrsVec<Todo>,
+        org: &[TodoSnapshot],
+        txn: &mut TransactionMut,
+    ) -> YrsResult<()> {
+        let wanted = org.iter().map(|todo| todo.id).collect::<HashSet<_>>();
+        // From the back, so that the indices that are left stay valid.
+        for index in (0..todos.len(&*txn)).rev() {
+            let id = *todos.get(&*txn, index)?.expect("in bounds").id(&*txn)?;
+            if !wanted.contains(&id) && self.should_remove(id) {
+                todos.remove(txn, index)?;
+            }
+        }
+
+        // Indices in the order the todos are shown in, which is what the org files follow.
+        let mut position = 0;
+        for org_todo in org {
+            let mut found = None;
+            let in_order = todos.in_order(&*txn)?;
+            for (index, todo) in (0..).zip(in_order).skip(position as usize) {
+                if *todo.id(&*txn)? == org_todo.id {
+                    found = Some((index, todo));
+                    break;
+                }
+            }
+
+            let todo = match found {
+                Some((index, todo)) => {
+                    if index != position {
+                        todos.move_in_order(txn, index, position)?;
+                    }
+                    for field in self.fields_to_apply(org_todo) {
+                        field.set(org_todo, &todo, txn)?;
+                    }
+                    todo
+                }
+                None => match self.insertion(org_todo) {
+                    Some(prelim) => {
+                        // New todos have no position, so they come last at first.
+                        let todo = todos.push(txn, prelim);
+                        todos.move_in_order(txn, todos.len(&*txn) - 1, position)?;
+                        todo
+                    }
+                    None => continue,
+                },
+            };
+            self.reconcile(&todo.child_todos(&*txn)?, &org_todo.child_todos, txn)?;
+            position += 1;
+        }
+        Ok(())
+    }
+
+    /// Whether to remove a todo from where it is in the synced document, because it was moved or
+    /// deleted in org.
+    fn should_remove(&mut self, id: Uuid) -> bool {
+        if self.org.contains_key(&id) {
+            return true;
+        }
+        if self.first_sync {
+            return false;
+        }
+        match self.app.get(&id) {
+            Some(app) if Some(app) != self.synced.get(&id) => {
+                self.conflict(id, &app.title, ConflictKind::DeletedInOrg);
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// The fields that were changed in org, and should be changed in the document too.
+    fn fields_to_apply(&mut self, org: &TodoSnapshot) -> Vec<Field> {
+        let exported = match self.exported.get(&org.id) {
+            Some(exported) => exported,
+            None => return Field::ALL.to_vec(),
+        };
+        let changed = Field::changed(exported, org);
+        let (app, synced) = match (self.app.get(&org.id), self.synced.get(&org.id)) {
+            (Some(app), Some(synced)) => (app, synced),
+            _ => {
+                if !changed.is_empty() {
+                    self.conflict(org.id, &org.title, ConflictKind::DeletedInApp);
+                }
+                return Vec::new();
+            }
+        };
+
+        let mut fields = Vec::new();
+        for field in changed {
+            if field.same(app, synced) || field.same(app, org) {
+                fields.push(field);
+            } else if field.is_text() {
+                self.conflict(org.id, &org.title, ConflictKind::Merged(field));
+                fields.push(field);
+            } else {
+                self.conflict(org.id, &org.title, ConflictKind::Changed(field));
+            }
+        }
+        fields
+    }
+
+    /// What to insert for a todo that isn't where org has it in the synced document, if
+    /// anything. Todos that were moved keep what isn't in org, like their estimate.
+    fn insertion(&mut self, org: &TodoSnapshot) -> Option<TodoPrelim> {
+        let mut todo = if self.synced.contains_key(&org.id) {
+            let fields = self.fields_to_apply(org);
+            let mut todo = self.app.get(&org.id)?.clone();
+            for field in fields {
+                field.copy(org, &mut todo);
+            }
+            todo
+        } else {
+            org.clone()
+        };
+        todo.child_todos = Box::default();
+        Some(todo.into())
+    }
+
+    fn conflict(&mut self, todo: Uuid, title: &str, kind: ConflictKind) {
+        self.conflicts.push(Conflict {
+            todo,
+            title: title.to_string(),
+            kind,
+        });
+    }
+}
+
+struct OrgFile {
+    path: PathBuf,
+    contents: String,
+    /// Everything before the first headline.
+    preamble: String,
+    keywords: TodoKeywords,
+    todos: Vec<TodoSnapshot>,
+}
+
+fn read_org_files(
+    settings: &SyncSettings,
+    import_settings: &ImportSettings,
+) -> Result<Vec<OrgFile>, SyncError> {
+    let mut paths = get_org_files_in_dir(&settings.dir);
+    paths.sort();
+
+    let mut files = Vec::new();
+    let mut errors = Vec::new();
+    let mut ids = HashSet::new();
+    for path in paths {
+        let contents = fs::read_to_string(&path)?;
+        let imported = get_todos_from_org(&contents, import_settings);
+        errors.extend(imported.errors.into_iter().map(|err| (path.clone(), err)));
+        let mut todos = snapshots(imported.todos)?;
+        give_copies_new_ids(&mut todos, &mut ids);
+        files.push(OrgFile {
+            preamble: preamble(&contents),
+            keywords: TodoKeywords::new(&contents, import_settings),
+            path,
+            contents,
+            todos,
+        });
+    }
+
+    // Leaving the headlines out would delete their todos.
+    if !errors.is_empty() {
+        return Err(SyncError::Import { errors });
+    }
+    Ok(files)
+}
+
+/// Writes the top-level todos of the document to the file they were in, and returns the files
+/// that changed.
+fn write_org_files(
+    settings: &SyncSettings,
+    import_settings: &ImportSettings,
+    files: &[OrgFile],
+    state: &State,
+    doc: &Doc,
+) -> Result<Vec<PathBuf>, SyncError> {
+    let txn = doc.transact();
+    let todos = state.todos(&txn)?.to_snapshot(&txn)?;
+
+    let mut written = Vec::new();
+    for (path, todos) in by_file(settings, files, todos) {
+        let old = files
+            .iter()
+            .find(|file| &file.path == path)
+            .map_or("", |file| file.contents.as_str());
+        let new = org_file_contents(path, files, &todos, import_settings);
+        if new != old {
+            fs::write(path, new)?;
+            written.push(path.clone());
+        }
+    }
+    Ok(written)
+}
+
+/// Top-level `todos` by the file they're in, or the inbox. Every file is there, even if it has no
+/// todos left.
+fn by_file<'a>(
+    settings: &'a SyncSettings,
+    files: &'a [OrgFile],
+    todos: Vec<TodoSnapshot>,
+) -> BTreeMap<&'a PathBuf, Vec<TodoSnapshot>> {
+    let mut file_of = HashMap::new();
+    for file in files {
+        let mut todos = HashMap::new();
+        flatten(&file.todos, &mut todos);
+        file_of.extend(todos.into_keys().map(|id| (id, &file.path)));
+    }
+
+    let mut by_file = files
+        .iter()
+        .map(|file| (&file.path, Vec::new()))
+        .collect::<BTreeMap<_, _>>();
+    for todo in todos {
+        let path = file_of.get(&todo.id).copied().unwrap_or(&settings.inbox);
+        by_file.entry(path).or_default().push(todo);
+    }
+    by_file
+}
+
+/// The file at `path` with `todos`, keeping its preamble and its TODO keywords.
+fn org_file_contents(
+    path: &Path,
+    files: &[OrgFile],
+    todos: &[TodoSnapshot],
+    import_settings: &ImportSettings,
+) -> String {
+    let (preamble, keywords) = match files.iter().find(|file| file.path == path) {
+        Some(file) => (file.preamble.as_str(), file.keywords.clone()),
+        None => ("", TodoKeywords::new("", import_settings)),
+    };
+    // A preamble that declares the keywords already has the line `todos_to_org` would add.
+    let org = if declares_keywords(preamble) {
+        headlines_to_org(todos, import_settings.tz, &keywords)
+    } else {
+        todos_to_org(todos, import_settings.tz, &keywords)
+    };
+    format!("{}{}", preamble, org)
+}
+
+fn preamble(contents: &str) -> String {
+    contents
+        .lines()
+        .take_while(|line| !is_headline(line))
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+fn give_copies_new_ids(todos: &mut [TodoSnapshot], ids: &mut HashSet<Uuid>) {
+    for todo in todos {
+        if !ids.insert(todo.id) {
+            todo.id = Uuid::new_v4();
+            ids.insert(todo.id);
+        }
+        give_copies_new_ids(&mut todo.child_todos, ids);
+    }
+}
+
+fn flat_todos(state: &State, txn: &impl ReadTxn) -> YrsResult<HashMap<Uuid, TodoSnapshot>> {
+    let mut todos = HashMap::new();
+    flatten(&state.todos(txn)?.to_snapshot(txn)?, &mut todos);
+    Ok(todos)
+}
+
+/// `todos` and their descendants by ID, without their children.
+fn flatten(todos: &[TodoSnapshot], flat: &mut HashMap<Uuid, TodoSnapshot>) {
+    for todo in todos {
+        flatten(&todo.child_todos, flat);
+        flat.insert(
+            todo.id,
+            TodoSnapshot {
+                child_todos: Box::default(),
+                ..todo.clone()
+            },
+        );
+    }
+}
+
+/// Reads prelims as snapshots, through a scratch document.
+fn snapshots(todos: Vec<TodoPrelim>) -> YrsResult<Vec<TodoSnapshot>> {
+    let doc = Doc::new();
+    let map = doc.get_or_insert_map("scratch");
+    let state = map.insert(
+        &mut doc.transact_mut(),
+        STATE_KEY,
+        StatePrelim {
+            todos: todos.into(),
+        },
+    );
+    let txn = doc.transact();
+    state.todos(&txn)?.to_snapshot(&txn)
+}
+
+/// A replica of `doc`, with its own client ID.
+fn fork(doc: &Doc) -> Doc {
+    let update = doc
+        .transact()
+        .encode_state_as_update_v1(&StateVector::default());
+    let fork = Doc::new();
+    fork.transact_mut()
+        .apply_update(Update::decode_v1(&update).expect("encoded just now"));
+    fork
+}
+
+/// Only edits what changed, so that concurrent edits elsewhere in the text are kept. Offsets are
+/// in bytes, the default of `yrs::Doc`.
+fn replace_text(text: &TextRef, txn: &mut TransactionMut, new: &str) {
+    let old = text.get_string(&*txn);
+    let prefix = common_len(old.chars(), new.chars());
+    let suffix = common_len(old[prefix..].chars().rev(), new[prefix..].chars().rev());
+
+    let removed = old.len() - prefix - suffix;
+    if removed > 0 {
+        text.remove_range(txn, prefix as u32, removed as u32);
+    }
+    let inserted = &new[prefix..new.len() - suffix];
+    if !inserted.is_empty() {
+        text.insert(txn, prefix as u32, inserted);
+    }
+}
+
+/// The length in bytes of the common start of `a` and `b`.
+fn common_len(a: impl Iterator<Item = char>, b: impl Iterator<Item = char>) -> usize {
+    a.zip(b)
+        .take_while(|(a, b)| a == b)
+        .map(|(c, _)| c.len_utf8())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::{NaiveDate, NaiveDateTime};
+    use chrono_tz::Europe::Berlin;
+    use uuid::Uuid;
+    use wire::state::{State, TodoPrelim};
+    use yrs::{GetString, Text, Transact, TransactionMut};
+
+    use super::{
+        load_doc, open_state, save_doc, sync, Conflict, ConflictKind, Field, SyncError, SyncReport,
+        SyncSettings,
+    };
+
+    fn at(day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2023, 11, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    fn edit_doc(
+        settings: &SyncSettings,
+        edit: impl FnOnce(&State, &mut TransactionMut) -> Result<(), SyncError>,
+    ) -> Result<(), SyncError> {
+        let doc = load_doc(&settings.doc)?;
+        let state = open_state(&doc)?;
+        edit(&state, &mut doc.transact_mut())?;
+        save_doc(&doc, &settings.doc)
+    }
+
+    /// The titles of the top-level todos.
+    fn titles(settings: &SyncSettings) -> Result<Vec<String>, SyncError> {
+        let doc = load_doc(&settings.doc)?;
+        let state = open_state(&doc)?;
+        let txn = doc.transact();
+        let todos = state.todos(&txn)?;
+        let titles = todos
+            .iter(&txn)
+            .map(|todo| Ok(todo?.title(&txn)?.get_string(&txn)))
+            .collect::<Result<_, SyncError>>()?;
+        Ok(titles)
+    }
+
+    fn edit_file(path: &std::path::Path, edits: &[(&str, &str)]) -> std::io::Result<()> {
+        let mut contents = fs::read_to_string(path)?;
+        for (from, to) in edits {
+            assert!(contents.contains(from), "{} isn't in {}", from, contents);
+            contents = contents.replacen(from, to, 1);
+        }
+        fs::write(path, contents)
+    }
+
+    #[test]
+    fn test_sync() -> Result<(), SyncError> {
+        let dir = std::env::temp_dir().join(format!("funften_org_sync_{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir)?;
+        let settings = SyncSettings::new(&dir, Berlin);
+        let todos_org = dir.join("todos.org");
+        fs::write(
+            &todos_org,
+            "#+TITLE: Todos\n#+TODO: TODO WAITING | DONE\n\n* TODO Write the sync\n  Carefully.\n* TODO Test it\n",
+        )?;
+
+        // The first sync gives the headlines IDs.
+        let report = sync(&settings, at(1, 8))?;
+        assert_eq!(report.written, vec![todos_org.clone()]);
+        assert!(report.conflicts.is_empty());
+        assert_eq!(titles(&settings)?, vec!["Write the sync", "Test it"]);
+        let contents = fs::read_to_string(&todos_org)?;
+        assert!(contents.starts_with(
+            "#+TITLE: Todos\n#+TODO: TODO WAITING | DONE\n\n* TODO Write the sync\n  :PROPERTIES:\n"
+        ));
+
+        // Nothing changed since.
+        assert_eq!(sync(&settings, at(1, 9))?, SyncReport::default());
+        assert_eq!(titles(&settings)?, vec!["Write the sync", "Test it"]);
+
+        let mut test_id = Uuid::nil();
+        edit_doc(&settings, |state, txn| {
+            let todos = state.todos(&*txn)?;
+            let write = todos.get(&*txn, 0)?.unwrap();
+            write.title(&*txn)?.insert(txn, 10, "org ");
+            let test = todos.get(&*txn, 1)?.unwrap();
+            test_id = *test.id(&*txn)?;
+            test.set_deadline(txn, at(10, 12).into());
+            todos.push(
+                txn,
+                TodoPrelim::new("From the app", at(1, 10), chrono::Duration::hours(1)),
+            );
+            Ok(())
+        })?;
+        edit_file(
+            &todos_org,
+            &[
+                ("* TODO Write the sync", "* DONE Write the sync"),
+                ("Carefully.", "Carefully, with tests."),
+                (
+                    "* TODO Test it\n",
+                    "* WAITING Test it :qa:\n  DEADLINE: <2023-11-09 Thu>\n",
+                ),
+            ],
+        )?;
+
+        let report = sync(&settings, at(1, 11))?;
+        assert_eq!(
+            report.conflicts,
+            vec![Conflict {
+                todo: test_id,
+                title: "Test it".into(),
+                kind: ConflictKind::Changed(Field::Deadline),
+            }]
+        );
+        assert_eq!(
+            report.written,
+            vec![dir.join("inbox.org"), todos_org.clone()]
+        );
+        assert_eq!(
+            titles(&settings)?,
+            vec!["Write the org sync", "Test it", "From the app"]
+        );
+        let contents = fs::read_to_string(&todos_org)?;
+        assert!(contents.contains("* DONE Write the org sync\n"));
+        assert!(contents.contains("  Carefully, with tests.\n"));
+        assert!(contents.contains("* WAITING Test it :qa:\n  DEADLINE: <2023-11-10 Fri 13:00>\n"));
+        assert!(fs::read_to_string(dir.join("inbox.org"))?.contains("* TODO From the app\n"));
+
+        // Deleting in org.
+        fs::write(dir.join("inbox.org"), "")?;
+        assert!(sync(&settings, at(1, 12))?.conflicts.is_empty());
+        assert_eq!(titles(&settings)?, vec!["Write the org sync", "Test it"]);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,619 @@
+//! Every document records, under `SCHEMA_VERSION_KEY` in its root map, the version of
+//! `StatePrelim` it was written with. Documents without one are at version 0.
+//!
+//! When a document is loaded, the migrations in `MIGRATIONS` that it hasn't seen yet are run
+//! in order inside one `TransactionMut`. Two replicas can load (and therefore migrate) the same
+//! document at the same time, and after merging, the last-writer-wins semantics of `yrs::Map`
+//! may even leave the older of the two schema versions in place, so that the same migration runs
+//! again later. Migrations must therefore be idempotent: check before writing, and only write
+//! deterministic values to map keys (never push onto arrays). Even IDs are deterministic: a todo
+//! that has none gets one derived from the yrs ID of its map, which is the same on every replica.
+//! Migrations never create shared types, though: if two replicas create one under the same key,
+//! one of them wins everywhere after merging, losing what was added to the other ones in the
+//! meantime. New fields that are shared types are optional instead, like `TodoPrelim::tags`.
+//!
+//! Versions 3 and 4 did that before, and do nothing now. They can't be squashed away: documents
+//! written in the meantime record them, so without them those documents would be newer than the
+//! latest migration, and be refused as `MigrationError::UnsupportedVersion`. Reusing the numbers
+//! for other migrations would skip those on such documents instead. So every version that was
+//! ever released keeps its entry, even if it has nothing left to do.
+
+use uuid::Uuid;
+use yrs::types::{Branch, BranchID};
+use yrs::{Array, ArrayRef, Map, MapRef, ReadTxn, TransactionMut, ID};
+use yrs_wrappers::{
+    try_from_yrs_value::TryFromYrsValue,
+    validation::Validation,
+    yrs_basic_types::YIdPrelim,
+    yrs_wrapper_error::{UnwrapAny, UnwrapYrsValue, YrsResult, YrsWrapperError},
+};
+
+use super::{State, StatePrelim};
+
+pub type SchemaVersion = u32;
+
+/// The key in the root map under which the `StatePrelim` lives.
+pub const STATE_KEY: &str = "state";
+
+/// The key in the root map under which the schema version lives.
+pub const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+pub struct Migration {
+    /// The schema version of the document after this migration has run.
+    pub version: SchemaVersion,
+    pub description: &'static str,
+    pub migrate: fn(&MapRef, &mut TransactionMut) -> YrsResult<()>,
+}
+
+pub static MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Start recording the schema version",
+        migrate: |_root, _txn| Ok(()),
+    },
+    Migration {
+        version: 2,
+        description: "Give every todo an ID",
+        migrate: add_todo_ids,
+    },
+    Migration {
+        version: 3,
+        // `blocked_by` used to be created here, and is optional now, see `Todo::add_blocker`.
+        description: "Nothing, todos without blockers don't need a list of them",
+        migrate: |_root, _txn| Ok(()),
+    },
+    Migration {
+        version: 4,
+        // `tags` and `properties` used to be created here, and are optional now.
+        description: "Nothing, todos without tags or properties don't need them",
+        migrate: |_root, _txn| Ok(()),
+    },
+    Migration {
+        version: 5,
+        // `YDateTime`s used to be stored as milliseconds since the epoch, and now as strings with
+        // all of their fractional seconds, which replicas from before can't read. Bumping the
+        // version makes them refuse the document instead. The milliseconds are still read, so
+        // they're left alone rather than rewritten, which would undo concurrent edits to them.
+        description: "Store date times as strings, keeping all of their fractional seconds",
+        migrate: |_root, _txn| Ok(()),
+    },
+];
+
+/// The namespace of the (v5) IDs that `add_todo_ids` derives.
+const DERIVED_TODO_ID_NAMESPACE: Uuid = Uuid::from_u128(0x8f3c_2a5e_61d4_4b0f_9c7a_e25b_d1f0_6a93);
+
+fn add_todo_ids(root: &MapRef, txn: &mut TransactionMut) -> YrsResult<()> {
+    for todo in todo_maps(root, txn)? {
+        if !todo.contains_key(txn, "id") {
+            let id = YIdPrelim::from(derived_todo_id(&todo));
+            todo.insert(txn, "id", id);
+        }
+    }
+    Ok(())
+}
+
+/// An ID for a todo that has none, derived from the yrs ID of its map (which every replica
+/// agrees on), so that replicas migrating the todo concurrently give it the same one.
+fn derived_todo_id(todo: &MapRef) -> Uuid {
+    let mut todo = todo.clone();
+    let branch: &mut Branch = todo.as_mut();
+    match branch.id() {
+        BranchID::Nested(ID { client, clock }) => {
+            let name = [client.to_be_bytes().as_slice(), &clock.to_be_bytes()].concat();
+            Uuid::new_v5(&DERIVED_TODO_ID_NAMESPACE, &name)
+        }
+        BranchID::Root(name) => Uuid::new_v5(&DERIVED_TODO_ID_NAMESPACE, name.as_bytes()),
+    }
+}
+
+pub fn current_schema_version() -> SchemaVersion {
+    latest_version(MIGRATIONS)
+}
+
+fn latest_version(migrations: &[Migration]) -> SchemaVersion {
+    migrations.last().map(|m| m.version).unwrap_or(0)
+}
+
+#[derive(Debug, thiserror::Error, Clone, PartialEq, Eq)]
+pub enum MigrationError {
+    #[error("YrsWrapperError: {0}")]
+    Yrs(#[from] YrsWrapperError),
+
+    #[error("Document has schema version {found}, but only up to {supported} is supported")]
+    UnsupportedVersion {
+        found: SchemaVersion,
+        supported: SchemaVersion,
+    },
+
+    #[error("Migration to schema version {version} failed: {source}")]
+    Failed {
+        version: SchemaVersion,
+        source: YrsWrapperError,
+    },
+}
+
+pub fn schema_version(root: &MapRef, txn: &impl ReadTxn) -> YrsResult<SchemaVersion> {
+    match root.get(txn, SCHEMA_VERSION_KEY) {
+        Some(value) => SchemaVersion::try_from(value.unwrap_yrs_any()?.unwrap_any_bigint()?)
+            .map_err(|_| YrsWrapperError::BigIntOutOfRange),
+        None => Ok(0),
+    }
+}
+
+fn set_schema_version(root: &MapRef, txn: &mut TransactionMut, version: SchemaVersion) {
+    root.insert(txn, SCHEMA_VERSION_KEY, i64::from(version));
+}
+
+/// Runs the migrations in `MIGRATIONS` that the document hasn't seen yet, and returns the
+/// resulting schema version.
+pub fn migrate(root: &MapRef, txn: &mut TransactionMut) -> Result<SchemaVersion, MigrationError> {
+    migrate_with(root, txn, MIGRATIONS)
+}
+
+/// Like `migrate`, but with a custom registry. `migrations` must be sorted by version.
+pub fn migrate_with(
+    root: &MapRef,
+    txn: &mut TransactionMut,
+    migrations: &[Migration],
+) -> Result<SchemaVersion, MigrationError> {
+    debug_assert!(migrations.windows(2).all(|w| w[0].version < w[1].version));
+
+    let supported = latest_version(migrations);
+    let mut version = schema_version(root, txn)?;
+    if version > supported {
+        return Err(MigrationError::UnsupportedVersion {
+            found: version,
+            supported,
+        });
+    }
+
+    for migration in migrations.iter().filter(|m| m.version > version) {
+        (migration.migrate)(root, txn).map_err(|source| MigrationError::Failed {
+            version: migration.version,
+            source,
+        })?;
+        version = migration.version;
+    }
+
+    if schema_version(root, txn)? < version {
+        set_schema_version(root, txn, version);
+    }
+
+    Ok(version)
+}
+
+/// Inserts a brand new state into `root`, marked as being at the current schema version.
+pub fn init_state(root: &MapRef, txn: &mut TransactionMut, state: StatePrelim) -> State {
+    let state = root.insert(txn, STATE_KEY, state);
+    set_schema_version(root, txn, current_schema_version());
+    state
+}
+
+/// Migrates the document, and then reads the state out of it, checking all of it.
+pub fn load_state(root: &MapRef, txn: &mut TransactionMut) -> Result<State, MigrationError> {
+    load_state_with(root, txn, Validation::Eager)
+}
+
+/// Like `load_state`, but with a choice of how much of the state is checked up front.
+pub fn load_state_with(
+    root: &MapRef,
+    txn: &mut TransactionMut,
+    validation: Validation,
+) -> Result<State, MigrationError> {
+    migrate(root, txn)?;
+
+    let state = root
+        .get(txn, STATE_KEY)
+        .ok_or_else(|| YrsWrapperError::YMapMissingAttr {
+            attr: STATE_KEY.to_string(),
+        })?;
+    Ok(State::try_from_yrs_value_with(state, txn, validation)?)
+}
+
+/// The raw maps of every todo in the document, children included. Migrations have to work on
+/// raw maps, because the generated wrappers only know how to read the latest schema.
+pub fn todo_maps(root: &MapRef, txn: &impl ReadTxn) -> YrsResult<Vec<MapRef>> {
+    fn collect(todos: ArrayRef, txn: &impl ReadTxn, maps: &mut Vec<MapRef>) -> YrsResult<()> {
+        for todo in todos.iter(txn) {
+            let todo = todo.unwrap_yrs_map()?;
+            if let Some(child_todos) = todo.get(txn, "child_todos") {
+                collect(child_todos.unwrap_yrs_array()?, txn, maps)?;
+            }
+            maps.push(todo);
+        }
+        Ok(())
+    }
+
+    let mut maps = vec![];
+    let Some(state) = root.get(txn, STATE_KEY) else {
+        return Ok(maps);
+    };
+    if let Some(todos) = state.unwrap_yrs_map()?.get(txn, "todos") {
+        collect(todos.unwrap_yrs_array()?, txn, &mut maps)?;
+    }
+    Ok(maps)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use yrs::updates::decoder::Decode;
+    use yrs::{
+        Doc, GetString, Map, MapRef, ReadTxn, StateVector, Transact, TransactionMut, Update,
+    };
+    use yrs_wrappers::{
+        snapshot::ToSnapshot,
+        validation::Validation,
+        yrs_basic_types::YStringPrelim,
+        yrs_wrapper_error::{UnwrapAny, UnwrapYrsValue, YrsResult, YrsWrapperError},
+    };
+
+    use super::{
+        current_schema_version, init_state, load_state, load_state_with, migrate_with,
+        schema_version, todo_maps, Migration, MigrationError, SchemaVersion, MIGRATIONS,
+    };
+    use crate::state::test_utils::todo_prelim;
+    use crate::state::{BlockerError, StatePrelim};
+
+    const PINNED_VERSION: SchemaVersion = 6;
+
+    fn add_pinned(root: &MapRef, txn: &mut TransactionMut) -> YrsResult<()> {
+        for todo in todo_maps(root, txn)? {
+            if !todo.contains_key(txn, "pinned") {
+                todo.insert(txn, "pinned", false);
+            }
+        }
+        Ok(())
+    }
+
+    fn migrations_with_pinned() -> Vec<Migration> {
+        MIGRATIONS
+            .iter()
+            .map(|m| Migration {
+                version: m.version,
+                description: m.description,
+                migrate: m.migrate,
+            })
+            .chain([Migration {
+                version: PINNED_VERSION,
+                description: "Add a pinned flag to todos",
+                migrate: add_pinned,
+            }])
+            .collect()
+    }
+
+    /// A document written before schema versions existed.
+    fn unversioned_doc(client_id: u64) -> (Doc, MapRef) {
+        let doc = Doc::with_client_id(client_id);
+        let root = doc.get_or_insert_map("root");
+        let mut txn = doc.try_transact_mut().unwrap();
+        root.insert(
+            &mut txn,
+            "state",
+            StatePrelim {
+                todos: vec![todo_prelim("parent", vec![todo_prelim("child", vec![])])].into(),
+            },
+        );
+        drop(txn);
+        (doc, root)
+    }
+
+    #[test]
+    fn test_load_unversioned_doc() -> Result<(), MigrationError> {
+        let (doc, root) = unversioned_doc(1);
+        let mut txn = doc.try_transact_mut().unwrap();
+
+        assert_eq!(schema_version(&root, &txn)?, 0);
+        let state = load_state(&root, &mut txn)?;
+        assert_eq!(schema_version(&root, &txn)?, current_schema_version());
+        assert_eq!(state.todos(&txn)?.len(&txn), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_init_state_is_current() -> Result<(), MigrationError> {
+        let doc = Doc::new();
+        let root = doc.get_or_insert_map("root");
+        let mut txn = doc.try_transact_mut().unwrap();
+
+        init_state(
+            &root,
+            &mut txn,
+            StatePrelim {
+                todos: vec![].into(),
+            },
+        );
+        assert_eq!(schema_version(&root, &txn)?, current_schema_version());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_is_idempotent() -> Result<(), MigrationError> {
+        let (doc, root) = unversioned_doc(1);
+        let mut txn = doc.try_transact_mut().unwrap();
+        let migrations = migrations_with_pinned();
+
+        assert_eq!(migrate_with(&root, &mut txn, &migrations)?, PINNED_VERSION);
+        // Pretend a concurrent replica's older version won.
+        root.insert(&mut txn, super::SCHEMA_VERSION_KEY, 2i64);
+        assert_eq!(migrate_with(&root, &mut txn, &migrations)?, PINNED_VERSION);
+
+        let todos = todo_maps(&root, &txn)?;
+        assert_eq!(todos.len(), 2);
+        for todo in todos {
+            assert!(!todo
+                .get(&txn, "pinned")
+                .unwrap()
+                .unwrap_yrs_any()?
+                .unwrap_any_bool()?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_migrations_converge() -> Result<(), MigrationError> {
+        let (doc_a, root_a) = unversioned_doc(1);
+        let doc_b = Doc::with_client_id(2);
+        let root_b = doc_b.get_or_insert_map("root");
+        let initial = doc_a
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        doc_b
+            .transact_mut()
+            .apply_update(Update::decode_v1(&initial).unwrap());
+
+        let migrations = migrations_with_pinned();
+        migrate_with(&root_a, &mut doc_a.transact_mut(), &migrations)?;
+        migrate_with(&root_b, &mut doc_b.transact_mut(), &migrations)?;
+
+        let update_a = doc_a
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        let update_b = doc_b
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        doc_a
+            .transact_mut()
+            .apply_update(Update::decode_v1(&update_b).unwrap());
+        doc_b
+            .transact_mut()
+            .apply_update(Update::decode_v1(&update_a).unwrap());
+
+        for (doc, root) in [(doc_a, root_a), (doc_b, root_b)] {
+            let txn = doc.transact();
+            assert_eq!(schema_version(&root, &txn)?, PINNED_VERSION);
+            let todos = todo_maps(&root, &txn)?;
+            assert_eq!(todos.len(), 2);
+            for todo in todos {
+                assert!(!todo
+                    .get(&txn, "pinned")
+                    .unwrap()
+                    .unwrap_yrs_any()?
+                    .unwrap_any_bool()?);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_state_with() -> Result<(), MigrationError> {
+        let (doc, root) = unversioned_doc(1);
+        let mut txn = doc.try_transact_mut().unwrap();
+        for validation in [Validation::Eager, Validation::Lazy, Validation::Cached] {
+            load_state_with(&root, &mut txn, validation)?;
+        }
+
+        // Corrupt the child todo.
+        let child = todo_maps(&root, &txn)?[0].clone();
+        child.insert(&mut txn, "completed", "not a bool".to_string());
+
+        // The insert advanced the state vector, so the cached result is not reused.
+        assert!(load_state_with(&root, &mut txn, Validation::Cached).is_err());
+        assert!(load_state_with(&root, &mut txn, Validation::Eager).is_err());
+
+        // Deletions don't advance the state vector, but aren't missed either.
+        child.insert(&mut txn, "completed", true);
+        load_state_with(&root, &mut txn, Validation::Cached)?;
+        child.remove(&mut txn, "completed");
+        assert!(load_state_with(&root, &mut txn, Validation::Cached).is_err());
+        child.insert(&mut txn, "completed", "not a bool".to_string());
+
+        // Lazily loading succeeds, and the error surfaces when the child is accessed.
+        let state = load_state_with(&root, &mut txn, Validation::Lazy)?;
+        let parent = state.todos(&txn)?.get(&txn, 0)?.unwrap();
+        assert_eq!(parent.title(&txn)?.get_string(&txn), "parent");
+        let child = parent.child_todos(&txn)?.get(&txn, 0)?.unwrap();
+        assert_eq!(
+            child.completed(&txn).unwrap_err().root_cause(),
+            &YrsWrapperError::UnexpectedAnyVariant {
+                expected: "Bool",
+                found: "String",
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_doc_from_the_future() -> Result<(), MigrationError> {
+        let (doc, root) = unversioned_doc(1);
+        let mut txn = doc.try_transact_mut().unwrap();
+        root.insert(&mut txn, super::SCHEMA_VERSION_KEY, 100i64);
+
+        assert_eq!(
+            load_state(&root, &mut txn).unwrap_err(),
+            MigrationError::UnsupportedVersion {
+                found: 100,
+                supported: current_schema_version(),
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_date_times() -> Result<(), MigrationError> {
+        let (doc, root) = unversioned_doc(1);
+        let mut txn = doc.try_transact_mut().unwrap();
+        let created_at = chrono::NaiveDate::from_ymd_opt(2023, 5, 1)
+            .unwrap()
+            .and_hms_milli_opt(8, 0, 0, 123)
+            .unwrap();
+        for todo in todo_maps(&root, &txn)? {
+            todo.insert(&mut txn, "created_at", created_at.timestamp_millis());
+        }
+        root.insert(&mut txn, super::SCHEMA_VERSION_KEY, 4i64);
+
+        // Date times stored as milliseconds are kept as they are, and still read.
+        let state = load_state(&root, &mut txn)?;
+        assert_eq!(schema_version(&root, &txn)?, 5);
+        let parent = state.todos(&txn)?.get(&txn, 0)?.unwrap();
+        assert_eq!(*parent.created_at(&txn)?, created_at);
+
+        // Replicas that can only read milliseconds refuse the document.
+        assert_eq!(
+            migrate_with(&root, &mut txn, &MIGRATIONS[..4]).unwrap_err(),
+            MigrationError::UnsupportedVersion {
+                found: 5,
+                supported: 4,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_todo_ids() -> Result<(), MigrationError> {
+        let (doc, root) = unversioned_doc(1);
+        let mut txn = doc.try_transact_mut().unwrap();
+        for todo in todo_maps(&root, &txn)? {
+            todo.remove(&mut txn, "id");
+        }
+
+        let state = load_state(&root, &mut txn)?;
+        let index = state.todo_index(&txn)?;
+        assert_eq!(index.len(), 2);
+
+        // Ids that already exist are kept.
+        let ids = index.iter().map(|(id, _)| id).collect::<Vec<_>>();
+        root.insert(&mut txn, super::SCHEMA_VERSION_KEY, 1i64);
+        let state = load_state(&root, &mut txn)?;
+        for id in ids {
+            assert!(state.todo_index(&txn)?.contains(id));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_todo_ids_agree() -> Result<(), MigrationError> {
+        let (doc_a, root_a) = unversioned_doc(1);
+        let mut txn = doc_a.transact_mut();
+        for todo in todo_maps(&root_a, &txn)? {
+            todo.remove(&mut txn, "id");
+        }
+        drop(txn);
+        let doc_b = Doc::with_client_id(2);
+        let root_b = doc_b.get_or_insert_map("root");
+        let initial = doc_a
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        doc_b
+            .transact_mut()
+            .apply_update(Update::decode_v1(&initial).unwrap());
+
+        let ids = [(&doc_a, &root_a), (&doc_b, &root_b)].map(|(doc, root)| {
+            let mut txn = doc.transact_mut();
+            let state = load_state(root, &mut txn).unwrap();
+            let index = state.todo_index(&txn).unwrap();
+            index.iter().map(|(id, _)| id).collect::<BTreeSet<_>>()
+        });
+        assert_eq!(ids[0].len(), 2);
+        assert_eq!(ids[0], ids[1]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_blocked_by() -> Result<(), MigrationError> {
+        let (doc, root) = unversioned_doc(1);
+        let mut txn = doc.try_transact_mut().unwrap();
+        for todo in todo_maps(&root, &txn)? {
+            todo.remove(&mut txn, "blocked_by");
+        }
+
+        let state = load_state(&root, &mut txn)?;
+        let parent = state.todos(&txn)?.get(&txn, 0)?.unwrap();
+        let child = parent.child_todos(&txn)?.get(&txn, 0)?.unwrap();
+        assert!(parent.blocker_ids(&txn)?.is_empty());
+
+        // Migrating doesn't create the list, adding the first blocker does.
+        let raw_parent = todo_maps(&root, &txn)?[1].clone();
+        assert!(!raw_parent.contains_key(&txn, "blocked_by"));
+        let (parent_id, child_id) = (*parent.id(&txn)?, *child.id(&txn)?);
+        let index = state.todo_index(&txn)?;
+        parent.add_blocker(&mut txn, &index, child_id).unwrap();
+        assert!(raw_parent.contains_key(&txn, "blocked_by"));
+        assert_eq!(parent.blocker_ids(&txn)?, vec![child_id]);
+
+        // Todos can't block themselves, even through other todos.
+        assert_eq!(
+            parent.add_blocker(&mut txn, &index, parent_id),
+            Err(BlockerError::SelfBlocking { todo: parent_id })
+        );
+        assert_eq!(
+            child.add_blocker(&mut txn, &index, parent_id),
+            Err(BlockerError::Cycle {
+                todo: child_id,
+                blocker: parent_id
+            })
+        );
+        assert!(child.blocker_ids(&txn)?.is_empty());
+
+        parent.remove_blocker(&mut txn, child_id)?;
+        assert!(parent.blocker_ids(&txn)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tags_and_properties() -> Result<(), MigrationError> {
+        let (doc, root) = unversioned_doc(1);
+        let mut txn = doc.try_transact_mut().unwrap();
+        for todo in todo_maps(&root, &txn)? {
+            todo.remove(&mut txn, "tags");
+            todo.remove(&mut txn, "properties");
+        }
+
+        let state = load_state(&root, &mut txn)?;
+        let parent = state.todos(&txn)?.get(&txn, 0)?.unwrap();
+
+        // Migrating doesn't create them, and todos without them have none.
+        assert!(parent.tags(&txn).is_none());
+        assert!(parent.properties(&txn).is_none());
+        let snapshot = parent.to_snapshot(&txn)?;
+        assert!(snapshot.tag_list().is_empty());
+        assert!(snapshot.property_map().is_empty());
+
+        parent.set_tags(&mut txn, vec![YStringPrelim::from("work")].into());
+        parent.set_properties(
+            &mut txn,
+            [("CATEGORY", YStringPrelim::from("funften"))]
+                .into_iter()
+                .collect(),
+        );
+        let snapshot = parent.to_snapshot(&txn)?;
+        assert_eq!(snapshot.tag_list(), ["work"]);
+        assert_eq!(
+            *snapshot.property_map(),
+            BTreeMap::from([("CATEGORY".to_string(), "funften".to_string())])
+        );
+
+        Ok(())
+    }
+}
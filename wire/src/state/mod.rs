@@ -1,11 +1,29 @@
 pub mod example;
+pub mod migration;
+pub mod recurrence;
+pub mod undo;
 
-use yrs::TextPrelim;
+#[cfg(test)]
+mod test_utils;
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+
+use uuid::Uuid;
+use yrs::{TextPrelim, TransactionMut};
 use yrs_wrappers::{
+    snapshot::ToSnapshot,
     ybox::YBox,
-    yrs_basic_types::{YBoolPrelim, YDateTimePrelim, YDurationPrelim},
+    yref::{YIndex, YRefPrelim},
+    yrs_basic_types::{
+        YBoolPrelim, YDateTimePrelim, YDurationPrelim, YF64Prelim, YIdPrelim, YStringPrelim,
+        YU32Prelim, YWeekdayPrelim, YZonedDateTimePrelim,
+    },
+    yrs_map::YrsMapPrelim,
+    yrs_ordered::{compare_positions, Positioned},
     yrs_struct::YrsStruct,
-    yrs_vec::YrsVecPrelim,
+    yrs_vec::{YrsVec, YrsVecPrelim},
+    yrs_wrapper_error::{YrsResult, YrsWrapperError},
 };
 
 #[derive(YrsStruct)]
@@ -20,27 +38,386 @@ pub struct ActualExecutionPrelim {
     pub end: Option<YDateTimePrelim>,
 }
 
+#[derive(YrsStruct)]
+pub enum FrequencyPrelim {
+    Daily,
+    Weekly,
+}
+
+#[derive(YrsStruct)]
+pub enum RecurrenceEndPrelim {
+    /// The last occurrence starts at or before this.
+    Until(YDateTimePrelim),
+    /// The number of occurrences, skipped ones included.
+    Count(YU32Prelim),
+}
+
+#[derive(YrsStruct)]
+pub enum OccurrenceOverridePrelim {
+    Completed,
+    Skipped,
+}
+
+/// When a todo recurs. See `recurrence` for how the rule is expanded into occurrences.
+#[derive(YrsStruct)]
+pub struct RecurrencePrelim {
+    /// The start of the first occurrence. All occurrences start at the same time of day in its
+    /// time zone.
+    pub start: YZonedDateTimePrelim,
+    pub frequency: FrequencyPrelim,
+    /// The number of days or weeks between occurrences. `0` is treated like `1`.
+    pub interval: YU32Prelim,
+    /// For weekly recurrences, the days of the week to recur on, defaulting to the day of
+    /// `start`. For daily recurrences, the only days to recur on, if not empty.
+    pub by_weekday: YrsVecPrelim<YWeekdayPrelim>,
+    pub end: Option<RecurrenceEndPrelim>,
+    /// By the date (in the time zone of `start`) of the occurrence they apply to.
+    pub overrides: YrsMapPrelim<OccurrenceOverridePrelim>,
+}
+
+#[derive(YrsStruct)]
+pub enum ActivityKindPrelim {
+    /// Done once the estimated duration has been spent on it, like practising an instrument.
+    TimeBased,
+    /// Done once its goal is reached, however long that takes.
+    GoalBased,
+}
+
+/// How much of a todo to schedule at once.
+#[derive(YrsStruct)]
+pub struct ChunkSizePrelim {
+    pub min: YDurationPrelim,
+    pub max: YDurationPrelim,
+}
+
+#[derive(YrsStruct)]
+pub enum TimeWindowPrelim {
+    /// For recurring todos. Fractions of the day for daily recurrences, or of the week (starting
+    /// on Monday) for weekly ones, in the time zone of the recurrence.
+    Fractional { start: YF64Prelim, end: YF64Prelim },
+    /// For todos that don't recur.
+    Absolute {
+        start: YDateTimePrelim,
+        end: YDateTimePrelim,
+    },
+}
+
+/// What a scheduler has to respect when planning the executions of a todo. See
+/// `core_logic::constraints` for the rules these have to follow.
+#[derive(YrsStruct)]
+pub struct SchedulingPrelim {
+    pub activity: ActivityKindPrelim,
+    pub chunk_size: Option<ChunkSizePrelim>,
+    /// When the todo should be scheduled, if possible. Empty means any time.
+    pub ideal_windows: YrsVecPrelim<TimeWindowPrelim>,
+    /// When the todo must be scheduled, which has to be within `ideal_windows`, unless that's
+    /// empty. Empty means any time.
+    pub required_windows: YrsVecPrelim<TimeWindowPrelim>,
+}
+
+/// Like the TODO keywords of org-mode.
+#[derive(YrsStruct)]
+pub enum TodoStatusPrelim {
+    Todo,
+    /// To be worked on next.
+    Next,
+    /// Waiting on something, or someone, else.
+    Waiting,
+    Done,
+    /// Closed without being done.
+    Cancelled,
+}
+
+impl TodoStatusSnapshot {
+    /// Whether todos with this status are completed.
+    pub fn is_closed(&self) -> bool {
+        matches!(
+            self,
+            TodoStatusSnapshot::Done | TodoStatusSnapshot::Cancelled
+        )
+    }
+
+    /// `status`, unless it contradicts `completed`, in which case `completed` wins.
+    fn resolve(status: Option<TodoStatusSnapshot>, completed: bool) -> TodoStatusSnapshot {
+        match status {
+            Some(status) if status.is_closed() == completed => status,
+            _ if completed => TodoStatusSnapshot::Done,
+            _ => TodoStatusSnapshot::Todo,
+        }
+    }
+}
+
 #[derive(YrsStruct)]
 pub struct TodoPrelim {
+    pub id: YIdPrelim,
     pub title: TextPrelim<String>,
     pub text: TextPrelim<String>,
     pub completed: YBoolPrelim,
+    /// Finer than `completed`, which stays the source of truth for whether the todo is done: a
+    /// status that contradicts it, like a concurrent edit can leave behind, is ignored. See
+    /// `Todo::current_status`.
+    pub status: Option<TodoStatusPrelim>,
+    /// When the todo was completed, if that's known.
+    pub completed_at: Option<YDateTimePrelim>,
     pub created_at: YDateTimePrelim,
     pub estimated_duration: YDurationPrelim,
     pub planned_executions: YrsVecPrelim<PlannedExecutionPrelim>,
     pub actual_executions: YrsVecPrelim<ActualExecutionPrelim>,
+    /// In the order of their `position`s, see `YrsVec::in_order`.
     pub child_todos: YBox<YrsVecPrelim<TodoPrelim>>,
+    /// Where the todo is in the list it's in, so that it can be moved without moving it in the
+    /// array, see `Positioned`. Todos that have never been moved may have none.
+    pub position: Option<YStringPrelim>,
+    /// The todos that have to be completed before this one can start. Todos from before there
+    /// were blockers have none, which is the same as an empty list. See `Todo::add_blocker`.
+    pub blocked_by: Option<YrsVecPrelim<YRefPrelim<Todo>>>,
     pub deadline: Option<YDateTimePrelim>,
+    /// Like org-mode priorities, usually a letter from `A` (the highest) to `C`.
+    pub priority: Option<YStringPrelim>,
+    /// Missing, like `properties`, in todos from before there were tags, which is the same as
+    /// having none. See `TodoSnapshot::tag_list`.
+    pub tags: Option<YrsVecPrelim<YStringPrelim>>,
+    /// Key/value metadata that doesn't have a field of its own, like the properties of an
+    /// imported org headline.
+    pub properties: Option<YrsMapPrelim<YStringPrelim>>,
+    pub recurrence: Option<RecurrencePrelim>,
+    pub scheduling: Option<SchedulingPrelim>,
 }
 
 #[derive(YrsStruct)]
 pub struct StatePrelim {
+    /// In the order of their `position`s, like `TodoPrelim::child_todos`.
     pub todos: YrsVecPrelim<TodoPrelim>,
 }
 
+impl State {
+    /// All the todos in the document, children included, by ID.
+    pub fn todo_index(&self, txn: &impl yrs::ReadTxn) -> YrsResult<YIndex<Todo>> {
+        todo_index(&self.todos(txn)?, txn)
+    }
+}
+
+impl TodoPrelim {
+    /// An open todo with a fresh ID, no text, and nothing else set.
+    pub fn new(
+        title: &str,
+        created_at: chrono::NaiveDateTime,
+        estimated_duration: chrono::Duration,
+    ) -> Self {
+        TodoPrelim {
+            id: YIdPrelim::new(),
+            title: TextPrelim::new(title.into()),
+            text: TextPrelim::new("".into()),
+            completed: false.into(),
+            status: None,
+            completed_at: None,
+            created_at: created_at.into(),
+            estimated_duration: estimated_duration.into(),
+            planned_executions: vec![].into(),
+            actual_executions: vec![].into(),
+            child_todos: YBox::new(vec![].into()),
+            position: None,
+            blocked_by: Some(vec![].into()),
+            deadline: None,
+            priority: None,
+            tags: Some(vec![].into()),
+            properties: Some(Default::default()),
+            recurrence: None,
+            scheduling: None,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error, Clone, PartialEq, Eq)]
+pub enum BlockerError {
+    #[error("YrsWrapperError: {0}")]
+    Yrs(#[from] YrsWrapperError),
+
+    #[error("The todo {todo} can't block itself")]
+    SelfBlocking { todo: Uuid },
+
+    #[error("The todo {todo} already blocks {blocker}, directly or through other todos")]
+    Cycle { todo: Uuid, blocker: Uuid },
+}
+
+impl Todo {
+    /// `status`, or `Todo` or `Done` if there is none, or it contradicts `completed`.
+    pub fn current_status(&self, txn: &impl yrs::ReadTxn) -> YrsResult<TodoStatusSnapshot> {
+        let status = match self.status(txn) {
+            Some(status) => Some(status?.to_snapshot(txn)?),
+            None => None,
+        };
+        Ok(TodoStatusSnapshot::resolve(status, *self.completed(txn)?))
+    }
+
+    /// Sets the status, and `completed` to match it.
+    pub fn change_status(&self, txn: &mut TransactionMut, status: TodoStatusSnapshot) {
+        self.set_completed(txn, status.is_closed().into());
+        self.set_status(txn, status.into());
+    }
+
+    /// The IDs of the todos blocking this one, including the ones that have been deleted since.
+    pub fn blocker_ids(&self, txn: &impl yrs::ReadTxn) -> YrsResult<Vec<Uuid>> {
+        let Some(blocked_by) = self.blocked_by(txn) else {
+            return Ok(vec![]);
+        };
+        blocked_by?
+            .iter(txn)
+            .map(|blocker| Ok(blocker?.id()))
+            .collect()
+    }
+
+    /// Makes `blocker` block this todo, unless it already does, or it would block itself, even
+    /// through other todos. `index` has to contain every todo of the document, like
+    /// `State::todo_index`. Replicas can still close a cycle concurrently, see
+    /// `core_logic::dependencies`.
+    ///
+    /// Todos from before there were blockers only get a list when their first blocker is added,
+    /// rather than in a migration that every replica would run, each creating its own list.
+    pub fn add_blocker(
+        &self,
+        txn: &mut TransactionMut,
+        index: &YIndex<Todo>,
+        blocker: Uuid,
+    ) -> Result<(), BlockerError> {
+        let todo = *self.id(&*txn)?;
+        if blocker == todo {
+            return Err(BlockerError::SelfBlocking { todo });
+        }
+        if self.blocker_ids(&*txn)?.contains(&blocker) {
+            return Ok(());
+        }
+        // Whether this todo already blocks `blocker`, directly or through other todos.
+        let mut seen = BTreeSet::new();
+        let mut stack = vec![blocker];
+        while let Some(current) = stack.pop() {
+            let Some(current) = index.get(current) else {
+                continue;
+            };
+            for next in current.blocker_ids(&*txn)? {
+                if next == todo {
+                    return Err(BlockerError::Cycle { todo, blocker });
+                }
+                if seen.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+
+        match self.blocked_by(&*txn) {
+            Some(blocked_by) => {
+                blocked_by?.push(txn, YRefPrelim::new(blocker));
+            }
+            None => {
+                self.set_blocked_by(txn, vec![YRefPrelim::new(blocker)].into());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn remove_blocker(&self, txn: &mut TransactionMut, blocker: Uuid) -> YrsResult<()> {
+        if let Some(blocked_by) = self.blocked_by(&*txn) {
+            blocked_by?.retain(txn, |r, _| r.id() != blocker)?;
+        }
+        Ok(())
+    }
+}
+
+impl Positioned for Todo {
+    fn position_in_list(&self, txn: &impl yrs::ReadTxn) -> YrsResult<Option<String>> {
+        Ok(self
+            .position(txn)
+            .transpose()?
+            .map(|position| position.to_string()))
+    }
+
+    fn set_position_in_list(&self, txn: &mut TransactionMut, position: String) {
+        self.set_position(txn, position.into());
+    }
+}
+
+impl TodoSnapshot {
+    /// Like `TodoPrelim::new`.
+    pub fn new(
+        title: &str,
+        created_at: chrono::NaiveDateTime,
+        estimated_duration: chrono::Duration,
+    ) -> Self {
+        TodoSnapshot {
+            id: Uuid::new_v4(),
+            title: title.into(),
+            text: "".into(),
+            completed: false,
+            status: None,
+            completed_at: None,
+            created_at,
+            estimated_duration,
+            planned_executions: vec![],
+            actual_executions: vec![],
+            child_todos: Box::default(),
+            position: None,
+            blocked_by: Some(vec![]),
+            deadline: None,
+            priority: None,
+            tags: Some(vec![]),
+            properties: Some(Default::default()),
+            recurrence: None,
+            scheduling: None,
+        }
+    }
+
+    /// `todos` in the order of their positions, like `YrsVec::in_order` orders them in the
+    /// document.
+    pub fn in_order(todos: &[TodoSnapshot]) -> Vec<&TodoSnapshot> {
+        let mut todos = todos.iter().collect::<Vec<_>>();
+        todos.sort_by(|a, b| compare_positions(a.position.as_deref(), b.position.as_deref()));
+        todos
+    }
+
+    /// Like `Todo::current_status`.
+    pub fn current_status(&self) -> TodoStatusSnapshot {
+        TodoStatusSnapshot::resolve(self.status.clone(), self.completed)
+    }
+
+    /// `tags`, or none if the todo is from before there were tags.
+    pub fn tag_list(&self) -> &[String] {
+        self.tags.as_deref().unwrap_or_default()
+    }
+
+    /// `properties`, or none if the todo is from before there were properties.
+    pub fn property_map(&self) -> Cow<'_, BTreeMap<String, String>> {
+        match &self.properties {
+            Some(properties) => Cow::Borrowed(properties),
+            None => Cow::Owned(BTreeMap::new()),
+        }
+    }
+}
+
+/// `todos`, and all their descendants, by ID.
+pub fn todo_index(todos: &YrsVec<Todo>, txn: &impl yrs::ReadTxn) -> YrsResult<YIndex<Todo>> {
+    fn collect(
+        todos: &YrsVec<Todo>,
+        txn: &impl yrs::ReadTxn,
+        index: &mut YIndex<Todo>,
+    ) -> YrsResult<()> {
+        for todo in todos.iter(txn) {
+            let todo = todo?;
+            collect(&todo.child_todos(txn)?, txn, index)?;
+            index.insert(todo.id(txn)?, todo);
+        }
+        Ok(())
+    }
+
+    let mut index = YIndex::new();
+    collect(todos, txn, &mut index)?;
+    Ok(index)
+}
+
 #[cfg(test)]
 mod tests {
+    use std::cell::RefCell;
     use std::ops::Deref;
+    use std::rc::Rc;
     use yrs::Doc;
     use yrs::GetString;
     use yrs::Map;
@@ -49,21 +426,29 @@ mod tests {
     use yrs_wrappers::ybox::YBox;
     use yrs_wrappers::yrs_wrapper_error::YrsResult;
 
+    use yrs_wrappers::snapshot::ToSnapshot;
+    use yrs_wrappers::try_from_yrs_value::TryFromYrsValue;
+    use yrs_wrappers::yrs_serde::YrsSerialize;
+    use yrs_wrappers::yrs_wrapper_error::YrsWrapperError;
+
+    use super::test_utils::{start, test_doc, todo_prelim};
     use super::ActualExecutionPrelim;
     use super::PlannedExecutionPrelim;
+    use super::State;
     use super::StatePrelim;
+    use super::TodoChange;
     use super::TodoPrelim;
+    use super::{PlannedExecutionSnapshot, TodoSnapshot, TodoStatusSnapshot};
 
     #[test]
     fn test_new_state() -> YrsResult<()> {
-        let start = chrono::Utc::now().naive_utc();
+        let start = chrono::Utc::now()
+            .date_naive()
+            .and_hms_nano_opt(8, 0, 0, 123_456_789)
+            .unwrap();
         let state_prelim = StatePrelim {
             todos: vec![TodoPrelim {
-                title: TextPrelim::new("yo".into()),
                 text: TextPrelim::new("yo".into()),
-                completed: false.into(),
-                created_at: chrono::Utc::now().naive_utc().into(),
-                estimated_duration: chrono::Duration::seconds(60).into(),
                 planned_executions: vec![PlannedExecutionPrelim {
                     start: start.into(),
                     end: chrono::Utc::now().naive_utc().into(),
@@ -74,14 +459,17 @@ mod tests {
                     end: None,
                 }]
                 .into(),
-                child_todos: YBox::new(vec![].into()),
                 deadline: Some((start + chrono::Duration::days(1)).into()),
+                ..TodoPrelim::new(
+                    "yo",
+                    chrono::Utc::now().naive_utc(),
+                    chrono::Duration::seconds(60),
+                )
             }]
             .into(),
         };
 
-        let doc = Doc::new();
-        let map = doc.get_or_insert_map("state");
+        let (doc, map) = test_doc();
         let mut txn = doc.try_transact_mut().unwrap();
 
         let state = map.insert(&mut txn, "state", state_prelim);
@@ -102,31 +490,515 @@ mod tests {
             &chrono::Duration::seconds(60)
         );
 
+        // Date times keep all of their fractional seconds.
+        let planned_execution = first_todo.planned_executions(&txn)?.get(&txn, 0)?.unwrap();
+        assert_eq!(planned_execution.start(&txn)?.deref(), &start);
         assert_eq!(
-            first_todo
-                .planned_executions(&txn)?
+            first_todo.deadline(&txn).transpose()?.unwrap().deref(),
+            &(start + chrono::Duration::days(1))
+        );
+
+        // Documents from before that have the milliseconds since the epoch.
+        let start = start.date().and_hms_milli_opt(8, 0, 0, 123).unwrap();
+        drop(txn);
+        let mut txn = doc.try_transact_mut().unwrap();
+        planned_execution
+            .0
+            .insert(&mut txn, "start", start.timestamp_millis());
+        assert_eq!(planned_execution.start(&txn)?.deref(), &start);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_setters() -> YrsResult<()> {
+        let start = start();
+
+        let (doc, map) = test_doc();
+        let mut txn = doc.try_transact_mut().unwrap();
+
+        let todo = map.insert(
+            &mut txn,
+            "todo",
+            TodoPrelim {
+                text: TextPrelim::new("yo".into()),
+                ..TodoPrelim::new("yo", start, chrono::Duration::seconds(60))
+            },
+        );
+
+        todo.set_completed(&mut txn, true.into());
+        assert_eq!(todo.completed(&txn)?.deref(), &true);
+
+        todo.set_deadline(&mut txn, start.into());
+        assert_eq!(todo.deadline(&txn).transpose()?.unwrap().deref(), &start);
+
+        todo.clear_deadline(&mut txn);
+        assert!(todo.deadline(&txn).is_none());
+
+        // Without a status, `completed` decides.
+        assert_eq!(todo.current_status(&txn)?, TodoStatusSnapshot::Done);
+        todo.change_status(&mut txn, TodoStatusSnapshot::Waiting);
+        assert_eq!(todo.completed(&txn)?.deref(), &false);
+        assert_eq!(todo.current_status(&txn)?, TodoStatusSnapshot::Waiting);
+        todo.change_status(&mut txn, TodoStatusSnapshot::Cancelled);
+        assert_eq!(todo.completed(&txn)?.deref(), &true);
+
+        // A status that contradicts `completed` is ignored.
+        todo.set_completed(&mut txn, false.into());
+        assert_eq!(todo.current_status(&txn)?, TodoStatusSnapshot::Todo);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_observe_typed() -> YrsResult<()> {
+        let start = start();
+
+        let (doc, map) = test_doc();
+        let mut txn = doc.try_transact_mut().unwrap();
+        let mut todo = map.insert(
+            &mut txn,
+            "todo",
+            TodoPrelim {
+                text: TextPrelim::new("yo".into()),
+                ..TodoPrelim::new("yo", start, chrono::Duration::seconds(60))
+            },
+        );
+        drop(txn);
+
+        let seen = Rc::new(RefCell::new(vec![]));
+        let seen2 = seen.clone();
+        let _subscription = todo.observe_typed(move |_txn, changes| {
+            for change in changes.unwrap() {
+                seen2.borrow_mut().push(match change {
+                    TodoChange::Completed(old, new) => {
+                        format!("completed: {:?} -> {:?}", old.map(|b| *b), new.map(|b| *b))
+                    }
+                    TodoChange::PlannedExecutionsChanged => "planned_executions changed".into(),
+                    _ => "something else".into(),
+                });
+            }
+        });
+
+        let mut txn = doc.try_transact_mut().unwrap();
+        todo.set_completed(&mut txn, true.into());
+        drop(txn);
+
+        let mut txn = doc.try_transact_mut().unwrap();
+        let planned_executions = todo.planned_executions(&txn)?;
+        planned_executions.push(
+            &mut txn,
+            PlannedExecutionPrelim {
+                start: start.into(),
+                end: (start + chrono::Duration::hours(1)).into(),
+            },
+        );
+        drop(txn);
+
+        assert_eq!(
+            seen.borrow().deref(),
+            &vec![
+                "completed: Some(false) -> Some(true)".to_string(),
+                "planned_executions changed".to_string(),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() -> YrsResult<()> {
+        let start = start();
+        let end = start + chrono::Duration::minutes(45);
+
+        let child = TodoSnapshot {
+            completed: true,
+            planned_executions: vec![PlannedExecutionSnapshot { start, end }],
+            deadline: Some(end),
+            ..TodoSnapshot::new("child", start, chrono::Duration::minutes(30))
+        };
+        let parent = TodoSnapshot {
+            text: "some text".into(),
+            child_todos: Box::new(vec![child]),
+            ..TodoSnapshot::new("parent", start, chrono::Duration::hours(2))
+        };
+
+        let (doc, map) = test_doc();
+        let mut txn = doc.try_transact_mut().unwrap();
+        let todo = map.insert(&mut txn, "todo", TodoPrelim::from(parent.clone()));
+
+        assert_eq!(
+            todo.child_todos(&txn)?
+                .iter(&txn)
+                .next()
+                .unwrap()?
+                .title(&txn)?
+                .get_string(&txn),
+            "child"
+        );
+        assert_eq!(todo.to_snapshot(&txn)?, parent);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serde_round_trip() -> YrsResult<()> {
+        let start = start();
+
+        let (doc, map) = test_doc();
+        let mut txn = doc.try_transact_mut().unwrap();
+        let state = map.insert(
+            &mut txn,
+            "state",
+            StatePrelim {
+                todos: vec![TodoPrelim {
+                    id: uuid::Uuid::from_u128(1).into(),
+                    text: TextPrelim::new("text".into()),
+                    completed: true.into(),
+                    actual_executions: vec![ActualExecutionPrelim {
+                        start: start.into(),
+                        end: None,
+                    }]
+                    .into(),
+                    deadline: Some(start.into()),
+                    ..TodoPrelim::new("title", start, chrono::Duration::minutes(90))
+                }]
+                .into(),
+            },
+        );
+
+        let json = serde_json::to_value(state.with_txn(&txn)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "todos": [{
+                    "id": "00000000-0000-0000-0000-000000000001",
+                    "title": "title",
+                    "text": "text",
+                    "completed": true,
+                    "status": null,
+                    "completed_at": null,
+                    "created_at": "2023-05-01T08:00:00",
+                    "estimated_duration": 5_400_000,
+                    "planned_executions": [],
+                    "actual_executions": [{ "start": "2023-05-01T08:00:00", "end": null }],
+                    "child_todos": [],
+                    "position": null,
+                    "blocked_by": [],
+                    "deadline": "2023-05-01T08:00:00",
+                    "priority": null,
+                    "tags": [],
+                    "properties": {},
+                    "recurrence": null,
+                    "scheduling": null,
+                }]
+            })
+        );
+
+        let prelim: StatePrelim = serde_json::from_value(json).unwrap();
+        let copy = map.insert(&mut txn, "copy", prelim);
+        assert_eq!(copy.to_snapshot(&txn)?, state.to_snapshot(&txn)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_yrs_vec_mutations() -> YrsResult<()> {
+        let start = start();
+        let at = |hour: i64| start + chrono::Duration::hours(hour);
+        let planned = |hour: i64| PlannedExecutionPrelim {
+            start: at(hour).into(),
+            end: at(hour + 1).into(),
+        };
+        let todo = |title: &str, child_todos: Vec<TodoPrelim>| TodoPrelim {
+            planned_executions: (0..5).map(planned).collect::<Vec<_>>().into(),
+            ..todo_prelim(title, child_todos)
+        };
+
+        let (doc, map) = test_doc();
+        let mut txn = doc.try_transact_mut().unwrap();
+        let state = map.insert(
+            &mut txn,
+            "state",
+            StatePrelim {
+                todos: vec![todo("a", vec![todo("a1", vec![])]), todo("b", vec![])].into(),
+            },
+        );
+        let todos = state.todos(&txn)?;
+        let a = todos.get(&txn, 0)?.unwrap();
+        let executions = a.planned_executions(&txn)?;
+        let starts = |txn: &yrs::TransactionMut| {
+            executions
+                .iter(txn)
+                .map(|e| e.and_then(|e| e.start(txn)).map(|start| *start))
+                .collect::<YrsResult<Vec<_>>>()
+        };
+
+        assert_eq!(executions.remove(&mut txn, 1)?.start, at(1));
+        assert_eq!(starts(&txn)?, vec![at(0), at(2), at(3), at(4)]);
+
+        assert_eq!(executions.pop(&mut txn)?.unwrap().start, at(4));
+        assert_eq!(starts(&txn)?, vec![at(0), at(2), at(3)]);
+
+        let removed = executions.splice(&mut txn, 1.., [planned(7), planned(8), planned(9)])?;
+        assert_eq!(
+            removed.iter().map(|e| e.start).collect::<Vec<_>>(),
+            vec![at(2), at(3)]
+        );
+        assert_eq!(starts(&txn)?, vec![at(0), at(7), at(8), at(9)]);
+
+        executions.move_to(&mut txn, 0, 4)?;
+        assert_eq!(starts(&txn)?, vec![at(7), at(8), at(9), at(0)]);
+
+        let removed = executions.retain(&mut txn, |e, txn| *e.start(txn).unwrap() != at(8))?;
+        assert_eq!(removed.len(), 1);
+        assert_eq!(starts(&txn)?, vec![at(7), at(9), at(0)]);
+
+        // Indices that concurrent edits made stale are errors, and nothing is changed.
+        assert_eq!(
+            executions.remove(&mut txn, 3).unwrap_err(),
+            YrsWrapperError::IndexOutOfBounds { index: 3, len: 3 }
+        );
+        assert_eq!(
+            executions.splice(&mut txn, 2..5, [planned(1)]).unwrap_err(),
+            YrsWrapperError::RangeOutOfBounds {
+                start: 2,
+                end: 5,
+                len: 3
+            }
+        );
+        assert!(executions.move_to(&mut txn, 3, 0).is_err());
+        assert_eq!(starts(&txn)?, vec![at(7), at(9), at(0)]);
+
+        assert_eq!(executions.remove_range(&mut txn, ..)?.len(), 3);
+        assert!(executions.pop(&mut txn)?.is_none());
+
+        // Move a child todo to another parent.
+        let child = a.child_todos(&txn)?.remove(&mut txn, 0)?;
+        let b = todos.get(&txn, 1)?.unwrap();
+        b.child_todos(&txn)?
+            .push(&mut txn, TodoPrelim::from(child.clone()));
+        assert!(a.child_todos(&txn)?.is_empty(&txn));
+        assert_eq!(
+            b.child_todos(&txn)?
                 .get(&txn, 0)?
                 .unwrap()
-                .start(&txn)?
-                // Not testing the fractional component of the seconds because I'm getting errors rn
-                // that I don't want to look into (or at least I believe `%.f` refers to fractional
-                // seconds, because that's what I removed.
-                .format("%Y-%m-%dT%H:%M:%SZ")
-                .to_string(),
-            start.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+                .to_snapshot(&txn)?,
+            child
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_todos_in_order() -> YrsResult<()> {
+        use yrs::updates::decoder::Decode;
+        use yrs::{ReadTxn, StateVector, Update};
+
+        let titles = |doc: &Doc, map: &yrs::MapRef| -> YrsResult<Vec<String>> {
+            let txn = doc.transact();
+            let state = State::try_from_yrs_value(map.get(&txn, "state").unwrap(), &txn)?;
+            state
+                .todos(&txn)?
+                .in_order(&txn)?
+                .iter()
+                .map(|todo| Ok(todo.title(&txn)?.get_string(&txn)))
+                .collect()
+        };
+
+        let doc_a = Doc::with_client_id(1);
+        let map_a = doc_a.get_or_insert_map("state");
+        let state = map_a.insert(
+            &mut doc_a.transact_mut(),
+            "state",
+            StatePrelim {
+                todos: ["a", "b", "c", "d"]
+                    .into_iter()
+                    .map(|title| todo_prelim(title, vec![]))
+                    .collect::<Vec<_>>()
+                    .into(),
+            },
         );
+        let todos = state.todos(&doc_a.transact())?;
+        // Todos that have never been moved are in the order of the array.
+        assert_eq!(titles(&doc_a, &map_a)?, vec!["a", "b", "c", "d"]);
+
+        todos.move_in_order(&mut doc_a.transact_mut(), 0, 4)?;
+        assert_eq!(titles(&doc_a, &map_a)?, vec!["b", "c", "d", "a"]);
+        todos.move_in_order(&mut doc_a.transact_mut(), 2, 0)?;
+        assert_eq!(titles(&doc_a, &map_a)?, vec!["d", "b", "c", "a"]);
+        // Only positions are written, the array stays as it was.
+        let txn = doc_a.transact();
+        let array_order = todos
+            .iter(&txn)
+            .map(|todo| Ok(todo?.title(&txn)?.get_string(&txn)))
+            .collect::<YrsResult<Vec<_>>>()?;
+        assert_eq!(array_order, vec!["a", "b", "c", "d"]);
+        assert_eq!(todos.order(&txn)?, vec![3, 1, 2, 0]);
+        drop(txn);
 
         assert_eq!(
-            first_todo
-                .deadline(&txn)
-                .transpose()?
-                .unwrap()
-                .format("%Y-%m-%dT%H:%M:%SZ")
-                .to_string(),
-            (start + chrono::Duration::days(1))
-                .format("%Y-%m-%dT%H:%M:%SZ")
-                .to_string()
+            todos
+                .move_in_order(&mut doc_a.transact_mut(), 4, 0)
+                .unwrap_err(),
+            YrsWrapperError::IndexOutOfBounds { index: 4, len: 4 }
+        );
+
+        // Moving the same todo concurrently puts it in one of the two places, not in both.
+        let doc_b = Doc::with_client_id(2);
+        let map_b = doc_b.get_or_insert_map("state");
+        let update = doc_a
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        doc_b
+            .transact_mut()
+            .apply_update(Update::decode_v1(&update).unwrap());
+        let txn = doc_b.transact();
+        let todos_b =
+            State::try_from_yrs_value(map_b.get(&txn, "state").unwrap(), &txn)?.todos(&txn)?;
+        drop(txn);
+
+        todos.move_in_order(&mut doc_a.transact_mut(), 0, 2)?;
+        todos_b.move_in_order(&mut doc_b.transact_mut(), 0, 4)?;
+        let update_a = doc_a
+            .transact()
+            .encode_state_as_update_v1(&doc_b.transact().state_vector());
+        let update_b = doc_b
+            .transact()
+            .encode_state_as_update_v1(&doc_a.transact().state_vector());
+        doc_a
+            .transact_mut()
+            .apply_update(Update::decode_v1(&update_b).unwrap());
+        doc_b
+            .transact_mut()
+            .apply_update(Update::decode_v1(&update_a).unwrap());
+
+        let merged = titles(&doc_a, &map_a)?;
+        assert_eq!(merged, titles(&doc_b, &map_b)?);
+        assert_eq!(merged.len(), 4);
+        assert_eq!(merged.iter().filter(|title| *title == "d").count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_error_path() -> YrsResult<()> {
+        let start = start();
+        let todo = |planned_executions: usize, child_todos: Vec<TodoPrelim>| TodoPrelim {
+            planned_executions: (0..planned_executions)
+                .map(|_| PlannedExecutionPrelim {
+                    start: start.into(),
+                    end: start.into(),
+                })
+                .collect::<Vec<_>>()
+                .into(),
+            child_todos: YBox::new(child_todos.into()),
+            ..TodoPrelim::new("", start, chrono::Duration::hours(1))
+        };
+
+        let (doc, map) = test_doc();
+        let mut txn = doc.try_transact_mut().unwrap();
+        let state = map.insert(
+            &mut txn,
+            "state",
+            StatePrelim {
+                todos: vec![todo(0, vec![]), todo(0, vec![todo(2, vec![])])].into(),
+            },
+        );
+
+        let planned_execution = state
+            .todos(&txn)?
+            .get(&txn, 1)?
+            .unwrap()
+            .child_todos(&txn)?
+            .get(&txn, 0)?
+            .unwrap()
+            .planned_executions(&txn)?
+            .get(&txn, 1)?
+            .unwrap();
+        planned_execution
+            .0
+            .insert(&mut txn, "end", "not a timestamp".to_string());
+
+        let error = State::try_from_yrs_value(map.get(&txn, "state").unwrap(), &txn).unwrap_err();
+        assert_eq!(
+            error.path().unwrap().to_string(),
+            "todos[1].child_todos[0].planned_executions[1].end"
+        );
+        assert_eq!(
+            error.root_cause(),
+            &YrsWrapperError::MalformedString {
+                expected: "date time",
+                value: "not a timestamp".into(),
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_todo_refs() -> YrsResult<()> {
+        let child = todo_prelim("a1", vec![]);
+        let blocked = TodoPrelim {
+            blocked_by: Some(vec![(&child.id).into()].into()),
+            ..todo_prelim("c", vec![])
+        };
+
+        let (doc, map) = test_doc();
+        let mut txn = doc.try_transact_mut().unwrap();
+        let state = map.insert(
+            &mut txn,
+            "state",
+            StatePrelim {
+                todos: vec![
+                    todo_prelim("a", vec![child]),
+                    todo_prelim("b", vec![]),
+                    blocked,
+                ]
+                .into(),
+            },
+        );
+        let target = state
+            .todos(&txn)?
+            .get(&txn, 2)?
+            .unwrap()
+            .blocked_by(&txn)
+            .unwrap()?
+            .get(&txn, 0)?
+            .unwrap();
+
+        let index = state.todo_index(&txn)?;
+        assert_eq!(index.len(), 4);
+        assert_eq!(target.resolve(&index)?.title(&txn)?.get_string(&txn), "a1");
+
+        // Moving the child to another parent keeps the reference valid, even though its position
+        // changed.
+        let todos = state.todos(&txn)?;
+        let moved = todos
+            .get(&txn, 0)?
+            .unwrap()
+            .child_todos(&txn)?
+            .remove(&mut txn, 0)?;
+        todos
+            .get(&txn, 1)?
+            .unwrap()
+            .child_todos(&txn)?
+            .push(&mut txn, TodoPrelim::from(moved));
+        let index = state.todo_index(&txn)?;
+        assert_eq!(target.resolve(&index)?.title(&txn)?.get_string(&txn), "a1");
+
+        // Deleting it makes the reference dangle.
+        todos
+            .get(&txn, 1)?
+            .unwrap()
+            .child_todos(&txn)?
+            .remove(&mut txn, 0)?;
+        let index = state.todo_index(&txn)?;
+        assert_eq!(
+            target.resolve(&index).unwrap_err(),
+            YrsWrapperError::DanglingRef { id: target.id() }
         );
+        assert_eq!(index.dangling([&target]), vec![&target]);
 
         Ok(())
     }
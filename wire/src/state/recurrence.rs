@@ -0,0 +1,395 @@
+//! Expanding a `Recurrence` into the concrete occurrences in a range of time.
+//!
+//! Occurrences are generated like RFC 5545 does for `FREQ=DAILY` and `FREQ=WEEKLY` (with weeks
+//! starting on Monday), in the time zone of the recurrence's start, so that an occurrence at 9:00
+//! stays at 9:00 across daylight saving time changes. An occurrence is identified by its date in
+//! that time zone, which is the key of its entry in `Recurrence::overrides`.
+
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, Weekday};
+use chrono_tz::Tz;
+use yrs::TransactionMut;
+use yrs_wrappers::yrs_wrapper_error::YrsResult;
+
+use super::{
+    FrequencyVariant, OccurrenceOverridePrelim, OccurrenceOverrideVariant, Recurrence,
+    RecurrenceEndVariant, Todo,
+};
+use crate::time::local_to_utc;
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum OccurrenceStatus {
+    Pending,
+    Completed,
+    Skipped,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Occurrence {
+    /// The date of the occurrence in the time zone of the recurrence.
+    pub date: NaiveDate,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub status: OccurrenceStatus,
+}
+
+/// The day or week an occurrence falls in, which `TimeWindowPrelim::Fractional` windows are
+/// fractions of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OccurrencePeriod {
+    zone: Tz,
+    /// Midnight at the start of the period, in `zone`.
+    local_start: NaiveDateTime,
+    length: chrono::Duration,
+}
+
+impl OccurrencePeriod {
+    /// The instant, in UTC, that is `fraction` of the way through the period, going by the
+    /// clock in the time zone of the recurrence.
+    pub fn at(&self, fraction: f64) -> NaiveDateTime {
+        let offset =
+            chrono::Duration::seconds((fraction * self.length.num_seconds() as f64).round() as i64);
+        local_to_utc(self.zone, self.local_start + offset)
+    }
+
+    pub fn start(&self) -> NaiveDateTime {
+        self.at(0.0)
+    }
+
+    pub fn end(&self) -> NaiveDateTime {
+        self.at(1.0)
+    }
+}
+
+impl Recurrence {
+    /// The occurrences starting in `[from, to)`, in order, each lasting `duration`.
+    pub fn occurrences(
+        &self,
+        txn: &impl yrs::ReadTxn,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+        duration: chrono::Duration,
+    ) -> YrsResult<Vec<Occurrence>> {
+        let first = *self.start(txn)?;
+        let interval = (*self.interval(txn)?).max(1);
+        let mut weekdays = self
+            .by_weekday(txn)?
+            .iter(txn)
+            .map(|weekday| Ok(*weekday?))
+            .collect::<YrsResult<Vec<Weekday>>>()?;
+        weekdays.sort_by_key(|weekday| weekday.num_days_from_monday());
+        weekdays.dedup();
+
+        let (until, count) = match self.end(txn).transpose()? {
+            Some(end) => match end.variant(txn)? {
+                RecurrenceEndVariant::Until(until) => (Some(*until), None),
+                RecurrenceEndVariant::Count(count) => (None, Some(*count)),
+            },
+            None => (None, None),
+        };
+
+        // Weekly recurrences generate dates on exactly `weekdays`, daily ones are filtered by them.
+        let (dates, filter): (Box<dyn Iterator<Item = NaiveDate>>, _) =
+            match self.frequency(txn)?.variant(txn)? {
+                FrequencyVariant::Daily => (
+                    Box::new(daily_dates(first.date_naive(), interval)),
+                    weekdays,
+                ),
+                FrequencyVariant::Weekly => {
+                    if weekdays.is_empty() {
+                        weekdays.push(first.date_naive().weekday());
+                    }
+                    let dates = weekly_dates(first.date_naive(), interval, weekdays);
+                    (Box::new(dates), Vec::new())
+                }
+            };
+
+        let overrides = self.overrides(txn)?;
+        let mut occurrences = Vec::new();
+        let mut counted = 0;
+        for date in dates {
+            let start = start_on(&first, date);
+            // Checked before filtering, so that a filter that never matches can't loop forever.
+            if start >= to || until.map_or(false, |until| start > until) {
+                break;
+            }
+            if !filter.is_empty() && !filter.contains(&date.weekday()) {
+                continue;
+            }
+            if count.map_or(false, |count| counted >= count) {
+                break;
+            }
+            counted += 1;
+            if start < from {
+                continue;
+            }
+
+            let status = match overrides.get(txn, &override_key(date))? {
+                Some(status) => match status.variant(txn)? {
+                    OccurrenceOverrideVariant::Completed => OccurrenceStatus::Completed,
+                    OccurrenceOverrideVariant::Skipped => OccurrenceStatus::Skipped,
+                },
+                None => OccurrenceStatus::Pending,
+            };
+            occurrences.push(Occurrence {
+                date,
+                start,
+                end: start + duration,
+                status,
+            });
+        }
+
+        Ok(occurrences)
+    }
+
+    /// Marks the occurrence on `date` as completed or skipped, or back to pending.
+    pub fn set_occurrence_status(
+        &self,
+        txn: &mut TransactionMut,
+        date: NaiveDate,
+        status: OccurrenceStatus,
+    ) -> YrsResult<()> {
+        let overrides = self.overrides(&*txn)?;
+        let key = override_key(date);
+        match status {
+            OccurrenceStatus::Pending => {
+                overrides.remove(txn, &key);
+            }
+            OccurrenceStatus::Completed => {
+                overrides.insert(txn, key, OccurrenceOverridePrelim::Completed);
+            }
+            OccurrenceStatus::Skipped => {
+                overrides.insert(txn, key, OccurrenceOverridePrelim::Skipped);
+            }
+        }
+        Ok(())
+    }
+
+    /// The day or week `occurrence` falls in.
+    pub fn period_of(
+        &self,
+        txn: &impl yrs::ReadTxn,
+        occurrence: &Occurrence,
+    ) -> YrsResult<OccurrencePeriod> {
+        let date = occurrence.date;
+        let (first_day, length) = match self.frequency(txn)?.variant(txn)? {
+            FrequencyVariant::Daily => (date, chrono::Duration::days(1)),
+            FrequencyVariant::Weekly => (
+                date - chrono::Duration::days(date.weekday().num_days_from_monday().into()),
+                chrono::Duration::weeks(1),
+            ),
+        };
+        Ok(OccurrencePeriod {
+            zone: self.start(txn)?.timezone(),
+            local_start: first_day.and_hms_opt(0, 0, 0).unwrap(),
+            length,
+        })
+    }
+}
+
+impl Todo {
+    /// The occurrences of the todo starting in `[from, to)`, each lasting the estimated duration
+    /// of the todo. Empty if the todo doesn't recur.
+    pub fn occurrences(
+        &self,
+        txn: &impl yrs::ReadTxn,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+    ) -> YrsResult<Vec<Occurrence>> {
+        match self.recurrence(txn).transpose()? {
+            Some(recurrence) => {
+                recurrence.occurrences(txn, from, to, *self.estimated_duration(txn)?)
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+fn override_key(date: NaiveDate) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
+
+fn daily_dates(first: NaiveDate, interval: u32) -> impl Iterator<Item = NaiveDate> {
+    (0..).map(move |i: i64| first + chrono::Duration::days(i * i64::from(interval)))
+}
+
+/// `weekdays` must be sorted, starting from Monday.
+fn weekly_dates(
+    first: NaiveDate,
+    interval: u32,
+    weekdays: Vec<Weekday>,
+) -> impl Iterator<Item = NaiveDate> {
+    let first_monday =
+        first - chrono::Duration::days(first.weekday().num_days_from_monday().into());
+    (0..)
+        .flat_map(move |i: i64| {
+            let monday = first_monday + chrono::Duration::weeks(i * i64::from(interval));
+            weekdays.clone().into_iter().map(move |weekday| {
+                monday + chrono::Duration::days(weekday.num_days_from_monday().into())
+            })
+        })
+        .filter(move |date| *date >= first)
+}
+
+/// The start, in UTC, of the occurrence on `date`, at the time of day of `first`.
+fn start_on(first: &DateTime<Tz>, date: NaiveDate) -> NaiveDateTime {
+    local_to_utc(first.timezone(), date.and_time(first.time()))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveDateTime, TimeZone, Weekday};
+    use chrono_tz::Europe::Berlin;
+    use yrs::{Map, Transact};
+    use yrs_wrappers::yrs_wrapper_error::YrsResult;
+
+    use super::{Occurrence, OccurrenceStatus};
+    use crate::state::test_utils::test_doc;
+    use crate::state::{FrequencyPrelim, RecurrenceEndPrelim, RecurrencePrelim, TodoPrelim};
+
+    fn utc(day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2023, 3, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    fn todo_prelim(recurrence: Option<RecurrencePrelim>) -> TodoPrelim {
+        TodoPrelim {
+            recurrence,
+            ..TodoPrelim::new("standup", utc(1, 0), chrono::Duration::minutes(15))
+        }
+    }
+
+    fn recurrence(
+        day: u32,
+        frequency: FrequencyPrelim,
+        interval: u32,
+        by_weekday: Vec<Weekday>,
+        end: Option<RecurrenceEndPrelim>,
+    ) -> RecurrencePrelim {
+        RecurrencePrelim {
+            start: Berlin
+                .with_ymd_and_hms(2023, 3, day, 9, 0, 0)
+                .unwrap()
+                .into(),
+            frequency,
+            interval: interval.into(),
+            by_weekday: by_weekday
+                .into_iter()
+                .map(Into::into)
+                .collect::<Vec<_>>()
+                .into(),
+            end,
+            overrides: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_weekly_occurrences() -> YrsResult<()> {
+        let (doc, map) = test_doc();
+        let mut txn = doc.transact_mut();
+        // Mondays and Wednesdays at 9:00 in Berlin, which is 8:00 UTC in winter.
+        let todo = map.insert(
+            &mut txn,
+            "todo",
+            todo_prelim(Some(recurrence(
+                6,
+                FrequencyPrelim::Weekly,
+                1,
+                vec![Weekday::Wed, Weekday::Mon],
+                Some(RecurrenceEndPrelim::Count(5.into())),
+            ))),
+        );
+        let starts = |txn: &yrs::TransactionMut, from, to| -> YrsResult<Vec<NaiveDateTime>> {
+            Ok(todo
+                .occurrences(txn, from, to)?
+                .into_iter()
+                .map(|occurrence| occurrence.start)
+                .collect())
+        };
+
+        assert_eq!(
+            starts(&txn, utc(1, 0), utc(31, 0))?,
+            vec![utc(6, 8), utc(8, 8), utc(13, 8), utc(15, 8), utc(20, 8)]
+        );
+        // Occurrences before the range still count towards the end.
+        assert_eq!(
+            starts(&txn, utc(14, 0), utc(31, 0))?,
+            vec![utc(15, 8), utc(20, 8)]
+        );
+
+        let recurrence = todo.recurrence(&txn).unwrap()?;
+        let march = |day| NaiveDate::from_ymd_opt(2023, 3, day).unwrap();
+        recurrence.set_occurrence_status(&mut txn, march(8), OccurrenceStatus::Completed)?;
+        recurrence.set_occurrence_status(&mut txn, march(13), OccurrenceStatus::Skipped)?;
+        assert_eq!(
+            todo.occurrences(&txn, utc(7, 0), utc(14, 0))?,
+            vec![
+                Occurrence {
+                    date: march(8),
+                    start: utc(8, 8),
+                    end: utc(8, 8) + chrono::Duration::minutes(15),
+                    status: OccurrenceStatus::Completed,
+                },
+                Occurrence {
+                    date: march(13),
+                    start: utc(13, 8),
+                    end: utc(13, 8) + chrono::Duration::minutes(15),
+                    status: OccurrenceStatus::Skipped,
+                },
+            ]
+        );
+
+        recurrence.set_occurrence_status(&mut txn, march(13), OccurrenceStatus::Pending)?;
+        assert_eq!(
+            todo.occurrences(&txn, utc(13, 0), utc(14, 0))?[0].status,
+            OccurrenceStatus::Pending
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_daily_occurrences() -> YrsResult<()> {
+        let (doc, map) = test_doc();
+        let mut txn = doc.transact_mut();
+
+        // Berlin switches to summer time on the 26th, after which 9:00 is 7:00 UTC.
+        let daily = map.insert(
+            &mut txn,
+            "daily",
+            todo_prelim(Some(recurrence(
+                24,
+                FrequencyPrelim::Daily,
+                1,
+                vec![],
+                Some(RecurrenceEndPrelim::Until(utc(27, 7).into())),
+            ))),
+        );
+        let starts = daily
+            .occurrences(&txn, utc(1, 0), utc(31, 0))?
+            .into_iter()
+            .map(|occurrence| occurrence.start)
+            .collect::<Vec<_>>();
+        assert_eq!(starts, vec![utc(24, 8), utc(25, 8), utc(26, 7), utc(27, 7)]);
+
+        // Every week from a Monday, but only on Tuesdays, is never.
+        let never = map.insert(
+            &mut txn,
+            "never",
+            todo_prelim(Some(recurrence(
+                6,
+                FrequencyPrelim::Daily,
+                7,
+                vec![Weekday::Tue],
+                None,
+            ))),
+        );
+        assert!(never.occurrences(&txn, utc(1, 0), utc(31, 0))?.is_empty());
+
+        let once = map.insert(&mut txn, "once", todo_prelim(None));
+        assert!(once.occurrences(&txn, utc(1, 0), utc(31, 0))?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,31 @@
+//! What the tests of the state share.
+
+use chrono::NaiveDateTime;
+use yrs::{Doc, MapRef, TextPrelim};
+use yrs_wrappers::ybox::YBox;
+
+use super::TodoPrelim;
+
+/// When the todos of the tests are created, 8:00 on May 1, 2023.
+pub fn start() -> NaiveDateTime {
+    chrono::NaiveDate::from_ymd_opt(2023, 5, 1)
+        .unwrap()
+        .and_hms_opt(8, 0, 0)
+        .unwrap()
+}
+
+/// A fresh document, with the map that tests put their values in.
+pub fn test_doc() -> (Doc, MapRef) {
+    let doc = Doc::new();
+    let map = doc.get_or_insert_map("state");
+    (doc, map)
+}
+
+/// A todo estimated to take 30 minutes, with its title as its text.
+pub fn todo_prelim(title: &str, child_todos: Vec<TodoPrelim>) -> TodoPrelim {
+    TodoPrelim {
+        text: TextPrelim::new(title.into()),
+        child_todos: YBox::new(child_todos.into()),
+        ..TodoPrelim::new(title, start(), chrono::Duration::minutes(30))
+    }
+}
@@ -0,0 +1,185 @@
+//! Undo and redo for the changes the local user makes to a `State`.
+//!
+//! Only transactions with `LOCAL_ORIGIN` as their origin are tracked, so that changes that
+//! arrive from other replicas are never undone. `StateUndoManager::transact` opens such a
+//! transaction, and makes everything done in it one step on the undo stack.
+
+use yrs::{Doc, Transact, TransactionAcqError, TransactionMut, UndoManager};
+
+use super::State;
+
+/// The origin of transactions made by the local user.
+pub const LOCAL_ORIGIN: &str = "local";
+
+pub struct StateUndoManager {
+    inner: UndoManager,
+}
+
+impl StateUndoManager {
+    pub fn new(doc: &Doc, state: &State) -> Self {
+        let mut inner = UndoManager::new(doc, &state.0);
+        inner.include_origin(LOCAL_ORIGIN);
+        StateUndoManager { inner }
+    }
+
+    /// Runs `f` in a transaction with `LOCAL_ORIGIN`, and makes the changes made in it undoable
+    /// as a whole, separately from the changes made before and after.
+    pub fn transact<R>(
+        &mut self,
+        doc: &Doc,
+        f: impl FnOnce(&mut TransactionMut) -> R,
+    ) -> Result<R, TransactionAcqError> {
+        let result = {
+            let mut txn = doc.try_transact_mut_with(LOCAL_ORIGIN)?;
+            f(&mut txn)
+        };
+        // Otherwise, changes made in quick succession would be merged into one step.
+        self.inner.reset();
+        Ok(result)
+    }
+
+    /// Returns whether there was anything to undo.
+    pub fn undo(&mut self) -> Result<bool, TransactionAcqError> {
+        self.inner.undo()
+    }
+
+    /// Returns whether there was anything to redo.
+    pub fn redo(&mut self) -> Result<bool, TransactionAcqError> {
+        self.inner.redo()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.inner.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.inner.can_redo()
+    }
+
+    /// Forgets everything that could be undone or redone.
+    pub fn clear(&mut self) -> Result<(), TransactionAcqError> {
+        self.inner.clear()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Timelike;
+    use yrs::updates::decoder::Decode;
+    use yrs::{Doc, Map, ReadTxn, StateVector, Transact, Transaction, Update};
+    use yrs_wrappers::{
+        try_from_yrs_value::TryFromYrsValue, yrs_vec::YrsVec, yrs_wrapper_error::YrsResult,
+    };
+
+    use super::StateUndoManager;
+    use crate::state::migration::{init_state, STATE_KEY};
+    use crate::state::test_utils::start;
+    use crate::state::{PlannedExecution, PlannedExecutionPrelim, State, StatePrelim, TodoPrelim};
+
+    fn state_prelim() -> StatePrelim {
+        StatePrelim {
+            todos: vec![TodoPrelim::new("todo", start(), chrono::Duration::hours(1))].into(),
+        }
+    }
+
+    fn planned_execution(hour: u32) -> PlannedExecutionPrelim {
+        let start = start().with_hour(hour).unwrap();
+        PlannedExecutionPrelim {
+            start: start.into(),
+            end: (start + chrono::Duration::hours(1)).into(),
+        }
+    }
+
+    #[test]
+    fn test_undo_redo() -> YrsResult<()> {
+        let doc = Doc::with_client_id(1);
+        let root = doc.get_or_insert_map("root");
+        let state = init_state(&root, &mut doc.transact_mut(), state_prelim());
+        let mut undo = StateUndoManager::new(&doc, &state);
+        assert!(!undo.can_undo());
+
+        let txn = doc.transact();
+        let todo = state.todos(&txn)?.get(&txn, 0)?.unwrap();
+        let executions = todo.planned_executions(&txn)?;
+        drop(txn);
+        let count = |doc: &Doc| executions.len(&doc.transact());
+
+        // Both pushes are one step.
+        undo.transact(&doc, |txn| {
+            executions.push(txn, planned_execution(8));
+            executions.push(txn, planned_execution(9));
+        })
+        .unwrap();
+        undo.transact(&doc, |txn| todo.set_completed(txn, true.into()))
+            .unwrap();
+        assert!(undo.can_undo());
+
+        assert!(undo.undo().unwrap());
+        assert!(!*todo.completed(&doc.transact())?);
+        assert_eq!(count(&doc), 2);
+
+        assert!(undo.undo().unwrap());
+        assert_eq!(count(&doc), 0);
+        assert!(!undo.can_undo());
+        assert!(undo.can_redo());
+
+        assert!(undo.redo().unwrap());
+        assert_eq!(count(&doc), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remote_changes_are_not_undone() -> YrsResult<()> {
+        let doc = Doc::with_client_id(1);
+        let root = doc.get_or_insert_map("root");
+        let state = init_state(&root, &mut doc.transact_mut(), state_prelim());
+        let executions_of =
+            |state: &State, txn: &Transaction<'_>| -> YrsResult<YrsVec<PlannedExecution>> {
+                state
+                    .todos(txn)?
+                    .get(txn, 0)?
+                    .unwrap()
+                    .planned_executions(txn)
+            };
+
+        let remote = Doc::with_client_id(2);
+        let remote_root = remote.get_or_insert_map("root");
+        let initial = doc
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        remote
+            .transact_mut()
+            .apply_update(Update::decode_v1(&initial).unwrap());
+
+        let mut undo = StateUndoManager::new(&doc, &state);
+        let executions = executions_of(&state, &doc.transact())?;
+        undo.transact(&doc, |txn| executions.push(txn, planned_execution(8)))
+            .unwrap();
+
+        // Another replica adds an execution, which arrives here as an update.
+        let remote_txn = remote.transact();
+        let remote_state = State::try_from_yrs_value(
+            remote_root.get(&remote_txn, STATE_KEY).unwrap(),
+            &remote_txn,
+        )?;
+        let remote_executions = executions_of(&remote_state, &remote_txn)?;
+        drop(remote_txn);
+        remote_executions.push(&mut remote.transact_mut(), planned_execution(10));
+        let update = remote
+            .transact()
+            .encode_state_as_update_v1(&doc.transact().state_vector());
+        doc.transact_mut()
+            .apply_update(Update::decode_v1(&update).unwrap());
+        assert_eq!(executions.len(&doc.transact()), 2);
+
+        // Only the local execution is undone, and there's nothing more to undo.
+        assert!(undo.undo().unwrap());
+        assert!(!undo.can_undo());
+        let txn = doc.transact();
+        assert_eq!(executions.len(&txn), 1);
+        assert_eq!(executions.get(&txn, 0)?.unwrap().start(&txn)?.hour(), 10);
+
+        Ok(())
+    }
+}
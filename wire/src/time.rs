@@ -0,0 +1,46 @@
+//! Converting the local times users see and enter to the UTC times documents store.
+
+use chrono::{NaiveDateTime, Offset, TimeZone};
+use chrono_tz::Tz;
+
+/// `local`, a time in `zone`, in UTC. Like RFC 5545 does, local times that happen twice are taken
+/// the first time, and local times skipped when the clocks are put forward are moved forward with
+/// them (so 2:30 becomes 3:30 when 2:00 becomes 3:00).
+pub fn local_to_utc(zone: Tz, local: NaiveDateTime) -> NaiveDateTime {
+    match zone.from_local_datetime(&local).earliest() {
+        Some(datetime) => datetime.naive_utc(),
+        None => {
+            // Read the local time with the offset from before the clocks were put forward.
+            let offset = zone
+                .offset_from_utc_datetime(&(local - chrono::Duration::days(1)))
+                .fix();
+            local - chrono::Duration::seconds(offset.local_minus_utc().into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveDateTime};
+    use chrono_tz::{America::New_York, Tz};
+
+    use super::local_to_utc;
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2023, 3, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_local_to_utc() {
+        assert_eq!(local_to_utc(New_York, at(1, 9, 0)), at(1, 14, 0));
+        assert_eq!(local_to_utc(New_York, at(20, 9, 0)), at(20, 13, 0));
+        assert_eq!(local_to_utc(Tz::UTC, at(1, 9, 0)), at(1, 9, 0));
+
+        // The clocks went from 2:00 to 3:00 on March 12, so 2:30 didn't happen.
+        assert_eq!(local_to_utc(New_York, at(12, 2, 30)), at(12, 7, 30));
+        assert_eq!(local_to_utc(New_York, at(12, 3, 30)), at(12, 7, 30));
+    }
+}
@@ -1,11 +1,121 @@
 extern crate proc_macro;
 
 use proc_macro::TokenStream;
-use proc_macro2::Literal;
+use proc_macro2::{Literal, TokenStream as TokenStream2};
 use quote::{format_ident, quote};
-use syn::{parse_macro_input, Data, DeriveInput, Fields};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{parse_macro_input, Data, DataEnum, DeriveInput, Fields, Token};
 
-#[proc_macro_derive(YrsStruct, attributes(yrs_value_type))]
+/// A single argument of a `#[yrs(...)]` field attribute.
+enum FieldAttr {
+    /// `rename = "key"`: store the field under `key` instead of the field's name.
+    Rename(syn::LitStr),
+
+    /// `default` or `default = expr`: if the field is missing from the map, the getter returns
+    /// `expr` (or `Default::default()`), which must evaluate to the `Prelim::Return` of the field,
+    /// instead of failing with `YMapMissingAttr`.
+    Default(Option<syn::Expr>),
+
+    /// `skip`: the field is never written to, or read from, the map.
+    Skip,
+}
+
+impl Parse for FieldAttr {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let ident: syn::Ident = input.parse()?;
+        match ident.to_string().as_str() {
+            "rename" => {
+                input.parse::<Token![=]>()?;
+                Ok(FieldAttr::Rename(input.parse()?))
+            }
+            "default" => {
+                if input.peek(Token![=]) {
+                    input.parse::<Token![=]>()?;
+                    Ok(FieldAttr::Default(Some(input.parse()?)))
+                } else {
+                    Ok(FieldAttr::Default(None))
+                }
+            }
+            "skip" => Ok(FieldAttr::Skip),
+            _ => Err(syn::Error::new(
+                ident.span(),
+                "Expected one of `rename`, `default` or `skip`",
+            )),
+        }
+    }
+}
+
+struct FieldInfo {
+    name: syn::Ident,
+    ty: syn::Type,
+    name_literal: Literal,
+    is_option: bool,
+    default: Option<syn::Expr>,
+}
+
+impl FieldInfo {
+    /// Returns `None` if the field is annotated with `#[yrs(skip)]`. `key` is what the field is
+    /// stored under in the map, unless it is overridden with `#[yrs(rename = "...")]`.
+    fn new(name: syn::Ident, key: String, field: &syn::Field) -> Option<Self> {
+        let mut key = key;
+        let mut default = None;
+
+        for attr in field.attrs.iter().filter(|a| a.path.is_ident("yrs")) {
+            let field_attrs = attr
+                .parse_args_with(Punctuated::<FieldAttr, Token![,]>::parse_terminated)
+                .unwrap_or_else(|e| panic!("Invalid #[yrs(...)] attribute: {}", e));
+
+            for field_attr in field_attrs {
+                match field_attr {
+                    FieldAttr::Rename(lit) => key = lit.value(),
+                    FieldAttr::Default(expr) => {
+                        default = Some(
+                            expr.unwrap_or_else(|| syn::parse_quote! { Default::default() }),
+                        )
+                    }
+                    FieldAttr::Skip => return None,
+                }
+            }
+        }
+
+        let field_type = field.ty.clone();
+        let (ty, is_option) = match &field_type {
+            syn::Type::Path(tp) => {
+                if tp.path.segments[0].ident.to_string() == "Option" {
+                    let inner_type = tp.path.segments[0].arguments.clone();
+                    match inner_type {
+                        syn::PathArguments::AngleBracketed(ab) => {
+                            let inner_type = ab.args[0].clone();
+                            match inner_type {
+                                syn::GenericArgument::Type(ty) => (ty, true),
+                                _ => panic!("Only type arguments are supported"),
+                            }
+                        }
+                        _ => panic!("Only angle bracketed arguments are supported"),
+                    }
+                } else {
+                    (field_type, false)
+                }
+            }
+            _ => (field_type, false),
+        };
+
+        if is_option && default.is_some() {
+            panic!("#[yrs(default)] is not supported on Option fields, which already default to None");
+        }
+
+        Some(FieldInfo {
+            name,
+            ty,
+            name_literal: Literal::string(&key),
+            is_option,
+            default,
+        })
+    }
+}
+
+#[proc_macro_derive(YrsStruct, attributes(yrs_value_type, yrs))]
 pub fn yrs_wrapper_derive(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let name = input.ident;
@@ -15,61 +125,260 @@ pub fn yrs_wrapper_derive(input: TokenStream) -> TokenStream {
         panic!("To emphasize that the struct that YrsWrapper annotates should be the prelim, the struct name should end with Prelim");
     }
 
-    struct FieldInfo {
-        name: syn::Ident,
-        ty: syn::Type,
-        name_literal: Literal,
-        is_option: bool,
-    }
-    let field_names_and_types = match data {
+    let gen = match data {
         Data::Struct(data) => match data.fields {
-            Fields::Named(fields) => fields
-                .named
-                .iter()
-                .map(|f| {
-                    let name = f.ident.clone().expect("Only named fields are supported");
-                    let field_type = f.ty.clone();
-                    let name_literal = Literal::string(&name.to_string());
-
-                    let (ty, is_option) = match &field_type {
-                        syn::Type::Path(tp) => {
-                            if tp.path.segments[0].ident.to_string() == "Option" {
-                                let inner_type = tp.path.segments[0].arguments.clone();
-                                match inner_type {
-                                    syn::PathArguments::AngleBracketed(ab) => {
-                                        let inner_type = ab.args[0].clone();
-                                        match inner_type {
-                                            syn::GenericArgument::Type(ty) => (ty, true),
-                                            _ => panic!("Only type arguments are supported"),
-                                        }
-                                    }
-                                    _ => panic!("Only angle bracketed arguments are supported"),
-                                }
-                            } else {
-                                (field_type, false)
-                            }
+            Fields::Named(fields) => {
+                let mut skipped_fields = vec![];
+                let field_names_and_types = fields
+                    .named
+                    .iter()
+                    .filter_map(|f| {
+                        let name = f.ident.clone().expect("Only named fields are supported");
+                        let key = name.to_string();
+                        let field_info = FieldInfo::new(name.clone(), key, f);
+                        if field_info.is_none() {
+                            skipped_fields.push(name);
                         }
-                        _ => (field_type, false),
-                    };
-
-                    FieldInfo {
-                        name,
-                        ty,
-                        name_literal,
-                        is_option,
-                    }
-                })
-                .collect::<Vec<_>>(),
+                        field_info
+                    })
+                    .collect::<Vec<_>>();
+                derive_struct(name, field_names_and_types, skipped_fields)
+            }
             _ => panic!("Only named fields are supported"),
         },
-        _ => panic!("Only structs are supported"),
+        Data::Enum(data) => derive_enum(name, data),
+        _ => panic!("Only structs and enums are supported"),
+    };
+
+    gen.into()
+}
+
+fn to_pascal_case(snake_case: &str) -> String {
+    snake_case
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// The type of the snapshot of a field.
+fn snapshot_type(FieldInfo { ty, is_option, .. }: &FieldInfo) -> TokenStream2 {
+    let snapshot_type = quote! {
+        <<#ty as yrs::block::Prelim>::Return as yrs_wrappers::snapshot::ToSnapshot>::Snapshot
+    };
+
+    if *is_option {
+        quote! { Option<#snapshot_type> }
+    } else {
+        snapshot_type
+    }
+}
+
+/// Turns `value`, the (already read) value of a field, into its snapshot, `?`-ing any error.
+fn to_snapshot_expr(FieldInfo { is_option, .. }: &FieldInfo, value: TokenStream2) -> TokenStream2 {
+    if *is_option {
+        quote! {
+            #value
+                .map(|value| yrs_wrappers::snapshot::ToSnapshot::to_snapshot(&value, txn))
+                .transpose()?
+        }
+    } else {
+        quote! { yrs_wrappers::snapshot::ToSnapshot::to_snapshot(&#value, txn)? }
+    }
+}
+
+/// Turns `snapshot`, the snapshot of a field, back into the prelim of that field.
+fn from_snapshot_expr(FieldInfo { ty, is_option, .. }: &FieldInfo, snapshot: TokenStream2) -> TokenStream2 {
+    if *is_option {
+        quote! {
+            #snapshot.map(|snapshot| <#ty as yrs_wrappers::snapshot::FromSnapshot<_>>::from_snapshot(snapshot))
+        }
+    } else {
+        quote! { <#ty as yrs_wrappers::snapshot::FromSnapshot<_>>::from_snapshot(#snapshot) }
+    }
+}
+
+/// Serializes `value`, the (already read) value of a field, with the `SerializeStruct`-like
+/// `state` in scope, as field `key` if one is given.
+fn serialize_field_line(
+    FieldInfo { is_option, .. }: &FieldInfo,
+    key: Option<String>,
+    value: TokenStream2,
+) -> TokenStream2 {
+    let value = if *is_option {
+        quote! {
+            &#value
+                .as_ref()
+                .map(|value| yrs_wrappers::yrs_serde::YrsSerialize::with_txn(value, txn))
+        }
+    } else {
+        quote! { &yrs_wrappers::yrs_serde::YrsSerialize::with_txn(&#value, txn) }
+    };
+
+    match key {
+        Some(key) => quote! { state.serialize_field(#key, #value)?; },
+        None => quote! { state.serialize_field(#value)?; },
+    }
+}
+
+/// The type a field has in the helper that the `YrsDeserialize` impls derive `serde::Deserialize` on.
+fn deserialized_type(FieldInfo { ty, is_option, .. }: &FieldInfo) -> TokenStream2 {
+    if *is_option {
+        quote! { Option<yrs_wrappers::yrs_serde::Deserialized<#ty>> }
+    } else {
+        quote! { yrs_wrappers::yrs_serde::Deserialized<#ty> }
+    }
+}
+
+/// Turns `deserialized`, of the type given by `deserialized_type`, into the prelim of the field.
+fn from_deserialized_expr(FieldInfo { is_option, .. }: &FieldInfo, deserialized: TokenStream2) -> TokenStream2 {
+    if *is_option {
+        quote! { #deserialized.map(|value| value.0) }
+    } else {
+        quote! { #deserialized.0 }
+    }
+}
+
+/// Inserts a field, bound to a local of the same name, into the `map` of a `Prelim::integrate`.
+fn insert_field_line(
+    FieldInfo {
+        name,
+        name_literal,
+        is_option,
+        ..
+    }: &FieldInfo,
+) -> TokenStream2 {
+    let line = quote! {
+        <yrs::MapRef as yrs::Map>::insert(&map, txn, #name_literal, #name);
+    };
+
+    if *is_option {
+        quote! {
+            if let Some(#name) = #name {
+                #line
+            };
+        }
+    } else {
+        line
+    }
+}
+
+/// Reads and converts a field out of the `map_ref` in scope, `?`-ing any error. The field is
+/// checked with `try_from_yrs_value` if the `eager` in scope is true, and with
+/// `try_from_yrs_value_shallow` otherwise.
+fn read_field_expr(
+    FieldInfo {
+        ty,
+        name_literal,
+        is_option,
+        default,
+        ..
+    }: &FieldInfo,
+) -> TokenStream2 {
+    let convert = quote! {
+        if eager {
+            <<#ty as yrs::block::Prelim>::Return as yrs_wrappers::try_from_yrs_value::TryFromYrsValue>::try_from_yrs_value(value, txn)
+        } else {
+            <<#ty as yrs::block::Prelim>::Return as yrs_wrappers::try_from_yrs_value::TryFromYrsValue>::try_from_yrs_value_shallow(value, txn)
+        }
+        .map_err(|e| e.in_field(#name_literal))?
+    };
+
+    if let Some(default) = default {
+        quote! {
+            match <yrs::MapRef as yrs::Map>::get(map_ref, txn, #name_literal) {
+                Some(value) => #convert,
+                None => #default,
+            }
+        }
+    } else if *is_option {
+        quote! {
+            match <yrs::MapRef as yrs::Map>::get(map_ref, txn, #name_literal) {
+                Some(value) => Some(#convert),
+                None => None,
+            }
+        }
+    } else {
+        quote! {
+            {
+                let value = <yrs::MapRef as yrs::Map>::get(map_ref, txn, #name_literal).ok_or_else(|| {
+                    yrs_wrappers::yrs_wrapper_error::YrsWrapperError::YMapMissingAttr {
+                        attr: #name_literal.to_string(),
+                    }
+                })?;
+                #convert
+            }
+        }
+    }
+}
+
+/// The impls that only depend on the generated type being a newtype around `yrs::MapRef`.
+fn map_ref_newtype_impls(name_without_prelim: &syn::Ident) -> TokenStream2 {
+    let try_from_block_ptr_impl = quote! {
+        impl TryFrom<yrs::block::BlockPtr> for #name_without_prelim {
+            type Error = <yrs::MapRef as TryFrom<yrs::block::BlockPtr>>::Error;
+
+            fn try_from(value: yrs::block::BlockPtr) -> Result<Self, Self::Error> {
+                let map_ref: yrs::MapRef = value.try_into()?;
+
+                Ok(#name_without_prelim(map_ref))
+            }
+        }
+    };
+
+    let as_mut_impl = quote! {
+        impl core::convert::AsMut<yrs::types::Branch> for #name_without_prelim {
+            fn as_mut(&mut self) -> &mut yrs::types::Branch {
+                <yrs::MapRef as core::convert::AsMut<yrs::types::Branch>>::as_mut(&mut self.0)
+            }
+        }
+    };
+
+    let observable_impl = quote! {
+        impl yrs::types::Observable for #name_without_prelim {
+
+            type Event = <yrs::MapRef as yrs::types::Observable>::Event;
+
+            fn try_observer_mut(
+                &mut self
+            ) -> Option<&mut yrs::observer::Observer<std::sync::Arc<dyn Fn(&yrs::TransactionMut<'_>, &Self::Event)>>> {
+                self.0.try_observer_mut()
+            }
+
+            fn try_observer(
+                &self
+            ) -> Option<&yrs::observer::Observer<std::sync::Arc<dyn Fn(&yrs::TransactionMut<'_>, &Self::Event)>>> {
+                self.0.try_observer()
+            }
+
+        }
     };
 
-    let field_checkers_in_try_from = field_names_and_types.iter().map(|FieldInfo { name, ty, name_literal, is_option }| {
+    quote! {
+        #try_from_block_ptr_impl
+
+        #as_mut_impl
+
+        #observable_impl
+    }
+}
+
+fn derive_struct(
+    name: syn::Ident,
+    field_names_and_types: Vec<FieldInfo>,
+    skipped_fields: Vec<syn::Ident>,
+) -> TokenStream2 {
+    let field_checkers_in_try_from = field_names_and_types.iter().map(|FieldInfo { name, ty, name_literal, is_option, default }| {
         let value_at_field_name = format_ident!("value_at_{}", name);
         let field_name_is_prelim_for = format_ident!("{}PrelimFor", name);
 
-        if *is_option {
+        // A missing field with a default is just as valid as a missing optional field.
+        if *is_option || default.is_some() {
             quote! {
                 let #value_at_field_name = <yrs::MapRef as yrs::Map>::get(&map_ref, txn, #name_literal);
 
@@ -79,7 +388,7 @@ pub fn yrs_wrapper_derive(input: TokenStream) -> TokenStream {
                     <#field_name_is_prelim_for as yrs_wrappers::try_from_yrs_value::TryFromYrsValue>::try_from_yrs_value(
                         value,
                         txn,
-                    )?;
+                    ).map_err(|e| e.in_field(#name_literal))?;
                 };
             }
         } else {
@@ -87,7 +396,7 @@ pub fn yrs_wrapper_derive(input: TokenStream) -> TokenStream {
                 let #value_at_field_name = match <yrs::MapRef as yrs::Map>::get(&map_ref, txn, #name_literal) {
                     Some(value) => value,
                     None => return Err(yrs_wrappers::yrs_wrapper_error::YrsWrapperError::YMapMissingAttr {
-                        attr: stringify!(#name).to_string(),
+                        attr: #name_literal.to_string(),
                     }),
                 };
 
@@ -97,7 +406,7 @@ pub fn yrs_wrapper_derive(input: TokenStream) -> TokenStream {
                 <#field_name_is_prelim_for as yrs_wrappers::try_from_yrs_value::TryFromYrsValue>::try_from_yrs_value(
                     #value_at_field_name,
                     txn,
-                )?;
+                ).map_err(|e| e.in_field(#name_literal))?;
             }
         }
 
@@ -125,36 +434,28 @@ pub fn yrs_wrapper_derive(input: TokenStream) -> TokenStream {
 
                 Ok(#name_without_prelim(map_ref))
             }
+
+            /// The fields are checked by the getters.
+            fn try_from_yrs_value_shallow(
+                value: yrs::types::Value,
+                _txn: &impl yrs::ReadTxn,
+            ) -> yrs_wrappers::yrs_wrapper_error::YrsResult<Self> {
+                let map_ref = <yrs::types::Value as yrs_wrappers::yrs_wrapper_error::UnwrapYrsValue>::unwrap_yrs_map(value)?;
+
+                Ok(#name_without_prelim(map_ref))
+            }
         }
     };
 
-    let lines_in_prelim_integrate = field_names_and_types.iter().map(
-        |FieldInfo {
-             name,
-             name_literal,
-             is_option,
-             ..
-         }| {
-            let line = quote! {
-                <yrs::MapRef as yrs::Map>::insert(&map, txn, #name_literal, #name);
-            };
+    let lines_in_prelim_integrate = field_names_and_types.iter().map(|field_info| {
+        let name = &field_info.name;
+        let line = insert_field_line(field_info);
 
-            let line = if *is_option {
-                quote! {
-                    if let Some(#name) = #name {
-                        #line
-                    };
-                }
-            } else {
-                line
-            };
-
-            quote! {
-                let #name = self.#name;
-                #line
-            }
-        },
-    );
+        quote! {
+            let #name = self.#name;
+            #line
+        }
+    });
 
     let prelim_impl = quote! {
         impl yrs::block::Prelim for #name {
@@ -177,22 +478,10 @@ pub fn yrs_wrapper_derive(input: TokenStream) -> TokenStream {
         }
     };
 
-    let try_from_block_ptr_impl = quote! {
-        impl TryFrom<yrs::block::BlockPtr> for #name_without_prelim {
-            type Error = <yrs::MapRef as TryFrom<yrs::block::BlockPtr>>::Error;
-
-            fn try_from(value: yrs::block::BlockPtr) -> Result<Self, Self::Error> {
-                let map_ref: yrs::MapRef = value.try_into()?;
-
-                Ok(#name_without_prelim(map_ref))
-            }
-        }
-    };
-
     let attr_access_impls =
         field_names_and_types
             .iter()
-            .map(|FieldInfo { name, name_literal, ty, is_option }| {
+            .map(|FieldInfo { name, name_literal, ty, is_option, default }| {
                 let field_name_is_prelim_for = format_ident!("{}PrelimFor", name);
 
             let mut return_type = quote! {
@@ -210,9 +499,20 @@ pub fn yrs_wrapper_derive(input: TokenStream) -> TokenStream {
                     let yrs_value = <yrs::MapRef as yrs::Map>::get(&self.0, txn, #name_literal)?;
 
                     // I'm not sure if this will be to expensive.
-                    Some(<#field_name_is_prelim_for as yrs_wrappers::try_from_yrs_value::TryFromYrsValue>::try_from_yrs_value(
+                    Some(<#field_name_is_prelim_for as yrs_wrappers::try_from_yrs_value::TryFromYrsValue>::try_from_yrs_value_shallow(
                         yrs_value, txn,
-                    ))
+                    ).map_err(|e| e.in_field(#name_literal)))
+                }
+            } else if let Some(default) = default {
+                quote! {
+                    let yrs_value = match <yrs::MapRef as yrs::Map>::get(&self.0, txn, #name_literal) {
+                        Some(yrs_value) => yrs_value,
+                        None => return Ok(#default),
+                    };
+
+                    <#field_name_is_prelim_for as yrs_wrappers::try_from_yrs_value::TryFromYrsValue>::try_from_yrs_value_shallow(
+                        yrs_value, txn,
+                    ).map_err(|e| e.in_field(#name_literal))
                 }
             } else {
                 quote! {
@@ -223,9 +523,9 @@ pub fn yrs_wrapper_derive(input: TokenStream) -> TokenStream {
                     })?;
 
                     // I'm not sure if this will be to expensive.
-                    <#field_name_is_prelim_for as yrs_wrappers::try_from_yrs_value::TryFromYrsValue>::try_from_yrs_value(
+                    <#field_name_is_prelim_for as yrs_wrappers::try_from_yrs_value::TryFromYrsValue>::try_from_yrs_value_shallow(
                         yrs_value, txn,
-                    )
+                    ).map_err(|e| e.in_field(#name_literal))
                 }
             };
 
@@ -246,37 +546,228 @@ pub fn yrs_wrapper_derive(input: TokenStream) -> TokenStream {
             })
             .collect::<Vec<_>>();
 
+    let attr_mutation_impls = field_names_and_types
+        .iter()
+        .map(|FieldInfo { name, name_literal, ty, is_option, .. }| {
+            let set_name = format_ident!("set_{}", name);
+            let setter = quote! {
+                pub fn #set_name(
+                    &self,
+                    txn: &mut yrs::TransactionMut,
+                    value: #ty,
+                ) -> <#ty as yrs::block::Prelim>::Return {
+                    <yrs::MapRef as yrs::Map>::insert(&self.0, txn, #name_literal, value)
+                }
+            };
+
+            if !*is_option {
+                return setter;
+            }
+
+            let clear_name = format_ident!("clear_{}", name);
+            quote! {
+                #setter
+
+                pub fn #clear_name(&self, txn: &mut yrs::TransactionMut) {
+                    <yrs::MapRef as yrs::Map>::remove(&self.0, txn, #name_literal);
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let change_enum_name = format_ident!("{}Change", name_without_prelim);
+
+    let change_enum_variants = field_names_and_types
+        .iter()
+        .map(|FieldInfo { name, name_literal, ty, .. }| {
+            let variant_name = format_ident!("{}", to_pascal_case(&name.to_string()));
+            let changed_variant_name = format_ident!("{}Changed", variant_name);
+            let replaced_doc = format!(
+                " The value at `{}` was set, replaced or removed. Has the old and new values, \
+                if any. Old values that were shared types can't be read anymore, and are `None`.",
+                name_literal.to_string().trim_matches('"')
+            );
+            let changed_doc = format!(
+                " Something inside the shared type at `{}` changed.",
+                name_literal.to_string().trim_matches('"')
+            );
+
+            quote! {
+                #[doc = #replaced_doc]
+                #variant_name(
+                    Option<<#ty as yrs::block::Prelim>::Return>,
+                    Option<<#ty as yrs::block::Prelim>::Return>,
+                ),
+                #[doc = #changed_doc]
+                #changed_variant_name
+            }
+        });
+
+    let change_enum = quote! {
+        pub enum #change_enum_name {
+            #(#change_enum_variants),*
+        }
+    };
+
+    let match_arms_for_replaced_fields = field_names_and_types
+        .iter()
+        .map(|FieldInfo { name, name_literal, ty, .. }| {
+            let variant_name = format_ident!("{}", to_pascal_case(&name.to_string()));
+            quote! {
+                #name_literal => changes.push(#change_enum_name::#variant_name(
+                    old.and_then(|old| {
+                        <<#ty as yrs::block::Prelim>::Return as yrs_wrappers::try_from_yrs_value::TryFromYrsValue>::try_from_yrs_value(old, txn).ok()
+                    }),
+                    new.map(|new| {
+                        <<#ty as yrs::block::Prelim>::Return as yrs_wrappers::try_from_yrs_value::TryFromYrsValue>::try_from_yrs_value(new, txn)
+                            .map_err(|e| e.in_field(#name_literal))
+                    })
+                    .transpose()?,
+                )),
+            }
+        });
+
+    let match_arms_for_changed_fields = field_names_and_types
+        .iter()
+        .map(|FieldInfo { name, name_literal, .. }| {
+            let changed_variant_name =
+                format_ident!("{}Changed", to_pascal_case(&name.to_string()));
+            quote! {
+                #name_literal => #change_enum_name::#changed_variant_name,
+            }
+        });
+
     let main_struct_impl = quote! {
         impl #name_without_prelim {
             #(#attr_access_impls)*
+
+            #(#attr_mutation_impls)*
+
+            /// Turns the events of a deep observer on this struct into typed changes to its fields.
+            /// Changes inside a field are only reported once per field, however many events they
+            /// caused.
+            #[allow(unused_variables)]
+            pub fn changes_from_events(
+                txn: &yrs::TransactionMut,
+                events: &yrs::types::Events,
+            ) -> yrs_wrappers::yrs_wrapper_error::YrsResult<Vec<#change_enum_name>> {
+                let mut changes = vec![];
+                let mut changed_keys = std::collections::HashSet::new();
+
+                for event in events.iter() {
+                    match event.path().front() {
+                        None => {
+                            let yrs::types::Event::Map(map_event) = event else {
+                                continue;
+                            };
+                            for (key, change) in map_event.keys(txn) {
+                                let (old, new) = match change {
+                                    yrs::types::EntryChange::Inserted(new) => (None, Some(new.clone())),
+                                    yrs::types::EntryChange::Updated(old, new) => (Some(old.clone()), Some(new.clone())),
+                                    yrs::types::EntryChange::Removed(old) => (Some(old.clone()), None),
+                                };
+                                match &**key {
+                                    #(#match_arms_for_replaced_fields)*
+                                    _ => {}
+                                }
+                            }
+                        }
+                        Some(yrs::types::PathSegment::Key(key)) => {
+                            if !changed_keys.insert(key.to_string()) {
+                                continue;
+                            }
+                            changes.push(match &**key {
+                                #(#match_arms_for_changed_fields)*
+                                _ => continue,
+                            });
+                        }
+                        Some(yrs::types::PathSegment::Index(_)) => {}
+                    }
+                }
+
+                Ok(changes)
+            }
+
+            /// Like `DeepObservable::observe_deep`, but with typed changes instead of raw events.
+            pub fn observe_typed<F>(&mut self, f: F) -> yrs::types::DeepEventsSubscription
+            where
+                F: Fn(&yrs::TransactionMut, yrs_wrappers::yrs_wrapper_error::YrsResult<Vec<#change_enum_name>>) + 'static,
+            {
+                yrs::types::DeepObservable::observe_deep(&mut self.0, move |txn, events| {
+                    f(txn, Self::changes_from_events(txn, events))
+                })
+            }
         }
-    };
 
-    let as_mut_impl = quote! {
-        impl core::convert::AsMut<yrs::types::Branch> for #name_without_prelim {
-            fn as_mut(&mut self) -> &mut yrs::types::Branch {
-                <yrs::MapRef as core::convert::AsMut<yrs::types::Branch>>::as_mut(&mut self.0)
+        impl yrs_wrappers::yrs_struct::ObserveTyped for #name_without_prelim {
+            type Change = #change_enum_name;
+
+            fn observe_typed<F>(&mut self, f: F) -> yrs::types::DeepEventsSubscription
+            where
+                F: Fn(&yrs::TransactionMut, yrs_wrappers::yrs_wrapper_error::YrsResult<Vec<Self::Change>>) + 'static,
+            {
+                #name_without_prelim::observe_typed(self, f)
             }
         }
     };
 
-    let observable_impl = quote! {
-        impl yrs::types::Observable for #name_without_prelim {
+    let snapshot_name = format_ident!("{}Snapshot", name_without_prelim);
+    let snapshot_fields = field_names_and_types.iter().map(|field_info| {
+        let name = &field_info.name;
+        let snapshot_type = snapshot_type(field_info);
+        quote! { pub #name: #snapshot_type }
+    });
+    let snapshot_struct = quote! {
+        #[derive(Clone, PartialEq, Debug)]
+        pub struct #snapshot_name {
+            #(#snapshot_fields),*
+        }
+    };
 
-            type Event = <yrs::MapRef as yrs::types::Observable>::Event;
+    let fields_in_to_snapshot = field_names_and_types.iter().map(|field_info| {
+        let name = &field_info.name;
+        let value = if field_info.is_option {
+            quote! { self.#name(txn).transpose()? }
+        } else {
+            quote! { self.#name(txn)? }
+        };
+        let snapshot = to_snapshot_expr(field_info, value);
+        quote! { #name: #snapshot }
+    });
+    let fields_in_from_snapshot = field_names_and_types.iter().map(|field_info| {
+        let name = &field_info.name;
+        let prelim = from_snapshot_expr(field_info, quote! { snapshot.#name });
+        quote! { #name: #prelim }
+    });
+    let snapshot_impls = quote! {
+        impl yrs_wrappers::snapshot::ToSnapshot for #name_without_prelim {
+            type Snapshot = #snapshot_name;
 
-            fn try_observer_mut(
-                &mut self
-            ) -> Option<&mut yrs::observer::Observer<std::sync::Arc<dyn Fn(&yrs::TransactionMut<'_>, &Self::Event)>>> {
-                self.0.try_observer_mut()
+            #[allow(unused_variables)]
+            fn to_snapshot(
+                &self,
+                txn: &impl yrs::ReadTxn,
+            ) -> yrs_wrappers::yrs_wrapper_error::YrsResult<Self::Snapshot> {
+                Ok(#snapshot_name {
+                    #(#fields_in_to_snapshot),*
+                })
             }
+        }
 
-            fn try_observer(
-                &self
-            ) -> Option<&yrs::observer::Observer<std::sync::Arc<dyn Fn(&yrs::TransactionMut<'_>, &Self::Event)>>> {
-                self.0.try_observer()
+        impl yrs_wrappers::snapshot::FromSnapshot<#snapshot_name> for #name {
+            #[allow(unused_variables)]
+            fn from_snapshot(snapshot: #snapshot_name) -> Self {
+                #name {
+                    #(#fields_in_from_snapshot,)*
+                    #(#skipped_fields: Default::default(),)*
+                }
             }
+        }
 
+        impl From<#snapshot_name> for #name {
+            fn from(snapshot: #snapshot_name) -> Self {
+                <Self as yrs_wrappers::snapshot::FromSnapshot<#snapshot_name>>::from_snapshot(snapshot)
+            }
         }
     };
 
@@ -311,23 +802,595 @@ pub fn yrs_wrapper_derive(input: TokenStream) -> TokenStream {
 
     };
 
-    let gen = quote! {
+    let name_string = name_without_prelim.to_string();
+    let field_count = field_names_and_types.len();
+    let lines_in_yrs_serialize = field_names_and_types.iter().map(|field_info| {
+        let name = &field_info.name;
+        let read = if field_info.is_option {
+            quote! { self.#name(txn).transpose() }
+        } else {
+            quote! { self.#name(txn) }
+        };
+        let serialize = serialize_field_line(field_info, Some(name.to_string()), quote! { value });
+        quote! {
+            let value = #read.map_err(<S::Error as yrs_wrappers::serde::ser::Error>::custom)?;
+            #serialize
+        }
+    });
+    let deserialized_fields = field_names_and_types.iter().map(|field_info| {
+        let name = &field_info.name;
+        let deserialized_type = deserialized_type(field_info);
+        quote! { #name: #deserialized_type }
+    });
+    let fields_in_yrs_deserialize = field_names_and_types.iter().map(|field_info| {
+        let name = &field_info.name;
+        let prelim = from_deserialized_expr(field_info, quote! { fields.#name });
+        quote! { #name: #prelim }
+    });
+    let serde_impls = quote! {
+        impl yrs_wrappers::yrs_serde::YrsSerialize for #name_without_prelim {
+            fn yrs_serialize<S: yrs_wrappers::serde::Serializer>(
+                &self,
+                txn: &impl yrs::ReadTxn,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error> {
+                use yrs_wrappers::serde::ser::SerializeStruct;
+
+                let mut state = serializer.serialize_struct(#name_string, #field_count)?;
+                #(#lines_in_yrs_serialize)*
+                state.end()
+            }
+        }
+
+        impl yrs_wrappers::yrs_serde::YrsDeserialize for #name {
+            fn yrs_deserialize<'de, D: yrs_wrappers::serde::Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<Self, D::Error> {
+                #[derive(yrs_wrappers::serde::Deserialize)]
+                #[serde(crate = "yrs_wrappers::serde", rename = #name_string)]
+                struct Fields {
+                    #(#deserialized_fields),*
+                }
+
+                let fields = <Fields as yrs_wrappers::serde::Deserialize>::deserialize(deserializer)?;
+                Ok(#name {
+                    #(#fields_in_yrs_deserialize,)*
+                    #(#skipped_fields: Default::default(),)*
+                })
+            }
+        }
+
+        impl<'de> yrs_wrappers::serde::Deserialize<'de> for #name {
+            fn deserialize<D: yrs_wrappers::serde::Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<Self, D::Error> {
+                <Self as yrs_wrappers::yrs_serde::YrsDeserialize>::yrs_deserialize(deserializer)
+            }
+        }
+    };
+
+    let map_ref_newtype_impls = map_ref_newtype_impls(&name_without_prelim);
+
+    quote! {
         #main_struct
 
+        #change_enum
+
         #try_from_yrs_value_impl
 
         #prelim_impl
 
-        #try_from_block_ptr_impl
-
         #main_struct_impl
 
-        #as_mut_impl
-
-        #observable_impl
+        #map_ref_newtype_impls
 
         #yrs_display_impl
+
+        #snapshot_struct
+
+        #snapshot_impls
+
+        #serde_impls
+    }
+}
+
+struct VariantInfo {
+    name: syn::Ident,
+    name_literal: Literal,
+    fields: Fields,
+    field_infos: Vec<FieldInfo>,
+}
+
+impl VariantInfo {
+    /// The pattern (or constructor) for this variant, binding each field to its `FieldInfo::name`.
+    fn pattern(&self, enum_name: &syn::Ident) -> TokenStream2 {
+        let name = &self.name;
+        let field_names = self.field_infos.iter().map(|f| &f.name);
+        match &self.fields {
+            Fields::Unit => quote! { #enum_name::#name },
+            Fields::Unnamed(_) => quote! { #enum_name::#name(#(#field_names),*) },
+            Fields::Named(_) => quote! { #enum_name::#name { #(#field_names),* } },
+        }
+    }
+
+    /// Constructs this variant with `values` in the order of `field_infos`.
+    fn construct(&self, enum_name: &syn::Ident, values: Vec<TokenStream2>) -> TokenStream2 {
+        let name = &self.name;
+        let field_names = self.field_infos.iter().map(|f| &f.name);
+        match &self.fields {
+            Fields::Unit => quote! { #enum_name::#name },
+            Fields::Unnamed(_) => quote! { #enum_name::#name(#(#values),*) },
+            Fields::Named(_) => quote! { #enum_name::#name { #(#field_names: #values),* } },
+        }
+    }
+}
+
+/// Enums are stored as a `yrs::MapRef` whose `yrs_wrappers::yrs_struct::VARIANT_KEY` holds the
+/// name of the variant, and whose other keys hold the fields of that variant (the index of the
+/// field for tuple variants).
+fn derive_enum(name: syn::Ident, data: DataEnum) -> TokenStream2 {
+    let name_without_prelim = format_ident!("{}", name.to_string().trim_end_matches("Prelim"));
+    let variant_enum_name = format_ident!("{}Variant", name_without_prelim);
+
+    let variants = data
+        .variants
+        .into_iter()
+        .map(|v| {
+            let field_infos = match &v.fields {
+                Fields::Unit => vec![],
+                Fields::Unnamed(fields) => fields
+                    .unnamed
+                    .iter()
+                    .enumerate()
+                    .map(|(i, f)| {
+                        FieldInfo::new(format_ident!("field_{}", i), i.to_string(), f)
+                            .expect("#[yrs(skip)] is not supported in enum variants")
+                    })
+                    .collect(),
+                Fields::Named(fields) => fields
+                    .named
+                    .iter()
+                    .map(|f| {
+                        let name = f.ident.clone().unwrap();
+                        let key = name.to_string();
+                        FieldInfo::new(name, key, f)
+                            .expect("#[yrs(skip)] is not supported in enum variants")
+                    })
+                    .collect(),
+            };
+
+            VariantInfo {
+                name_literal: Literal::string(&v.ident.to_string()),
+                name: v.ident,
+                fields: v.fields,
+                field_infos,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let main_struct = quote! {
+        #[derive(Clone, Debug)]
+        pub struct #name_without_prelim(yrs::MapRef);
     };
 
-    gen.into()
+    let variant_enum_variants = variants.iter().map(|variant| {
+        let name = &variant.name;
+        let field_types = variant
+            .field_infos
+            .iter()
+            .map(|FieldInfo { ty, is_option, .. }| {
+                if *is_option {
+                    quote! { Option<<#ty as yrs::block::Prelim>::Return> }
+                } else {
+                    quote! { <#ty as yrs::block::Prelim>::Return }
+                }
+            });
+        let field_names = variant.field_infos.iter().map(|f| &f.name);
+
+        match &variant.fields {
+            Fields::Unit => quote! { #name },
+            Fields::Unnamed(_) => quote! { #name(#(#field_types),*) },
+            Fields::Named(_) => quote! { #name { #(#field_names: #field_types),* } },
+        }
+    });
+
+    let variant_enum = quote! {
+        pub enum #variant_enum_name {
+            #(#variant_enum_variants),*
+        }
+    };
+
+    let match_arms_in_variant_from_map_ref = variants.iter().map(|variant| {
+        let name_literal = &variant.name_literal;
+        let field_reads = variant.field_infos.iter().map(|field_info| {
+            let name = &field_info.name;
+            let read = read_field_expr(field_info);
+            quote! { let #name = #read; }
+        });
+        let constructor = variant.pattern(&variant_enum_name);
+
+        quote! {
+            #name_literal => {
+                #(#field_reads)*
+                Ok(#constructor)
+            }
+        }
+    });
+
+    let main_struct_impl = quote! {
+        impl #name_without_prelim {
+            /// Checks the fields of the variant with `try_from_yrs_value` if `eager`, and with
+            /// `try_from_yrs_value_shallow` otherwise.
+            #[allow(unused_variables)]
+            fn variant_from_map_ref(
+                map_ref: &yrs::MapRef,
+                txn: &impl yrs::ReadTxn,
+                eager: bool,
+            ) -> yrs_wrappers::yrs_wrapper_error::YrsResult<#variant_enum_name> {
+                let variant = <yrs::MapRef as yrs::Map>::get(map_ref, txn, yrs_wrappers::yrs_struct::VARIANT_KEY)
+                    .ok_or_else(|| yrs_wrappers::yrs_wrapper_error::YrsWrapperError::YMapMissingAttr {
+                        attr: yrs_wrappers::yrs_struct::VARIANT_KEY.to_string(),
+                    })?;
+                let variant = yrs_wrappers::yrs_wrapper_error::UnwrapAny::unwrap_any_string(
+                    yrs_wrappers::yrs_wrapper_error::UnwrapYrsValue::unwrap_yrs_any(variant)?,
+                )?;
+
+                match variant.as_str() {
+                    #(#match_arms_in_variant_from_map_ref)*
+                    _ => Err(yrs_wrappers::yrs_wrapper_error::YrsWrapperError::UnknownVariant {
+                        enum_name: stringify!(#name_without_prelim),
+                        variant,
+                    }),
+                }
+            }
+
+            pub fn variant(
+                &self,
+                txn: impl std::ops::Deref<Target = impl yrs::ReadTxn>,
+            ) -> yrs_wrappers::yrs_wrapper_error::YrsResult<#variant_enum_name> {
+                Self::variant_from_map_ref(&self.0, txn.deref(), false)
+            }
+        }
+    };
+
+    let try_from_yrs_value_impl = quote! {
+        impl yrs_wrappers::try_from_yrs_value::TryFromYrsValue for #name_without_prelim {
+            fn try_from_yrs_value(
+                value: yrs::types::Value,
+                txn: &impl yrs::ReadTxn,
+            ) -> yrs_wrappers::yrs_wrapper_error::YrsResult<Self> {
+                let map_ref = <yrs::types::Value as yrs_wrappers::yrs_wrapper_error::UnwrapYrsValue>::unwrap_yrs_map(value)?;
+
+                Self::variant_from_map_ref(&map_ref, txn, true)?;
+
+                Ok(#name_without_prelim(map_ref))
+            }
+
+            /// The variant, and its fields, are checked by `variant`.
+            fn try_from_yrs_value_shallow(
+                value: yrs::types::Value,
+                _txn: &impl yrs::ReadTxn,
+            ) -> yrs_wrappers::yrs_wrapper_error::YrsResult<Self> {
+                let map_ref = <yrs::types::Value as yrs_wrappers::yrs_wrapper_error::UnwrapYrsValue>::unwrap_yrs_map(value)?;
+
+                Ok(#name_without_prelim(map_ref))
+            }
+        }
+    };
+
+    let match_arms_in_prelim_integrate = variants.iter().map(|variant| {
+        let name_literal = &variant.name_literal;
+        let pattern = variant.pattern(&name);
+        let lines = variant.field_infos.iter().map(insert_field_line);
+
+        quote! {
+            #pattern => {
+                <yrs::MapRef as yrs::Map>::insert(&map, txn, yrs_wrappers::yrs_struct::VARIANT_KEY, #name_literal.to_string());
+                #(#lines)*
+            }
+        }
+    });
+
+    let prelim_impl = quote! {
+        impl yrs::block::Prelim for #name {
+            type Return = #name_without_prelim;
+
+            /// Copied from impl Prelim for MapRef.
+            fn into_content(
+                self,
+                txn: &mut yrs::TransactionMut,
+            ) -> (yrs::block::ItemContent, Option<Self>) {
+                let inner = yrs::types::Branch::new(yrs::types::TYPE_REFS_MAP, None);
+                (yrs::block::ItemContent::Type(inner), Some(self))
+            }
+
+            fn integrate(self, txn: &mut yrs::TransactionMut, inner_ref: yrs::types::BranchPtr) {
+                let map = yrs::MapRef::from(inner_ref);
+
+                match self {
+                    #(#match_arms_in_prelim_integrate)*
+                }
+            }
+        }
+    };
+
+    let match_arms_in_yrs_display = variants.iter().map(|variant| {
+        let name_string = variant.name.to_string();
+        let pattern = variant.pattern(&variant_enum_name);
+        let calls = variant.field_infos.iter().map(|field_info| {
+            let name = &field_info.name;
+            let value_string = if field_info.is_option {
+                quote! {
+                    match &#name {
+                        Some(value) => yrs_wrappers::yrs_display::YrsDisplay::fmt(value, txn)?,
+                        None => "None".to_string(),
+                    }
+                }
+            } else {
+                quote! { yrs_wrappers::yrs_display::YrsDisplay::fmt(&#name, txn)? }
+            };
+
+            match &variant.fields {
+                Fields::Named(_) => {
+                    let name_string = name.to_string();
+                    quote! { result = result + #name_string + ": " + &#value_string + ", "; }
+                }
+                _ => quote! { result = result + &#value_string + ", "; },
+            }
+        });
+
+        let (open, close) = match &variant.fields {
+            Fields::Unit => ("", ""),
+            Fields::Unnamed(_) => ("(", ")"),
+            Fields::Named(_) => (" { ", " }"),
+        };
+
+        quote! {
+            #pattern => {
+                result = result + #name_string + #open;
+                #(#calls)*
+                result = result + #close;
+            }
+        }
+    });
+
+    let yrs_display_impl = quote! {
+        impl yrs_wrappers::yrs_display::YrsDisplay for #name_without_prelim {
+            fn fmt(&self, txn: &impl yrs::ReadTxn) -> yrs_wrappers::yrs_wrapper_error::YrsResult<String> {
+                let mut result = String::new();
+                match self.variant(txn)? {
+                    #(#match_arms_in_yrs_display)*
+                }
+                Ok(result)
+            }
+        }
+    };
+
+    let snapshot_name = format_ident!("{}Snapshot", name_without_prelim);
+    let snapshot_variants = variants.iter().map(|variant| {
+        let name = &variant.name;
+        let snapshot_types = variant.field_infos.iter().map(snapshot_type);
+        let field_names = variant.field_infos.iter().map(|f| &f.name);
+
+        match &variant.fields {
+            Fields::Unit => quote! { #name },
+            Fields::Unnamed(_) => quote! { #name(#(#snapshot_types),*) },
+            Fields::Named(_) => quote! { #name { #(#field_names: #snapshot_types),* } },
+        }
+    });
+    let snapshot_struct = quote! {
+        #[derive(Clone, PartialEq, Debug)]
+        pub enum #snapshot_name {
+            #(#snapshot_variants),*
+        }
+    };
+
+    let match_arms_in_to_snapshot = variants.iter().map(|variant| {
+        let pattern = variant.pattern(&variant_enum_name);
+        let snapshots = variant
+            .field_infos
+            .iter()
+            .map(|field_info| {
+                let name = &field_info.name;
+                to_snapshot_expr(field_info, quote! { #name })
+            })
+            .collect();
+        let constructor = variant.construct(&snapshot_name, snapshots);
+        quote! { #pattern => #constructor, }
+    });
+    let match_arms_in_from_snapshot = variants.iter().map(|variant| {
+        let pattern = variant.pattern(&snapshot_name);
+        let prelims = variant
+            .field_infos
+            .iter()
+            .map(|field_info| {
+                let name = &field_info.name;
+                from_snapshot_expr(field_info, quote! { #name })
+            })
+            .collect();
+        let constructor = variant.construct(&name, prelims);
+        quote! { #pattern => #constructor, }
+    });
+    let snapshot_impls = quote! {
+        impl yrs_wrappers::snapshot::ToSnapshot for #name_without_prelim {
+            type Snapshot = #snapshot_name;
+
+            fn to_snapshot(
+                &self,
+                txn: &impl yrs::ReadTxn,
+            ) -> yrs_wrappers::yrs_wrapper_error::YrsResult<Self::Snapshot> {
+                Ok(match self.variant(txn)? {
+                    #(#match_arms_in_to_snapshot)*
+                })
+            }
+        }
+
+        impl yrs_wrappers::snapshot::FromSnapshot<#snapshot_name> for #name {
+            fn from_snapshot(snapshot: #snapshot_name) -> Self {
+                match snapshot {
+                    #(#match_arms_in_from_snapshot)*
+                }
+            }
+        }
+
+        impl From<#snapshot_name> for #name {
+            fn from(snapshot: #snapshot_name) -> Self {
+                <Self as yrs_wrappers::snapshot::FromSnapshot<#snapshot_name>>::from_snapshot(snapshot)
+            }
+        }
+    };
+
+    let name_string = name_without_prelim.to_string();
+    let match_arms_in_yrs_serialize = variants.iter().enumerate().map(|(index, variant)| {
+        let index = index as u32;
+        let pattern = variant.pattern(&variant_enum_name);
+        let variant_string = variant.name.to_string();
+        let field_count = variant.field_infos.len();
+
+        let body = match &variant.fields {
+            Fields::Unit => quote! {
+                serializer.serialize_unit_variant(#name_string, #index, #variant_string)
+            },
+            // serde treats tuple variants with a single field as newtype variants.
+            Fields::Unnamed(_) if field_count == 1 => {
+                let field_info = &variant.field_infos[0];
+                let name = &field_info.name;
+                let value = if field_info.is_option {
+                    quote! {
+                        &#name
+                            .as_ref()
+                            .map(|value| yrs_wrappers::yrs_serde::YrsSerialize::with_txn(value, txn))
+                    }
+                } else {
+                    quote! { &yrs_wrappers::yrs_serde::YrsSerialize::with_txn(&#name, txn) }
+                };
+                quote! {
+                    serializer.serialize_newtype_variant(#name_string, #index, #variant_string, #value)
+                }
+            }
+            Fields::Unnamed(_) => {
+                let lines = variant.field_infos.iter().map(|field_info| {
+                    let name = &field_info.name;
+                    serialize_field_line(field_info, None, quote! { #name })
+                });
+                quote! {
+                    let mut state = serializer.serialize_tuple_variant(#name_string, #index, #variant_string, #field_count)?;
+                    #(#lines)*
+                    state.end()
+                }
+            }
+            Fields::Named(_) => {
+                let lines = variant.field_infos.iter().map(|field_info| {
+                    let name = &field_info.name;
+                    serialize_field_line(field_info, Some(name.to_string()), quote! { #name })
+                });
+                quote! {
+                    let mut state = serializer.serialize_struct_variant(#name_string, #index, #variant_string, #field_count)?;
+                    #(#lines)*
+                    state.end()
+                }
+            }
+        };
+
+        quote! {
+            #pattern => {
+                #body
+            }
+        }
+    });
+
+    let deserialized_variants = variants.iter().map(|variant| {
+        let name = &variant.name;
+        let deserialized_types = variant.field_infos.iter().map(deserialized_type);
+        let field_names = variant.field_infos.iter().map(|f| &f.name);
+
+        match &variant.fields {
+            Fields::Unit => quote! { #name },
+            Fields::Unnamed(_) => quote! { #name(#(#deserialized_types),*) },
+            Fields::Named(_) => quote! { #name { #(#field_names: #deserialized_types),* } },
+        }
+    });
+    let deserialized_enum_name = format_ident!("Variants");
+    let match_arms_in_yrs_deserialize = variants.iter().map(|variant| {
+        let pattern = variant.pattern(&deserialized_enum_name);
+        let prelims = variant
+            .field_infos
+            .iter()
+            .map(|field_info| {
+                let name = &field_info.name;
+                from_deserialized_expr(field_info, quote! { #name })
+            })
+            .collect();
+        let constructor = variant.construct(&name, prelims);
+        quote! { #pattern => #constructor, }
+    });
+
+    let serde_impls = quote! {
+        impl yrs_wrappers::yrs_serde::YrsSerialize for #name_without_prelim {
+            fn yrs_serialize<S: yrs_wrappers::serde::Serializer>(
+                &self,
+                txn: &impl yrs::ReadTxn,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error> {
+                #[allow(unused_imports)]
+                use yrs_wrappers::serde::ser::{SerializeStructVariant, SerializeTupleVariant};
+
+                let variant = self
+                    .variant(txn)
+                    .map_err(<S::Error as yrs_wrappers::serde::ser::Error>::custom)?;
+                match variant {
+                    #(#match_arms_in_yrs_serialize)*
+                }
+            }
+        }
+
+        impl yrs_wrappers::yrs_serde::YrsDeserialize for #name {
+            fn yrs_deserialize<'de, D: yrs_wrappers::serde::Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<Self, D::Error> {
+                #[derive(yrs_wrappers::serde::Deserialize)]
+                #[serde(crate = "yrs_wrappers::serde", rename = #name_string)]
+                enum #deserialized_enum_name {
+                    #(#deserialized_variants),*
+                }
+
+                let variant = <#deserialized_enum_name as yrs_wrappers::serde::Deserialize>::deserialize(deserializer)?;
+                Ok(match variant {
+                    #(#match_arms_in_yrs_deserialize)*
+                })
+            }
+        }
+
+        impl<'de> yrs_wrappers::serde::Deserialize<'de> for #name {
+            fn deserialize<D: yrs_wrappers::serde::Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<Self, D::Error> {
+                <Self as yrs_wrappers::yrs_serde::YrsDeserialize>::yrs_deserialize(deserializer)
+            }
+        }
+    };
+
+    let map_ref_newtype_impls = map_ref_newtype_impls(&name_without_prelim);
+
+    quote! {
+        #main_struct
+
+        #variant_enum
+
+        #try_from_yrs_value_impl
+
+        #prelim_impl
+
+        #main_struct_impl
+
+        #map_ref_newtype_impls
+
+        #yrs_display_impl
+
+        #snapshot_struct
+
+        #snapshot_impls
+
+        #serde_impls
+    }
 }
@@ -0,0 +1,64 @@
+//! Keys that can always be generated between any two other keys, so that an item can be placed
+//! between two others by giving it a new key, without touching the keys of other items.
+//!
+//! Keys are strings of base 62 digits (`0-9A-Za-z`, which sort the same as their ASCII codes),
+//! read as the digits after the point of a fraction. They never end in `0`, so that there is
+//! always room before them.
+
+const DIGITS: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+fn digit_value(digit: u8) -> usize {
+    DIGITS
+        .iter()
+        .position(|&d| d == digit)
+        .expect("Keys should only contain base 62 digits.")
+}
+
+/// Whether `key` is one that `key_between` could have generated.
+pub fn is_valid_key(key: &str) -> bool {
+    !key.is_empty() && !key.ends_with('0') && key.bytes().all(|digit| DIGITS.contains(&digit))
+}
+
+/// A key greater than `lower` and smaller than `upper`, where `None` means "no bound". Both
+/// must be valid keys, and `lower` must be smaller than `upper`.
+pub fn key_between(lower: Option<&str>, upper: Option<&str>) -> String {
+    if let (Some(lower), Some(upper)) = (lower, upper) {
+        assert!(lower < upper, "{lower} should be smaller than {upper}");
+    }
+    midpoint(
+        lower.unwrap_or("").as_bytes(),
+        upper.map(|upper| upper.as_bytes()),
+    )
+}
+
+/// Based on https://observablehq.com/@dgreensp/implementing-fractional-indexing
+fn midpoint(lower: &[u8], upper: Option<&[u8]>) -> String {
+    if let Some(upper) = upper {
+        // Skip the digits the bounds have in common (padding `lower` with zeros).
+        let common = upper
+            .iter()
+            .enumerate()
+            .take_while(|&(i, &digit)| lower.get(i).copied().unwrap_or(DIGITS[0]) == digit)
+            .count();
+        if common > 0 {
+            let prefix = String::from_utf8_lossy(&upper[..common]);
+            let lower = lower.get(common..).unwrap_or(&[]);
+            return format!("{prefix}{}", midpoint(lower, Some(&upper[common..])));
+        }
+    }
+
+    let lower_digit = lower.first().map_or(0, |&digit| digit_value(digit));
+    let upper_digit = upper.map_or(DIGITS.len(), |upper| digit_value(upper[0]));
+    if upper_digit - lower_digit > 1 {
+        (DIGITS[(lower_digit + upper_digit + 1) / 2] as char).to_string()
+    } else if let Some(upper) = upper.filter(|upper| upper.len() > 1) {
+        // The first digit of `upper` alone is smaller than `upper`, and can't be `0`.
+        (upper[0] as char).to_string()
+    } else {
+        format!(
+            "{}{}",
+            DIGITS[lower_digit] as char,
+            midpoint(lower.get(1..).unwrap_or(&[]), None)
+        )
+    }
+}
@@ -1,10 +1,26 @@
 // I don't know why having  a backtrace in thiserror error structs require this feature.
 #![feature(error_generic_member_access)]
 
+pub use serde;
+
+// The code `YrsStruct` generates refers to this crate by name, which lets the tests of this crate
+// derive it, too.
+extern crate self as yrs_wrappers;
+
+pub mod fractional_index;
+pub mod snapshot;
 pub mod try_from_yrs_value;
+pub mod validation;
 pub mod ybox;
+pub mod yref;
 pub mod yrs_basic_types;
 pub mod yrs_display;
+pub mod yrs_map;
+pub mod yrs_ordered;
+pub mod yrs_serde;
 pub mod yrs_struct;
 pub mod yrs_vec;
 pub mod yrs_wrapper_error;
+
+#[cfg(test)]
+mod test_utils;
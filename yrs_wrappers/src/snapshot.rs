@@ -0,0 +1,31 @@
+use yrs::GetString;
+use yrs::ReadTxn;
+
+use crate::yrs_wrapper_error::YrsResult;
+
+/// Converts a wrapper into an owned, plain Rust value that doesn't need a transaction to be read.
+pub trait ToSnapshot {
+    type Snapshot;
+
+    fn to_snapshot(&self, txn: &impl ReadTxn) -> YrsResult<Self::Snapshot>;
+}
+
+/// The reverse of `ToSnapshot`, implemented by the prelim of the wrapper, so that a snapshot can
+/// be inserted into a document again.
+pub trait FromSnapshot<S> {
+    fn from_snapshot(snapshot: S) -> Self;
+}
+
+impl ToSnapshot for yrs::TextRef {
+    type Snapshot = String;
+
+    fn to_snapshot(&self, txn: &impl ReadTxn) -> YrsResult<Self::Snapshot> {
+        Ok(self.get_string(txn))
+    }
+}
+
+impl FromSnapshot<String> for yrs::TextPrelim<String> {
+    fn from_snapshot(snapshot: String) -> Self {
+        yrs::TextPrelim::new(snapshot)
+    }
+}
@@ -0,0 +1,10 @@
+//! What the tests of the wrappers share.
+
+use yrs::{Doc, MapRef};
+
+/// A fresh document, with the map that tests put their values in.
+pub fn test_doc() -> (Doc, MapRef) {
+    let doc = Doc::new();
+    let map = doc.get_or_insert_map("state");
+    (doc, map)
+}
@@ -1,19 +1,43 @@
+use crate::validation::{try_from_yrs_value_cached, Validation};
 use crate::yrs_wrapper_error::{YrsResult, YrsWrapperError};
 
 pub trait TryFromYrsValue: Sized {
+    /// Converts the value, after checking that it, and everything nested in it, is valid.
     fn try_from_yrs_value(
         value: yrs::types::Value,
         txn: &impl yrs::ReadTxn,
     ) -> Result<Self, YrsWrapperError>;
+
+    /// Converts the value, checking only as much as is needed to construct `Self`. Types that
+    /// contain other values, and check those again when they are accessed, should override this.
+    fn try_from_yrs_value_shallow(
+        value: yrs::types::Value,
+        txn: &impl yrs::ReadTxn,
+    ) -> Result<Self, YrsWrapperError> {
+        Self::try_from_yrs_value(value, txn)
+    }
+
+    fn try_from_yrs_value_with(
+        value: yrs::types::Value,
+        txn: &impl yrs::ReadTxn,
+        validation: Validation,
+    ) -> Result<Self, YrsWrapperError>
+    where
+        Self: 'static,
+    {
+        match validation {
+            Validation::Eager => Self::try_from_yrs_value(value, txn),
+            Validation::Lazy => Self::try_from_yrs_value_shallow(value, txn),
+            Validation::Cached => try_from_yrs_value_cached(value, txn),
+        }
+    }
 }
 
 impl TryFromYrsValue for yrs::TextRef {
     fn try_from_yrs_value(value: yrs::types::Value, _txn: &impl yrs::ReadTxn) -> YrsResult<Self> {
         match value {
             yrs::types::Value::YText(text_ref) => Ok(text_ref),
-            _ => Err(YrsWrapperError::UnexpectedYrsValue {
-                expected: "TextRef",
-            }),
+            other => Err(YrsWrapperError::unexpected_yrs_value("TextRef", &other)),
         }
     }
 }
@@ -22,9 +46,7 @@ impl TryFromYrsValue for yrs::XmlTextRef {
     fn try_from_yrs_value(value: yrs::types::Value, _txn: &impl yrs::ReadTxn) -> YrsResult<Self> {
         match value {
             yrs::types::Value::YXmlText(yxml_text_ref) => Ok(yxml_text_ref),
-            _ => Err(YrsWrapperError::UnexpectedYrsValue {
-                expected: "XmlTextRef",
-            }),
+            other => Err(YrsWrapperError::unexpected_yrs_value("XmlTextRef", &other)),
         }
     }
 }
@@ -33,7 +55,7 @@ impl TryFromYrsValue for yrs::MapRef {
     fn try_from_yrs_value(value: yrs::types::Value, _txn: &impl yrs::ReadTxn) -> YrsResult<Self> {
         match value {
             yrs::types::Value::YMap(ymap_ref) => Ok(ymap_ref),
-            _ => Err(YrsWrapperError::UnexpectedYrsValue { expected: "MapRef" }),
+            other => Err(YrsWrapperError::unexpected_yrs_value("MapRef", &other)),
         }
     }
 }
@@ -42,9 +64,7 @@ impl TryFromYrsValue for yrs::ArrayRef {
     fn try_from_yrs_value(value: yrs::types::Value, _txn: &impl yrs::ReadTxn) -> YrsResult<Self> {
         match value {
             yrs::types::Value::YArray(array_ref) => Ok(array_ref),
-            _ => Err(YrsWrapperError::UnexpectedYrsValue {
-                expected: "ArrayRef",
-            }),
+            other => Err(YrsWrapperError::unexpected_yrs_value("ArrayRef", &other)),
         }
     }
 }
@@ -53,9 +73,10 @@ impl TryFromYrsValue for yrs::XmlFragmentRef {
     fn try_from_yrs_value(value: yrs::types::Value, _txn: &impl yrs::ReadTxn) -> YrsResult<Self> {
         match value {
             yrs::types::Value::YXmlFragment(i) => Ok(i),
-            _ => Err(YrsWrapperError::UnexpectedYrsValue {
-                expected: "XmlFragmentRef",
-            }),
+            other => Err(YrsWrapperError::unexpected_yrs_value(
+                "XmlFragmentRef",
+                &other,
+            )),
         }
     }
 }
@@ -64,9 +85,10 @@ impl TryFromYrsValue for yrs::XmlElementRef {
     fn try_from_yrs_value(value: yrs::types::Value, _txn: &impl yrs::ReadTxn) -> YrsResult<Self> {
         match value {
             yrs::types::Value::YXmlElement(i) => Ok(i),
-            _ => Err(YrsWrapperError::UnexpectedYrsValue {
-                expected: "XmlElementRef",
-            }),
+            other => Err(YrsWrapperError::unexpected_yrs_value(
+                "XmlElementRef",
+                &other,
+            )),
         }
     }
 }
@@ -75,7 +97,7 @@ impl TryFromYrsValue for yrs::Doc {
     fn try_from_yrs_value(value: yrs::types::Value, _txn: &impl yrs::ReadTxn) -> YrsResult<Self> {
         match value {
             yrs::types::Value::YDoc(i) => Ok(i),
-            _ => Err(YrsWrapperError::UnexpectedYrsValue { expected: "Doc" }),
+            other => Err(YrsWrapperError::unexpected_yrs_value("Doc", &other)),
         }
     }
 }
@@ -87,4 +109,11 @@ where
     fn try_from_yrs_value(value: yrs::types::Value, txn: &impl yrs::ReadTxn) -> YrsResult<Self> {
         Ok(Box::new(T::try_from_yrs_value(value, txn)?))
     }
+
+    fn try_from_yrs_value_shallow(
+        value: yrs::types::Value,
+        txn: &impl yrs::ReadTxn,
+    ) -> YrsResult<Self> {
+        Ok(Box::new(T::try_from_yrs_value_shallow(value, txn)?))
+    }
 }
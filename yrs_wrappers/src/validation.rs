@@ -0,0 +1,80 @@
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use yrs::types::BranchID;
+use yrs::{ReadTxn, Snapshot};
+
+use crate::try_from_yrs_value::TryFromYrsValue;
+use crate::yrs_wrapper_error::YrsResult;
+
+/// How thoroughly `TryFromYrsValue::try_from_yrs_value_with` checks a value.
+///
+/// Whatever is picked, getters (and `YrsVec::iter`/`YrsVec::get`, etc.) check the part of the
+/// value they read, so a value that turns out to be invalid produces an error on access at the
+/// latest, never a panic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Validation {
+    /// Check the whole tree up front.
+    #[default]
+    Eager,
+
+    /// Only check the outermost value, and leave the rest to be checked on access.
+    Lazy,
+
+    /// Like `Eager`, unless the same value was already checked as the same type in the current
+    /// state of the document (its state vector and delete set), in which case like `Lazy`.
+    Cached,
+}
+
+/// How many values `Validation::Cached` remembers before it forgets all of them. Checking a value
+/// again is only as expensive as `Eager`, and most entries are stale by then anyway.
+const VALIDATED_CAPACITY: usize = 1024;
+
+thread_local! {
+    /// The state of the document in which a branch, identified by its ID, was last found to be a
+    /// valid value of a type. IDs are shared by the replicas of a document, but replicas in the
+    /// same state have the same values, so they can share the entries, too.
+    static VALIDATED: RefCell<HashMap<(BranchID, TypeId), Snapshot>> = RefCell::new(HashMap::new());
+}
+
+fn branch_id(value: &mut yrs::types::Value) -> Option<BranchID> {
+    let branch: &mut yrs::types::Branch = match value {
+        yrs::types::Value::YMap(map_ref) => map_ref.as_mut(),
+        yrs::types::Value::YArray(array_ref) => array_ref.as_mut(),
+        yrs::types::Value::YText(text_ref) => text_ref.as_mut(),
+        _ => return None,
+    };
+    Some(branch.id())
+}
+
+pub(crate) fn try_from_yrs_value_cached<T: TryFromYrsValue + 'static>(
+    mut value: yrs::types::Value,
+    txn: &impl ReadTxn,
+) -> YrsResult<T> {
+    let Some(id) = branch_id(&mut value) else {
+        return T::try_from_yrs_value(value, txn);
+    };
+    let key = (id, TypeId::of::<T>());
+    let snapshot = txn.snapshot();
+
+    let validated = VALIDATED.with(|validated| validated.borrow().get(&key) == Some(&snapshot));
+    if validated {
+        return T::try_from_yrs_value_shallow(value, txn);
+    }
+
+    let result = T::try_from_yrs_value(value, txn)?;
+    VALIDATED.with(|validated| {
+        let mut validated = validated.borrow_mut();
+        if validated.len() >= VALIDATED_CAPACITY && !validated.contains_key(&key) {
+            validated.clear();
+        }
+        validated.insert(key, snapshot);
+    });
+    Ok(result)
+}
+
+/// Forgets which values `Validation::Cached` has checked. Useful after dropping a large document.
+pub fn clear_validation_cache() {
+    VALIDATED.with(|validated| validated.borrow_mut().clear());
+}
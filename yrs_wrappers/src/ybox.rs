@@ -1,5 +1,9 @@
 use crate::{
-    try_from_yrs_value::TryFromYrsValue, yrs_display::YrsDisplay, yrs_wrapper_error::YrsResult,
+    snapshot::{FromSnapshot, ToSnapshot},
+    try_from_yrs_value::TryFromYrsValue,
+    yrs_display::YrsDisplay,
+    yrs_serde::{YrsDeserialize, YrsSerialize},
+    yrs_wrapper_error::YrsResult,
 };
 use yrs::block::{BlockPtr, Prelim};
 
@@ -13,6 +17,13 @@ where
     fn try_from_yrs_value(value: yrs::types::Value, txn: &impl yrs::ReadTxn) -> YrsResult<Self> {
         Ok(YBox(Box::new(T::try_from_yrs_value(value, txn)?)))
     }
+
+    fn try_from_yrs_value_shallow(
+        value: yrs::types::Value,
+        txn: &impl yrs::ReadTxn,
+    ) -> YrsResult<Self> {
+        Ok(YBox(Box::new(T::try_from_yrs_value_shallow(value, txn)?)))
+    }
 }
 
 impl<T> Prelim for YBox<T>
@@ -56,3 +67,35 @@ impl<T: YrsDisplay> YrsDisplay for YBox<T> {
         self.0.fmt(txn)
     }
 }
+
+impl<T: ToSnapshot> ToSnapshot for YBox<T> {
+    type Snapshot = Box<T::Snapshot>;
+
+    fn to_snapshot(&self, txn: &impl yrs::ReadTxn) -> YrsResult<Self::Snapshot> {
+        self.0.to_snapshot(txn).map(Box::new)
+    }
+}
+
+impl<S, P: FromSnapshot<S>> FromSnapshot<Box<S>> for YBox<P> {
+    fn from_snapshot(snapshot: Box<S>) -> Self {
+        YBox::new(P::from_snapshot(*snapshot))
+    }
+}
+
+impl<T: YrsSerialize> YrsSerialize for YBox<T> {
+    fn yrs_serialize<S: serde::Serializer>(
+        &self,
+        txn: &impl yrs::ReadTxn,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        self.0.yrs_serialize(txn, serializer)
+    }
+}
+
+impl<P: YrsDeserialize> YrsDeserialize for YBox<P> {
+    fn yrs_deserialize<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        P::yrs_deserialize(deserializer).map(YBox::new)
+    }
+}
@@ -0,0 +1,241 @@
+//! References by `YId`, which stay valid when the referenced value moves around in the
+//! document, unlike array positions.
+//!
+//! A `YRef<T>` only stores the ID. To get at the `T`, it has to be looked up in a `YIndex<T>`,
+//! which maps the IDs of all the `T`s in the document to them. Since the referenced value can be
+//! deleted (possibly concurrently with the reference being created), a lookup can fail with
+//! `YrsWrapperError::DanglingRef`.
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use uuid::Uuid;
+
+use crate::{
+    snapshot::{FromSnapshot, ToSnapshot},
+    try_from_yrs_value::TryFromYrsValue,
+    yrs_basic_types::{YId, YIdPrelim},
+    yrs_display::YrsDisplay,
+    yrs_serde::{YrsDeserialize, YrsSerialize},
+    yrs_wrapper_error::{UnwrapAny, UnwrapYrsValue, YrsResult, YrsWrapperError},
+};
+
+pub struct YRefPrelim<T> {
+    id: Uuid,
+    phantom: PhantomData<T>,
+}
+
+impl<T> YRefPrelim<T> {
+    pub fn new(id: Uuid) -> Self {
+        YRefPrelim {
+            id,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> From<YId> for YRefPrelim<T> {
+    fn from(value: YId) -> Self {
+        YRefPrelim::new(*value)
+    }
+}
+
+impl<T> From<&YIdPrelim> for YRefPrelim<T> {
+    fn from(value: &YIdPrelim) -> Self {
+        YRefPrelim::new(value.id())
+    }
+}
+
+/// A reference to a `T` elsewhere in the document.
+pub struct YRef<T> {
+    id: Uuid,
+    phantom: PhantomData<T>,
+}
+
+impl<T> Clone for YRef<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for YRef<T> {}
+
+impl<T> std::fmt::Debug for YRef<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("YRef").field(&self.id).finish()
+    }
+}
+
+impl<T> PartialEq for YRef<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Eq for YRef<T> {}
+
+impl<T> YRef<T> {
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// Looks up the referenced value, failing with `YrsWrapperError::DanglingRef` if it's not in
+    /// the document (anymore).
+    pub fn resolve<'a>(&self, index: &'a YIndex<T>) -> YrsResult<&'a T> {
+        index
+            .get(self.id)
+            .ok_or(YrsWrapperError::DanglingRef { id: self.id })
+    }
+
+    pub fn is_dangling(&self, index: &YIndex<T>) -> bool {
+        !index.contains(self.id)
+    }
+}
+
+fn ref_from_str<T>(value: &str) -> YrsResult<YRef<T>> {
+    Uuid::try_parse(value)
+        .map(|id| YRef {
+            id,
+            phantom: PhantomData,
+        })
+        .map_err(|_| YrsWrapperError::MalformedString {
+            expected: "UUID",
+            value: value.to_string(),
+        })
+}
+
+impl<T> yrs::block::Prelim for YRefPrelim<T> {
+    type Return = YRef<T>;
+
+    fn into_content(
+        self,
+        _txn: &mut yrs::TransactionMut,
+    ) -> (yrs::block::ItemContent, Option<Self>) {
+        // Stored just like a `YId`.
+        let value: lib0::any::Any = self.id.to_string().into();
+        (yrs::block::ItemContent::Any(vec![value]), None)
+    }
+
+    fn integrate(self, _txn: &mut yrs::TransactionMut, _inner_ref: yrs::types::BranchPtr) {}
+}
+
+impl<T> TryFromYrsValue for YRef<T> {
+    /// Doesn't check that the referenced value exists, since that needs a `YIndex`.
+    fn try_from_yrs_value(value: yrs::types::Value, _txn: &impl yrs::ReadTxn) -> YrsResult<Self> {
+        ref_from_str(&value.unwrap_yrs_any()?.unwrap_any_string()?)
+    }
+}
+
+impl<T> TryFrom<yrs::block::BlockPtr> for YRef<T> {
+    type Error = YrsWrapperError;
+
+    fn try_from(value: yrs::block::BlockPtr) -> Result<Self, Self::Error> {
+        let any: lib0::any::Any =
+            value
+                .try_into()
+                .map_err(|_| YrsWrapperError::FromBlockPtrError {
+                    expected: "lib0::any::Any",
+                })?;
+
+        ref_from_str(&any.unwrap_any_string()?)
+    }
+}
+
+impl<T> ToSnapshot for YRef<T> {
+    type Snapshot = Uuid;
+
+    fn to_snapshot(&self, _txn: &impl yrs::ReadTxn) -> YrsResult<Self::Snapshot> {
+        Ok(self.id)
+    }
+}
+
+impl<T> FromSnapshot<Uuid> for YRefPrelim<T> {
+    fn from_snapshot(snapshot: Uuid) -> Self {
+        YRefPrelim::new(snapshot)
+    }
+}
+
+impl<T> YrsSerialize for YRef<T> {
+    fn yrs_serialize<S: Serializer>(
+        &self,
+        _txn: &impl yrs::ReadTxn,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        self.id.serialize(serializer)
+    }
+}
+
+impl<T> YrsDeserialize for YRefPrelim<T> {
+    fn yrs_deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Uuid::deserialize(deserializer).map(YRefPrelim::new)
+    }
+}
+
+impl<T> YrsDisplay for YRef<T> {
+    fn fmt(&self, _txn: &impl yrs::ReadTxn) -> YrsResult<String> {
+        Ok(format!("&{}", self.id))
+    }
+}
+
+/// The `T`s in a document, by ID. It's a snapshot: it has to be rebuilt to see values that were
+/// inserted or removed since it was built.
+#[derive(Clone, Debug)]
+pub struct YIndex<T> {
+    by_id: HashMap<Uuid, T>,
+}
+
+impl<T> YIndex<T> {
+    pub fn new() -> Self {
+        YIndex {
+            by_id: HashMap::new(),
+        }
+    }
+
+    /// Returns the value that was already indexed under `id`, if any.
+    pub fn insert(&mut self, id: YId, value: T) -> Option<T> {
+        self.by_id.insert(*id, value)
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<&T> {
+        self.by_id.get(&id)
+    }
+
+    pub fn contains(&self, id: Uuid) -> bool {
+        self.by_id.contains_key(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_id.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_id.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Uuid, &T)> {
+        self.by_id.iter().map(|(id, value)| (*id, value))
+    }
+
+    /// The references among `refs` that don't resolve.
+    pub fn dangling<'a>(&self, refs: impl IntoIterator<Item = &'a YRef<T>>) -> Vec<&'a YRef<T>>
+    where
+        T: 'a,
+    {
+        refs.into_iter().filter(|r| r.is_dangling(self)).collect()
+    }
+}
+
+impl<T> Default for YIndex<T> {
+    fn default() -> Self {
+        YIndex::new()
+    }
+}
+
+impl<T> FromIterator<(YId, T)> for YIndex<T> {
+    fn from_iter<I: IntoIterator<Item = (YId, T)>>(iter: I) -> Self {
+        YIndex {
+            by_id: iter.into_iter().map(|(id, value)| (*id, value)).collect(),
+        }
+    }
+}
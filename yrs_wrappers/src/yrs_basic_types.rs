@@ -1,17 +1,52 @@
-use chrono::NaiveDateTime;
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, SecondsFormat, Timelike};
+use chrono_tz::Tz;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use uuid::Uuid;
 
 use crate::{
+    snapshot::{FromSnapshot, ToSnapshot},
     try_from_yrs_value::TryFromYrsValue,
     yrs_display::YrsDisplay,
+    yrs_serde::{YrsDeserialize, YrsSerialize},
     yrs_wrapper_error::{UnwrapAny, UnwrapYrsValue, YrsResult, YrsWrapperError},
 };
 
 #[derive(derive_more::From, Debug)]
 pub struct YBoolPrelim(bool);
 
-#[derive(Debug, derive_more::Deref)]
+#[derive(derive_more::From, Debug, derive_more::Deref)]
 pub struct YBool(bool);
 
+impl ToSnapshot for YBool {
+    type Snapshot = bool;
+
+    fn to_snapshot(&self, _txn: &impl yrs::ReadTxn) -> YrsResult<Self::Snapshot> {
+        Ok(self.0)
+    }
+}
+
+impl FromSnapshot<bool> for YBoolPrelim {
+    fn from_snapshot(snapshot: bool) -> Self {
+        Self(snapshot)
+    }
+}
+
+impl YrsSerialize for YBool {
+    fn yrs_serialize<S: Serializer>(
+        &self,
+        _txn: &impl yrs::ReadTxn,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bool(self.0)
+    }
+}
+
+impl YrsDeserialize for YBoolPrelim {
+    fn yrs_deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        bool::deserialize(deserializer).map(Self)
+    }
+}
+
 impl YrsDisplay for YBool {
     fn fmt(&self, _txn: &impl yrs::ReadTxn) -> YrsResult<String> {
         Ok(self.0.to_string())
@@ -56,9 +91,57 @@ impl TryFrom<yrs::block::BlockPtr> for YBool {
 #[derive(derive_more::From, Debug)]
 pub struct YDateTimePrelim(NaiveDateTime);
 
-#[derive(Debug, derive_more::Deref)]
+#[derive(derive_more::From, Debug, derive_more::Deref)]
 pub struct YDateTime(NaiveDateTime);
 
+const DATE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+/// Stored like `2023-05-01T08:00:00.123456789`, with as many fractional digits as needed, so that
+/// nothing is lost. Documents from before that store the milliseconds since the epoch instead,
+/// which are still read. Code that reads only those can't read these, so documents that may
+/// contain them need a new schema version (see `wire::state::migration`).
+fn date_time_from_any(any: lib0::any::Any) -> YrsResult<NaiveDateTime> {
+    match any {
+        lib0::any::Any::String(value) => NaiveDateTime::parse_from_str(&value, DATE_TIME_FORMAT)
+            .map_err(|_| YrsWrapperError::MalformedString {
+                expected: "date time",
+                value: value.to_string(),
+            }),
+        any => NaiveDateTime::from_timestamp_millis(any.unwrap_any_bigint()?)
+            .ok_or(YrsWrapperError::BigIntOutOfRange),
+    }
+}
+
+impl ToSnapshot for YDateTime {
+    type Snapshot = NaiveDateTime;
+
+    fn to_snapshot(&self, _txn: &impl yrs::ReadTxn) -> YrsResult<Self::Snapshot> {
+        Ok(self.0)
+    }
+}
+
+impl FromSnapshot<NaiveDateTime> for YDateTimePrelim {
+    fn from_snapshot(snapshot: NaiveDateTime) -> Self {
+        Self(snapshot)
+    }
+}
+
+impl YrsSerialize for YDateTime {
+    fn yrs_serialize<S: Serializer>(
+        &self,
+        _txn: &impl yrs::ReadTxn,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl YrsDeserialize for YDateTimePrelim {
+    fn yrs_deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        NaiveDateTime::deserialize(deserializer).map(Self)
+    }
+}
+
 impl YrsDisplay for YDateTime {
     fn fmt(&self, _txn: &impl yrs::ReadTxn) -> YrsResult<String> {
         Ok(self.0.to_string())
@@ -73,7 +156,7 @@ impl yrs::block::Prelim for YDateTimePrelim {
         _txn: &mut yrs::TransactionMut,
     ) -> (yrs::block::ItemContent, Option<Self>) {
         // Copied from Prelim implementation for lib0::any::Any
-        let value: lib0::any::Any = self.0.timestamp_millis().into();
+        let value: lib0::any::Any = self.0.format(DATE_TIME_FORMAT).to_string().into();
         (yrs::block::ItemContent::Any(vec![value]), None)
     }
 
@@ -82,11 +165,7 @@ impl yrs::block::Prelim for YDateTimePrelim {
 
 impl TryFromYrsValue for YDateTime {
     fn try_from_yrs_value(value: yrs::types::Value, _txn: &impl yrs::ReadTxn) -> YrsResult<Self> {
-        let millis = value.unwrap_yrs_any()?.unwrap_any_bigint()?;
-        Ok(YDateTime(
-            NaiveDateTime::from_timestamp_millis(millis)
-                .ok_or_else(|| YrsWrapperError::BigIntOutOfRange)?,
-        ))
+        date_time_from_any(value.unwrap_yrs_any()?).map(YDateTime)
     }
 }
 
@@ -101,20 +180,46 @@ impl TryFrom<yrs::block::BlockPtr> for YDateTime {
                     expected: "lib0::any::Any",
                 })?;
 
-        let millis = any.unwrap_any_bigint()?;
-        Ok(YDateTime(
-            NaiveDateTime::from_timestamp_millis(millis)
-                .ok_or_else(|| YrsWrapperError::BigIntOutOfRange)?,
-        ))
+        date_time_from_any(any).map(YDateTime)
     }
 }
 
 #[derive(derive_more::From, Debug)]
 pub struct YDurationPrelim(chrono::Duration);
 
-#[derive(Debug, derive_more::Deref)]
+#[derive(derive_more::From, Debug, derive_more::Deref)]
 pub struct YDuration(chrono::Duration);
 
+impl ToSnapshot for YDuration {
+    type Snapshot = chrono::Duration;
+
+    fn to_snapshot(&self, _txn: &impl yrs::ReadTxn) -> YrsResult<Self::Snapshot> {
+        Ok(self.0)
+    }
+}
+
+impl FromSnapshot<chrono::Duration> for YDurationPrelim {
+    fn from_snapshot(snapshot: chrono::Duration) -> Self {
+        Self(snapshot)
+    }
+}
+
+impl YrsSerialize for YDuration {
+    fn yrs_serialize<S: Serializer>(
+        &self,
+        _txn: &impl yrs::ReadTxn,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(self.0.num_milliseconds())
+    }
+}
+
+impl YrsDeserialize for YDurationPrelim {
+    fn yrs_deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        i64::deserialize(deserializer).map(|millis| Self(chrono::Duration::milliseconds(millis)))
+    }
+}
+
 impl YrsDisplay for YDuration {
     fn fmt(&self, _txn: &impl yrs::ReadTxn) -> YrsResult<String> {
         Ok(self.0.to_string())
@@ -158,3 +263,715 @@ impl TryFrom<yrs::block::BlockPtr> for YDuration {
         Ok(YDuration(chrono::Duration::milliseconds(millis)))
     }
 }
+
+/// An instant, together with the IANA time zone it should be shown in.
+#[derive(derive_more::From, Debug)]
+pub struct YZonedDateTimePrelim(DateTime<Tz>);
+
+#[derive(derive_more::From, Debug, derive_more::Deref)]
+pub struct YZonedDateTime(DateTime<Tz>);
+
+/// Stored like `2023-05-01T10:00:00.5+02:00[Europe/Berlin]`, i.e., RFC 3339 with as many
+/// fractional digits as needed, followed by the zone in brackets.
+fn zoned_date_time_to_string(date_time: &DateTime<Tz>) -> String {
+    format!(
+        "{}[{}]",
+        date_time.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        date_time.timezone().name()
+    )
+}
+
+fn zoned_date_time_from_str(value: &str) -> YrsResult<DateTime<Tz>> {
+    let malformed = || YrsWrapperError::MalformedString {
+        expected: "zoned date time",
+        value: value.to_string(),
+    };
+
+    let (instant, zone) = value
+        .strip_suffix(']')
+        .and_then(|value| value.split_once('['))
+        .ok_or_else(malformed)?;
+    let zone: Tz = zone.parse().map_err(|_| malformed())?;
+    Ok(DateTime::parse_from_rfc3339(instant)
+        .map_err(|_| malformed())?
+        .with_timezone(&zone))
+}
+
+impl ToSnapshot for YZonedDateTime {
+    type Snapshot = DateTime<Tz>;
+
+    fn to_snapshot(&self, _txn: &impl yrs::ReadTxn) -> YrsResult<Self::Snapshot> {
+        Ok(self.0)
+    }
+}
+
+impl FromSnapshot<DateTime<Tz>> for YZonedDateTimePrelim {
+    fn from_snapshot(snapshot: DateTime<Tz>) -> Self {
+        Self(snapshot)
+    }
+}
+
+impl YrsSerialize for YZonedDateTime {
+    fn yrs_serialize<S: Serializer>(
+        &self,
+        _txn: &impl yrs::ReadTxn,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&zoned_date_time_to_string(&self.0))
+    }
+}
+
+impl YrsDeserialize for YZonedDateTimePrelim {
+    fn yrs_deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        zoned_date_time_from_str(&value)
+            .map(Self)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl YrsDisplay for YZonedDateTime {
+    fn fmt(&self, _txn: &impl yrs::ReadTxn) -> YrsResult<String> {
+        Ok(self.0.to_string())
+    }
+}
+
+impl yrs::block::Prelim for YZonedDateTimePrelim {
+    type Return = YZonedDateTime;
+
+    fn into_content(
+        self,
+        _txn: &mut yrs::TransactionMut,
+    ) -> (yrs::block::ItemContent, Option<Self>) {
+        // Copied from Prelim implementation for lib0::any::Any
+        let value: lib0::any::Any = zoned_date_time_to_string(&self.0).into();
+        (yrs::block::ItemContent::Any(vec![value]), None)
+    }
+
+    fn integrate(self, _txn: &mut yrs::TransactionMut, _inner_ref: yrs::types::BranchPtr) {}
+}
+
+impl TryFromYrsValue for YZonedDateTime {
+    fn try_from_yrs_value(value: yrs::types::Value, _txn: &impl yrs::ReadTxn) -> YrsResult<Self> {
+        let value = value.unwrap_yrs_any()?.unwrap_any_string()?;
+        zoned_date_time_from_str(&value).map(YZonedDateTime)
+    }
+}
+
+impl TryFrom<yrs::block::BlockPtr> for YZonedDateTime {
+    type Error = YrsWrapperError;
+
+    fn try_from(value: yrs::block::BlockPtr) -> Result<Self, Self::Error> {
+        let any: lib0::any::Any =
+            value
+                .try_into()
+                .map_err(|_| YrsWrapperError::FromBlockPtrError {
+                    expected: "lib0::any::Any",
+                })?;
+
+        let value = any.unwrap_any_string()?;
+        zoned_date_time_from_str(&value).map(YZonedDateTime)
+    }
+}
+
+/// A calendar date without a time, e.g., for all-day deadlines.
+#[derive(derive_more::From, Debug)]
+pub struct YDatePrelim(NaiveDate);
+
+#[derive(derive_more::From, Debug, derive_more::Deref)]
+pub struct YDate(NaiveDate);
+
+/// Stored as the number of days since 0001-01-01.
+fn date_from_days(days: i64) -> YrsResult<NaiveDate> {
+    i32::try_from(days)
+        .ok()
+        .and_then(NaiveDate::from_num_days_from_ce_opt)
+        .ok_or(YrsWrapperError::BigIntOutOfRange)
+}
+
+impl ToSnapshot for YDate {
+    type Snapshot = NaiveDate;
+
+    fn to_snapshot(&self, _txn: &impl yrs::ReadTxn) -> YrsResult<Self::Snapshot> {
+        Ok(self.0)
+    }
+}
+
+impl FromSnapshot<NaiveDate> for YDatePrelim {
+    fn from_snapshot(snapshot: NaiveDate) -> Self {
+        Self(snapshot)
+    }
+}
+
+impl YrsSerialize for YDate {
+    fn yrs_serialize<S: Serializer>(
+        &self,
+        _txn: &impl yrs::ReadTxn,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl YrsDeserialize for YDatePrelim {
+    fn yrs_deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        NaiveDate::deserialize(deserializer).map(Self)
+    }
+}
+
+impl YrsDisplay for YDate {
+    fn fmt(&self, _txn: &impl yrs::ReadTxn) -> YrsResult<String> {
+        Ok(self.0.to_string())
+    }
+}
+
+impl yrs::block::Prelim for YDatePrelim {
+    type Return = YDate;
+
+    fn into_content(
+        self,
+        _txn: &mut yrs::TransactionMut,
+    ) -> (yrs::block::ItemContent, Option<Self>) {
+        // Copied from Prelim implementation for lib0::any::Any
+        let value: lib0::any::Any = i64::from(self.0.num_days_from_ce()).into();
+        (yrs::block::ItemContent::Any(vec![value]), None)
+    }
+
+    fn integrate(self, _txn: &mut yrs::TransactionMut, _inner_ref: yrs::types::BranchPtr) {}
+}
+
+impl TryFromYrsValue for YDate {
+    fn try_from_yrs_value(value: yrs::types::Value, _txn: &impl yrs::ReadTxn) -> YrsResult<Self> {
+        let days = value.unwrap_yrs_any()?.unwrap_any_bigint()?;
+        date_from_days(days).map(YDate)
+    }
+}
+
+impl TryFrom<yrs::block::BlockPtr> for YDate {
+    type Error = YrsWrapperError;
+
+    fn try_from(value: yrs::block::BlockPtr) -> Result<Self, Self::Error> {
+        let any: lib0::any::Any =
+            value
+                .try_into()
+                .map_err(|_| YrsWrapperError::FromBlockPtrError {
+                    expected: "lib0::any::Any",
+                })?;
+
+        let days = any.unwrap_any_bigint()?;
+        date_from_days(days).map(YDate)
+    }
+}
+
+/// A time of day, without a date or a time zone.
+#[derive(derive_more::From, Debug)]
+pub struct YTimePrelim(NaiveTime);
+
+#[derive(derive_more::From, Debug, derive_more::Deref)]
+pub struct YTime(NaiveTime);
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+/// Stored as the number of nanoseconds since midnight. A leap second is stored as an
+/// overflow of the nanoseconds of the second before it, like chrono does.
+fn time_to_nanos(time: &NaiveTime) -> i64 {
+    i64::from(time.num_seconds_from_midnight()) * NANOS_PER_SECOND + i64::from(time.nanosecond())
+}
+
+fn time_from_nanos(nanos: i64) -> YrsResult<NaiveTime> {
+    let seconds = (nanos / NANOS_PER_SECOND).min(86_399);
+    let nanos = nanos - seconds * NANOS_PER_SECOND;
+    u32::try_from(seconds)
+        .ok()
+        .zip(u32::try_from(nanos).ok())
+        .and_then(|(seconds, nanos)| NaiveTime::from_num_seconds_from_midnight_opt(seconds, nanos))
+        .ok_or(YrsWrapperError::BigIntOutOfRange)
+}
+
+impl ToSnapshot for YTime {
+    type Snapshot = NaiveTime;
+
+    fn to_snapshot(&self, _txn: &impl yrs::ReadTxn) -> YrsResult<Self::Snapshot> {
+        Ok(self.0)
+    }
+}
+
+impl FromSnapshot<NaiveTime> for YTimePrelim {
+    fn from_snapshot(snapshot: NaiveTime) -> Self {
+        Self(snapshot)
+    }
+}
+
+impl YrsSerialize for YTime {
+    fn yrs_serialize<S: Serializer>(
+        &self,
+        _txn: &impl yrs::ReadTxn,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl YrsDeserialize for YTimePrelim {
+    fn yrs_deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        NaiveTime::deserialize(deserializer).map(Self)
+    }
+}
+
+impl YrsDisplay for YTime {
+    fn fmt(&self, _txn: &impl yrs::ReadTxn) -> YrsResult<String> {
+        Ok(self.0.to_string())
+    }
+}
+
+impl yrs::block::Prelim for YTimePrelim {
+    type Return = YTime;
+
+    fn into_content(
+        self,
+        _txn: &mut yrs::TransactionMut,
+    ) -> (yrs::block::ItemContent, Option<Self>) {
+        // Copied from Prelim implementation for lib0::any::Any
+        let value: lib0::any::Any = time_to_nanos(&self.0).into();
+        (yrs::block::ItemContent::Any(vec![value]), None)
+    }
+
+    fn integrate(self, _txn: &mut yrs::TransactionMut, _inner_ref: yrs::types::BranchPtr) {}
+}
+
+impl TryFromYrsValue for YTime {
+    fn try_from_yrs_value(value: yrs::types::Value, _txn: &impl yrs::ReadTxn) -> YrsResult<Self> {
+        let nanos = value.unwrap_yrs_any()?.unwrap_any_bigint()?;
+        time_from_nanos(nanos).map(YTime)
+    }
+}
+
+impl TryFrom<yrs::block::BlockPtr> for YTime {
+    type Error = YrsWrapperError;
+
+    fn try_from(value: yrs::block::BlockPtr) -> Result<Self, Self::Error> {
+        let any: lib0::any::Any =
+            value
+                .try_into()
+                .map_err(|_| YrsWrapperError::FromBlockPtrError {
+                    expected: "lib0::any::Any",
+                })?;
+
+        let nanos = any.unwrap_any_bigint()?;
+        time_from_nanos(nanos).map(YTime)
+    }
+}
+
+/// A unique, stable identifier, for referring to a value from elsewhere in the document
+/// regardless of where it is stored.
+#[derive(derive_more::From, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct YIdPrelim(Uuid);
+
+impl YIdPrelim {
+    /// A fresh, random (v4) ID.
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+
+    /// So that a reference to a value can be made before it's inserted.
+    pub fn id(&self) -> Uuid {
+        self.0
+    }
+}
+
+impl Default for YIdPrelim {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(derive_more::From, Debug, derive_more::Deref, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct YId(Uuid);
+
+/// Stored in the hyphenated form, e.g., `67e55044-10b1-426f-9247-bb680e5fe0c8`.
+fn uuid_from_str(value: &str) -> YrsResult<Uuid> {
+    Uuid::try_parse(value).map_err(|_| YrsWrapperError::MalformedString {
+        expected: "UUID",
+        value: value.to_string(),
+    })
+}
+
+impl ToSnapshot for YId {
+    type Snapshot = Uuid;
+
+    fn to_snapshot(&self, _txn: &impl yrs::ReadTxn) -> YrsResult<Self::Snapshot> {
+        Ok(self.0)
+    }
+}
+
+impl FromSnapshot<Uuid> for YIdPrelim {
+    fn from_snapshot(snapshot: Uuid) -> Self {
+        Self(snapshot)
+    }
+}
+
+impl YrsSerialize for YId {
+    fn yrs_serialize<S: Serializer>(
+        &self,
+        _txn: &impl yrs::ReadTxn,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl YrsDeserialize for YIdPrelim {
+    fn yrs_deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Uuid::deserialize(deserializer).map(Self)
+    }
+}
+
+impl YrsDisplay for YId {
+    fn fmt(&self, _txn: &impl yrs::ReadTxn) -> YrsResult<String> {
+        Ok(self.0.to_string())
+    }
+}
+
+impl yrs::block::Prelim for YIdPrelim {
+    type Return = YId;
+
+    fn into_content(
+        self,
+        _txn: &mut yrs::TransactionMut,
+    ) -> (yrs::block::ItemContent, Option<Self>) {
+        // Copied from Prelim implementation for lib0::any::Any
+        let value: lib0::any::Any = self.0.to_string().into();
+        (yrs::block::ItemContent::Any(vec![value]), None)
+    }
+
+    fn integrate(self, _txn: &mut yrs::TransactionMut, _inner_ref: yrs::types::BranchPtr) {}
+}
+
+impl TryFromYrsValue for YId {
+    fn try_from_yrs_value(value: yrs::types::Value, _txn: &impl yrs::ReadTxn) -> YrsResult<Self> {
+        let value = value.unwrap_yrs_any()?.unwrap_any_string()?;
+        uuid_from_str(&value).map(YId)
+    }
+}
+
+impl TryFrom<yrs::block::BlockPtr> for YId {
+    type Error = YrsWrapperError;
+
+    fn try_from(value: yrs::block::BlockPtr) -> Result<Self, Self::Error> {
+        let any: lib0::any::Any =
+            value
+                .try_into()
+                .map_err(|_| YrsWrapperError::FromBlockPtrError {
+                    expected: "lib0::any::Any",
+                })?;
+
+        let value = any.unwrap_any_string()?;
+        uuid_from_str(&value).map(YId)
+    }
+}
+
+/// Defines a prelim and a wrapper for a plain value that is stored as a single
+/// `lib0::any::Any`, given how to convert the value to and from that `Any`.
+macro_rules! any_backed_type {
+    (
+        $(#[$meta:meta])*
+        $prelim:ident, $wrapper:ident, $inner:ty,
+        to_any: $to_any:expr,
+        from_any: $from_any:expr,
+        display: $display:expr $(,)?
+    ) => {
+        $(#[$meta])*
+        #[derive(derive_more::From, Debug, Clone, PartialEq)]
+        pub struct $prelim($inner);
+
+        #[derive(derive_more::From, Debug, derive_more::Deref, Clone, PartialEq)]
+        pub struct $wrapper($inner);
+
+        impl $wrapper {
+            fn from_any(any: lib0::any::Any) -> YrsResult<Self> {
+                let from_any: fn(lib0::any::Any) -> YrsResult<$inner> = $from_any;
+                from_any(any).map($wrapper)
+            }
+        }
+
+        impl ToSnapshot for $wrapper {
+            type Snapshot = $inner;
+
+            fn to_snapshot(&self, _txn: &impl yrs::ReadTxn) -> YrsResult<Self::Snapshot> {
+                Ok(self.0.clone())
+            }
+        }
+
+        impl FromSnapshot<$inner> for $prelim {
+            fn from_snapshot(snapshot: $inner) -> Self {
+                Self(snapshot)
+            }
+        }
+
+        impl YrsSerialize for $wrapper {
+            fn yrs_serialize<S: Serializer>(
+                &self,
+                _txn: &impl yrs::ReadTxn,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error> {
+                self.0.serialize(serializer)
+            }
+        }
+
+        impl YrsDeserialize for $prelim {
+            fn yrs_deserialize<'de, D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<Self, D::Error> {
+                <$inner>::deserialize(deserializer).map(Self)
+            }
+        }
+
+        impl YrsDisplay for $wrapper {
+            fn fmt(&self, _txn: &impl yrs::ReadTxn) -> YrsResult<String> {
+                let display: fn(&$inner) -> String = $display;
+                Ok(display(&self.0))
+            }
+        }
+
+        impl yrs::block::Prelim for $prelim {
+            type Return = $wrapper;
+
+            fn into_content(
+                self,
+                _txn: &mut yrs::TransactionMut,
+            ) -> (yrs::block::ItemContent, Option<Self>) {
+                // Copied from Prelim implementation for lib0::any::Any
+                let to_any: fn($inner) -> lib0::any::Any = $to_any;
+                (yrs::block::ItemContent::Any(vec![to_any(self.0)]), None)
+            }
+
+            fn integrate(self, _txn: &mut yrs::TransactionMut, _inner_ref: yrs::types::BranchPtr) {}
+        }
+
+        impl TryFromYrsValue for $wrapper {
+            fn try_from_yrs_value(
+                value: yrs::types::Value,
+                _txn: &impl yrs::ReadTxn,
+            ) -> YrsResult<Self> {
+                $wrapper::from_any(value.unwrap_yrs_any()?)
+            }
+        }
+
+        impl TryFrom<yrs::block::BlockPtr> for $wrapper {
+            type Error = YrsWrapperError;
+
+            fn try_from(value: yrs::block::BlockPtr) -> Result<Self, Self::Error> {
+                let any: lib0::any::Any =
+                    value
+                        .try_into()
+                        .map_err(|_| YrsWrapperError::FromBlockPtrError {
+                            expected: "lib0::any::Any",
+                        })?;
+                $wrapper::from_any(any)
+            }
+        }
+    };
+}
+
+any_backed_type!(
+    /// A plain string, which unlike `yrs::TextPrelim` is replaced as a whole when set, rather than
+    /// merged character by character. Good for things like colours or identifiers from elsewhere.
+    YStringPrelim, YString, String,
+    to_any: |value| lib0::any::Any::String(value.into()),
+    from_any: |any| any.unwrap_any_string(),
+    display: |value| value.clone(),
+);
+
+impl From<&str> for YStringPrelim {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+any_backed_type!(
+    YI64Prelim, YI64, i64,
+    to_any: lib0::any::Any::BigInt,
+    from_any: |any| any.unwrap_any_bigint(),
+    display: |value| value.to_string(),
+);
+
+any_backed_type!(
+    /// Stored as a BigInt, like `YI64`. Values that don't fit are an error when read.
+    YU32Prelim, YU32, u32,
+    to_any: |value| lib0::any::Any::BigInt(value.into()),
+    from_any: |any| {
+        u32::try_from(any.unwrap_any_bigint()?).map_err(|_| YrsWrapperError::BigIntOutOfRange)
+    },
+    display: |value| value.to_string(),
+);
+
+any_backed_type!(
+    YF64Prelim, YF64, f64,
+    to_any: lib0::any::Any::Number,
+    from_any: |any| any.unwrap_any_number(),
+    display: |value| value.to_string(),
+);
+
+any_backed_type!(
+    YBytesPrelim, YBytes, Vec<u8>,
+    to_any: |value| lib0::any::Any::Buffer(value.into()),
+    from_any: |any| any.unwrap_any_buffer(),
+    display: |value| format!("{:?}", value),
+);
+
+any_backed_type!(
+    /// Stored by its English abbreviation, like `Mon`.
+    YWeekdayPrelim, YWeekday, chrono::Weekday,
+    to_any: |value| lib0::any::Any::String(value.to_string().into()),
+    from_any: |any| {
+        let value = any.unwrap_any_string()?;
+        value
+            .parse()
+            .map_err(|_| YrsWrapperError::MalformedString { expected: "weekday", value })
+    },
+    display: |value| value.to_string(),
+);
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use yrs::{Array, Map, Transact};
+
+    use crate::snapshot::ToSnapshot;
+    use crate::test_utils::test_doc;
+    use crate::yrs_display::YrsDisplay;
+    use crate::yrs_serde::YrsSerialize;
+    use crate::yrs_struct::YrsStruct;
+    use crate::yrs_vec::YrsVecPrelim;
+    use crate::yrs_wrapper_error::{UnwrapYrsValue, YrsResult, YrsWrapperError};
+
+    use super::{
+        YBytesPrelim, YDatePrelim, YF64Prelim, YI64Prelim, YStringPrelim, YTimePrelim, YU32Prelim,
+        YZonedDateTimePrelim,
+    };
+
+    #[derive(YrsStruct)]
+    pub struct LabelPrelim {
+        pub name: YStringPrelim,
+        pub priority: YI64Prelim,
+        pub effort_points: YU32Prelim,
+        pub weight: YF64Prelim,
+        pub icon: YBytesPrelim,
+        pub aliases: YrsVecPrelim<YStringPrelim>,
+    }
+
+    #[derive(YrsStruct)]
+    pub struct ReminderPrelim {
+        pub at: YZonedDateTimePrelim,
+        pub day: YDatePrelim,
+        pub time_of_day: YTimePrelim,
+    }
+
+    #[test]
+    fn test_date_and_time_types() -> YrsResult<()> {
+        let at = chrono_tz::Europe::Berlin
+            .with_ymd_and_hms(2023, 5, 1, 10, 0, 0)
+            .unwrap()
+            + chrono::Duration::nanoseconds(123_456_789);
+        let day = chrono::NaiveDate::from_ymd_opt(2023, 5, 1).unwrap();
+        let time_of_day = chrono::NaiveTime::from_hms_nano_opt(23, 59, 59, 999_999_999).unwrap();
+
+        let (doc, map) = test_doc();
+        let mut txn = doc.try_transact_mut().unwrap();
+        let reminder = map.insert(
+            &mut txn,
+            "reminder",
+            ReminderPrelim {
+                at: at.into(),
+                day: day.into(),
+                time_of_day: time_of_day.into(),
+            },
+        );
+
+        assert_eq!(
+            reminder.to_snapshot(&txn)?,
+            ReminderSnapshot {
+                at,
+                day,
+                time_of_day
+            }
+        );
+        assert_eq!(reminder.at(&txn)?.timezone(), chrono_tz::Europe::Berlin);
+        assert_eq!(
+            serde_json::to_value(reminder.with_txn(&txn)).unwrap()["at"],
+            "2023-05-01T10:00:00.123456789+02:00[Europe/Berlin]"
+        );
+
+        // A zone that doesn't exist is an error, not a silent fallback to UTC.
+        reminder.0.insert(
+            &mut txn,
+            "at",
+            "2023-05-01T10:00:00+02:00[Mars/Olympus]".to_string(),
+        );
+        assert_eq!(
+            reminder.at(&txn).unwrap_err().root_cause(),
+            &YrsWrapperError::MalformedString {
+                expected: "zoned date time",
+                value: "2023-05-01T10:00:00+02:00[Mars/Olympus]".into(),
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_primitive_types() -> YrsResult<()> {
+        let (doc, map) = test_doc();
+        let mut txn = doc.try_transact_mut().unwrap();
+        let label = map.insert(
+            &mut txn,
+            "label",
+            LabelPrelim {
+                name: "urgent".into(),
+                priority: (-1).into(),
+                effort_points: 3.into(),
+                weight: 0.5.into(),
+                icon: vec![0xff, 0x00].into(),
+                aliases: vec!["asap".into(), "now".into()].into(),
+            },
+        );
+
+        assert_eq!(
+            label.to_snapshot(&txn)?,
+            LabelSnapshot {
+                name: "urgent".into(),
+                priority: -1,
+                effort_points: 3,
+                weight: 0.5,
+                icon: vec![0xff, 0x00],
+                aliases: vec!["asap".into(), "now".into()],
+            }
+        );
+        assert_eq!(label.aliases(&txn)?.get(&txn, 1)?.unwrap().as_str(), "now");
+        assert_eq!(label.weight(&txn)?.fmt(&txn)?, "0.5");
+
+        label.0.insert(&mut txn, "effort_points", -3i64);
+        assert_eq!(
+            label.effort_points(&txn).unwrap_err().root_cause(),
+            &YrsWrapperError::BigIntOutOfRange
+        );
+
+        label
+            .0
+            .get(&txn, "aliases")
+            .unwrap()
+            .unwrap_yrs_array()?
+            .push_back(&mut txn, 7i64);
+        let error = label.aliases(&txn)?.get(&txn, 2).unwrap_err();
+        assert_eq!(error.path().unwrap().to_string(), "[2]");
+        assert_eq!(
+            error.root_cause(),
+            &YrsWrapperError::UnexpectedAnyVariant {
+                expected: "String",
+                found: "BigInt",
+            }
+        );
+
+        Ok(())
+    }
+}
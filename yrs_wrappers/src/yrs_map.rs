@@ -0,0 +1,319 @@
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+use serde::ser::{Error as _, SerializeMap};
+use serde::{Deserialize, Deserializer, Serializer};
+use yrs::{block::Prelim, Map, ReadTxn, TransactionMut};
+
+use crate::snapshot::{FromSnapshot, ToSnapshot};
+use crate::try_from_yrs_value::TryFromYrsValue;
+use crate::yrs_display::YrsDisplay;
+use crate::yrs_serde::{Deserialized, YrsDeserialize, YrsSerialize};
+use crate::yrs_wrapper_error::{UnwrapYrsValue, YrsResult};
+
+/// A `yrs::MapRef` with arbitrary string keys, whose values are all `V`s.
+#[derive(Clone, Debug)]
+pub struct YrsMap<V> {
+    inner: yrs::MapRef,
+    phantom: std::marker::PhantomData<V>,
+}
+
+impl<V> TryFromYrsValue for YrsMap<V>
+where
+    V: TryFromYrsValue,
+{
+    fn try_from_yrs_value(value: yrs::types::Value, txn: &impl yrs::ReadTxn) -> YrsResult<Self> {
+        let map_ref = value.unwrap_yrs_map()?;
+
+        // Verify that the map contains deserializable values.
+        map_ref
+            .iter(txn)
+            .map(|(k, v)| {
+                V::try_from_yrs_value(v, txn)
+                    .map(|_| ())
+                    .map_err(|e| e.in_field(k))
+            })
+            .collect::<Result<(), _>>()?;
+
+        Ok(YrsMap {
+            inner: map_ref,
+            phantom: std::marker::PhantomData,
+        })
+    }
+
+    /// The elements are checked when they're accessed.
+    fn try_from_yrs_value_shallow(
+        value: yrs::types::Value,
+        _txn: &impl yrs::ReadTxn,
+    ) -> YrsResult<Self> {
+        Ok(YrsMap {
+            inner: value.unwrap_yrs_map()?,
+            phantom: std::marker::PhantomData,
+        })
+    }
+}
+
+impl<V> core::convert::AsMut<yrs::types::Branch> for YrsMap<V> {
+    fn as_mut(&mut self) -> &mut yrs::types::Branch {
+        self.inner.as_mut()
+    }
+}
+
+impl<V: TryFromYrsValue> TryFrom<yrs::block::BlockPtr> for YrsMap<V> {
+    type Error = <yrs::MapRef as TryFrom<yrs::block::BlockPtr>>::Error;
+
+    fn try_from(value: yrs::block::BlockPtr) -> Result<Self, Self::Error> {
+        let map_ref: yrs::MapRef = value.try_into()?;
+        // Like `YrsVec`, this doesn't check that the values are deserializable into V.
+        Ok(YrsMap {
+            inner: map_ref,
+            phantom: std::marker::PhantomData,
+        })
+    }
+}
+
+pub struct YrsMapPrelim<P>(HashMap<String, P>);
+
+impl<V, P> yrs::block::Prelim for YrsMapPrelim<P>
+where
+    P: Prelim<Return = V>,
+    V: TryFromYrsValue,
+{
+    type Return = YrsMap<V>;
+
+    /// Copied from impl Prelim for MapPrelim.
+    fn into_content(
+        self,
+        _txn: &mut yrs::TransactionMut,
+    ) -> (yrs::block::ItemContent, Option<Self>) {
+        let inner = yrs::types::Branch::new(yrs::types::TYPE_REFS_MAP, None);
+        (yrs::block::ItemContent::Type(inner), Some(self))
+    }
+
+    fn integrate(self, txn: &mut yrs::TransactionMut, inner_ref: yrs::types::BranchPtr) {
+        let map = yrs::MapRef::from(inner_ref);
+        for (key, value) in self.0 {
+            map.insert(txn, key, value);
+        }
+    }
+}
+
+impl<K, P> FromIterator<(K, P)> for YrsMapPrelim<P>
+where
+    K: Into<String>,
+{
+    fn from_iter<I: IntoIterator<Item = (K, P)>>(iter: I) -> Self {
+        YrsMapPrelim(iter.into_iter().map(|(k, p)| (k.into(), p)).collect())
+    }
+}
+
+impl<P> From<HashMap<String, P>> for YrsMapPrelim<P> {
+    fn from(value: HashMap<String, P>) -> Self {
+        YrsMapPrelim(value)
+    }
+}
+
+impl<P> Default for YrsMapPrelim<P> {
+    fn default() -> Self {
+        YrsMapPrelim(HashMap::new())
+    }
+}
+
+impl<V> YrsMap<V>
+where
+    V: TryFromYrsValue,
+{
+    pub fn iter<'a>(
+        &'a self,
+        txn: &'a impl yrs::ReadTxn,
+    ) -> impl Iterator<Item = YrsResult<(&'a str, V)>> + 'a {
+        self.inner.iter(txn).map(move |(key, value)| {
+            V::try_from_yrs_value_shallow(value, txn)
+                .map(|value| (key, value))
+                .map_err(|e| e.in_field(key))
+        })
+    }
+
+    /// Like `iter`, but ordered by key.
+    fn sorted_iter<'a>(&'a self, txn: &'a impl yrs::ReadTxn) -> YrsResult<Vec<(&'a str, V)>> {
+        let mut entries = self.iter(txn).collect::<YrsResult<Vec<_>>>()?;
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(entries)
+    }
+
+    pub fn len(&self, txn: &impl ReadTxn) -> u32 {
+        self.inner.len(txn)
+    }
+
+    pub fn is_empty(&self, txn: &impl ReadTxn) -> bool {
+        self.inner.len(txn) == 0
+    }
+
+    pub fn contains_key(&self, txn: &impl ReadTxn, key: &str) -> bool {
+        self.inner.contains_key(txn, key)
+    }
+
+    pub fn get(&self, txn: &impl ReadTxn, key: &str) -> YrsResult<Option<V>> {
+        self.inner
+            .get(txn, key)
+            .map(|value| V::try_from_yrs_value_shallow(value, txn).map_err(|e| e.in_field(key)))
+            .transpose()
+    }
+
+    pub fn insert<P: Prelim<Return = V>>(
+        &self,
+        txn: &mut TransactionMut,
+        key: impl Into<Rc<str>>,
+        value: P,
+    ) -> P::Return {
+        self.inner.insert(txn, key, value)
+    }
+
+    /// Returns whether there was a value under `key`.
+    pub fn remove(&self, txn: &mut TransactionMut, key: &str) -> bool {
+        self.inner.remove(txn, key).is_some()
+    }
+}
+
+impl<V> YrsDisplay for YrsMap<V>
+where
+    V: YrsDisplay + TryFromYrsValue,
+{
+    fn fmt(&self, txn: &impl ReadTxn) -> YrsResult<String> {
+        let mut result = String::new();
+        result.push('{');
+        for (i, (key, value)) in self.sorted_iter(txn)?.into_iter().enumerate() {
+            if i > 0 {
+                result.push_str(", ");
+            }
+            result.push_str(key);
+            result.push_str(": ");
+            result.push_str(&value.fmt(txn)?);
+        }
+        result.push('}');
+        Ok(result)
+    }
+}
+
+impl<V> ToSnapshot for YrsMap<V>
+where
+    V: ToSnapshot + TryFromYrsValue,
+{
+    type Snapshot = BTreeMap<String, V::Snapshot>;
+
+    fn to_snapshot(&self, txn: &impl ReadTxn) -> YrsResult<Self::Snapshot> {
+        self.iter(txn)
+            .map(|entry| {
+                let (key, value) = entry?;
+                Ok((key.to_string(), value.to_snapshot(txn)?))
+            })
+            .collect()
+    }
+}
+
+impl<S, P: FromSnapshot<S>> FromSnapshot<BTreeMap<String, S>> for YrsMapPrelim<P> {
+    fn from_snapshot(snapshot: BTreeMap<String, S>) -> Self {
+        snapshot
+            .into_iter()
+            .map(|(key, value)| (key, P::from_snapshot(value)))
+            .collect()
+    }
+}
+
+impl<V> YrsSerialize for YrsMap<V>
+where
+    V: YrsSerialize + TryFromYrsValue,
+{
+    fn yrs_serialize<S: Serializer>(
+        &self,
+        txn: &impl ReadTxn,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let entries = self.sorted_iter(txn).map_err(S::Error::custom)?;
+        let mut map = serializer.serialize_map(Some(entries.len()))?;
+        for (key, value) in entries {
+            map.serialize_entry(key, &value.with_txn(txn))?;
+        }
+        map.end()
+    }
+}
+
+impl<P: YrsDeserialize> YrsDeserialize for YrsMapPrelim<P> {
+    fn yrs_deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let entries = HashMap::<String, Deserialized<P>>::deserialize(deserializer)?;
+        Ok(entries
+            .into_iter()
+            .map(|(key, value)| (key, value.0))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use yrs::{Map, Transact};
+
+    use crate::test_utils::test_doc;
+    use crate::try_from_yrs_value::TryFromYrsValue;
+    use crate::yrs_basic_types::{YBoolPrelim, YDurationPrelim};
+    use crate::yrs_display::YrsDisplay;
+    use crate::yrs_struct::YrsStruct;
+    use crate::yrs_wrapper_error::{UnwrapYrsValue, YrsResult};
+
+    use super::YrsMapPrelim;
+
+    #[derive(YrsStruct)]
+    pub struct SettingsPrelim {
+        pub flags: YrsMapPrelim<YBoolPrelim>,
+        pub reminders: Option<YrsMapPrelim<YDurationPrelim>>,
+    }
+
+    #[test]
+    fn test_yrs_map() -> YrsResult<()> {
+        let (doc, map) = test_doc();
+        let mut txn = doc.try_transact_mut().unwrap();
+
+        let settings = map.insert(
+            &mut txn,
+            "settings",
+            SettingsPrelim {
+                flags: [("dark_mode", true.into()), ("compact", false.into())]
+                    .into_iter()
+                    .collect(),
+                reminders: None,
+            },
+        );
+
+        let flags = settings.flags(&txn)?;
+        assert_eq!(flags.len(&txn), 2);
+        assert!(flags.contains_key(&txn, "dark_mode"));
+        assert!(*flags.get(&txn, "dark_mode")?.unwrap());
+        assert!(flags.get(&txn, "missing")?.is_none());
+
+        flags.insert(&mut txn, "week_starts_on_monday", YBoolPrelim::from(true));
+        assert!(flags.remove(&mut txn, "compact"));
+        assert!(!flags.remove(&mut txn, "compact"));
+        assert_eq!(
+            flags.fmt(&txn)?,
+            "{dark_mode: true, week_starts_on_monday: true}"
+        );
+
+        let mut keys = flags
+            .iter(&txn)
+            .map(|entry| entry.map(|(key, _)| key.to_string()))
+            .collect::<YrsResult<Vec<_>>>()?;
+        keys.sort();
+        assert_eq!(keys, vec!["dark_mode", "week_starts_on_monday"]);
+
+        // A value of the wrong type makes the whole map invalid.
+        map.get(&txn, "settings")
+            .unwrap()
+            .unwrap_yrs_map()?
+            .get(&txn, "flags")
+            .unwrap()
+            .unwrap_yrs_map()?
+            .insert(&mut txn, "bogus", "not a bool".to_string());
+        assert!(Settings::try_from_yrs_value(map.get(&txn, "settings").unwrap(), &txn).is_err());
+
+        Ok(())
+    }
+}
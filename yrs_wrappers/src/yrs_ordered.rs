@@ -0,0 +1,709 @@
+//! A list that can be reordered concurrently.
+//!
+//! Moving an item in a `YrsVec` means removing it and inserting it again, so two replicas moving
+//! the same item at the same time end up with two copies of it. A `YrsOrdered` instead stores its
+//! items in a `yrs::MapRef`, each under a random key and along with a position from
+//! `fractional_index`, and moving an item only overwrites its position. Concurrent moves of the
+//! same item resolve to one of them, and moving an item that is concurrently removed leaves it
+//! removed.
+//!
+//! Items are ordered by position, and items that were concurrently put at the same spot (and so
+//! got the same position) by key.
+//!
+//! Lists that were `YrsVec`s before there were `YrsOrdered`s can be reordered the same way
+//! without changing their type, as long as their items store their own positions: see
+//! `Positioned`. Items that don't have one yet come after the ones that do, in the order of the
+//! array.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use serde::ser::{Error as _, SerializeSeq};
+use serde::{Deserialize, Deserializer, Serializer};
+use uuid::Uuid;
+use yrs::{block::Prelim, Map, MapPrelim, MapRef, ReadTxn, TransactionMut};
+
+use crate::fractional_index::{is_valid_key, key_between};
+use crate::snapshot::{FromSnapshot, ToSnapshot};
+use crate::try_from_yrs_value::TryFromYrsValue;
+use crate::yrs_display::YrsDisplay;
+use crate::yrs_serde::{Deserialized, YrsDeserialize, YrsSerialize};
+use crate::yrs_vec::YrsVec;
+use crate::yrs_wrapper_error::{UnwrapAny, UnwrapYrsValue, YrsResult, YrsWrapperError};
+
+const POSITION_KEY: &str = "position";
+const VALUE_KEY: &str = "value";
+
+#[derive(Clone, Debug)]
+pub struct YrsOrdered<T> {
+    inner: MapRef,
+    phantom: std::marker::PhantomData<T>,
+}
+
+/// An item of a `YrsOrdered`, as stored in the document.
+struct Entry {
+    key: Uuid,
+    position: String,
+    map: MapRef,
+}
+
+impl Entry {
+    fn read(key: &str, value: yrs::types::Value, txn: &impl ReadTxn) -> YrsResult<Self> {
+        Self::read_unprefixed(key, value, txn).map_err(|e| e.in_field(key))
+    }
+
+    fn read_unprefixed(key: &str, value: yrs::types::Value, txn: &impl ReadTxn) -> YrsResult<Self> {
+        let key = Uuid::try_parse(key).map_err(|_| YrsWrapperError::MalformedString {
+            expected: "UUID",
+            value: key.to_string(),
+        })?;
+        let map = value.unwrap_yrs_map()?;
+        let position = map
+            .get(txn, POSITION_KEY)
+            .ok_or_else(|| YrsWrapperError::YMapMissingAttr {
+                attr: POSITION_KEY.to_string(),
+            })?
+            .unwrap_yrs_any()
+            .and_then(|any| any.unwrap_any_string())
+            .map_err(|e| e.in_field(POSITION_KEY))?;
+        if !is_valid_key(&position) {
+            return Err(YrsWrapperError::MalformedString {
+                expected: "fractional index",
+                value: position,
+            }
+            .in_field(POSITION_KEY));
+        }
+        Ok(Entry { key, position, map })
+    }
+
+    fn value<T: TryFromYrsValue>(&self, txn: &impl ReadTxn) -> YrsResult<T> {
+        let value =
+            self.map
+                .get(txn, VALUE_KEY)
+                .ok_or_else(|| YrsWrapperError::YMapMissingAttr {
+                    attr: VALUE_KEY.to_string(),
+                })?;
+        T::try_from_yrs_value_shallow(value, txn).map_err(|e| e.in_field(VALUE_KEY))
+    }
+}
+
+fn insert_entry<P: Prelim>(
+    map: &MapRef,
+    txn: &mut TransactionMut,
+    position: String,
+    value: P,
+) -> (Uuid, P::Return) {
+    let key = Uuid::new_v4();
+    let entry = map.insert(
+        txn,
+        key.to_string(),
+        MapPrelim::<lib0::any::Any>::from(HashMap::new()),
+    );
+    entry.insert(txn, POSITION_KEY, position);
+    (key, entry.insert(txn, VALUE_KEY, value))
+}
+
+impl<T> TryFromYrsValue for YrsOrdered<T>
+where
+    T: TryFromYrsValue,
+{
+    fn try_from_yrs_value(value: yrs::types::Value, txn: &impl yrs::ReadTxn) -> YrsResult<Self> {
+        let ordered = Self::try_from_yrs_value_shallow(value, txn)?;
+
+        // Verify that the entries are well-formed and contain deserializable values.
+        for entry in ordered.entries(txn)? {
+            let value = entry.map.get(txn, VALUE_KEY).ok_or_else(|| {
+                YrsWrapperError::YMapMissingAttr {
+                    attr: VALUE_KEY.to_string(),
+                }
+                .in_field(entry.key.to_string())
+            })?;
+            T::try_from_yrs_value(value, txn)
+                .map_err(|e| e.in_field(VALUE_KEY).in_field(entry.key.to_string()))?;
+        }
+
+        Ok(ordered)
+    }
+
+    /// The entries are checked when they're accessed.
+    fn try_from_yrs_value_shallow(
+        value: yrs::types::Value,
+        _txn: &impl yrs::ReadTxn,
+    ) -> YrsResult<Self> {
+        Ok(YrsOrdered {
+            inner: value.unwrap_yrs_map()?,
+            phantom: std::marker::PhantomData,
+        })
+    }
+}
+
+impl<T> core::convert::AsMut<yrs::types::Branch> for YrsOrdered<T> {
+    fn as_mut(&mut self) -> &mut yrs::types::Branch {
+        self.inner.as_mut()
+    }
+}
+
+impl<T: TryFromYrsValue> TryFrom<yrs::block::BlockPtr> for YrsOrdered<T> {
+    type Error = <MapRef as TryFrom<yrs::block::BlockPtr>>::Error;
+
+    fn try_from(value: yrs::block::BlockPtr) -> Result<Self, Self::Error> {
+        let map_ref: MapRef = value.try_into()?;
+        // Like `YrsVec`, this doesn't check that the values are deserializable into T.
+        Ok(YrsOrdered {
+            inner: map_ref,
+            phantom: std::marker::PhantomData,
+        })
+    }
+}
+
+pub struct YrsOrderedPrelim<P>(Vec<P>);
+
+impl<T, P> yrs::block::Prelim for YrsOrderedPrelim<P>
+where
+    P: Prelim<Return = T>,
+    T: TryFromYrsValue,
+{
+    type Return = YrsOrdered<T>;
+
+    /// Copied from impl Prelim for MapPrelim.
+    fn into_content(
+        self,
+        _txn: &mut yrs::TransactionMut,
+    ) -> (yrs::block::ItemContent, Option<Self>) {
+        let inner = yrs::types::Branch::new(yrs::types::TYPE_REFS_MAP, None);
+        (yrs::block::ItemContent::Type(inner), Some(self))
+    }
+
+    fn integrate(self, txn: &mut yrs::TransactionMut, inner_ref: yrs::types::BranchPtr) {
+        let map = MapRef::from(inner_ref);
+        let mut position = None;
+        for value in self.0 {
+            let next = key_between(position.as_deref(), None);
+            insert_entry(&map, txn, next.clone(), value);
+            position = Some(next);
+        }
+    }
+}
+
+impl<P> FromIterator<P> for YrsOrderedPrelim<P> {
+    fn from_iter<I: IntoIterator<Item = P>>(iter: I) -> Self {
+        YrsOrderedPrelim(iter.into_iter().collect())
+    }
+}
+
+impl<P> From<Vec<P>> for YrsOrderedPrelim<P> {
+    fn from(value: Vec<P>) -> Self {
+        YrsOrderedPrelim(value)
+    }
+}
+
+impl<T> YrsOrdered<T>
+where
+    T: TryFromYrsValue,
+{
+    /// All entries, in order.
+    fn entries(&self, txn: &impl ReadTxn) -> YrsResult<Vec<Entry>> {
+        let mut entries = self
+            .inner
+            .iter(txn)
+            .map(|(key, value)| Entry::read(key, value, txn))
+            .collect::<YrsResult<Vec<_>>>()?;
+        entries.sort_by(|a, b| (&a.position, a.key).cmp(&(&b.position, b.key)));
+        Ok(entries)
+    }
+
+    /// The items in order, along with their keys, which identify them in `move_between`, etc.
+    pub fn iter<'a>(
+        &'a self,
+        txn: &'a impl ReadTxn,
+    ) -> impl Iterator<Item = YrsResult<(Uuid, T)>> + 'a {
+        let (entries, error) = match self.entries(txn) {
+            Ok(entries) => (entries, None),
+            Err(e) => (vec![], Some(Err(e))),
+        };
+        error
+            .into_iter()
+            .chain(entries.into_iter().map(move |entry| {
+                entry
+                    .value(txn)
+                    .map(|value| (entry.key, value))
+                    .map_err(|e| e.in_field(entry.key.to_string()))
+            }))
+    }
+
+    pub fn keys(&self, txn: &impl ReadTxn) -> YrsResult<Vec<Uuid>> {
+        Ok(self.entries(txn)?.into_iter().map(|e| e.key).collect())
+    }
+
+    pub fn len(&self, txn: &impl ReadTxn) -> u32 {
+        self.inner.len(txn)
+    }
+
+    pub fn is_empty(&self, txn: &impl ReadTxn) -> bool {
+        self.inner.len(txn) == 0
+    }
+
+    pub fn get(&self, txn: &impl ReadTxn, key: Uuid) -> YrsResult<Option<T>> {
+        let key = key.to_string();
+        self.inner
+            .get(txn, &key)
+            .map(|value| {
+                Entry::read(&key, value, txn)?
+                    .value(txn)
+                    .map_err(|e| e.in_field(key.as_str()))
+            })
+            .transpose()
+    }
+
+    /// The item at `index` in order. Prefer keys to identify items: concurrent insertions change
+    /// the indices of the items after them.
+    pub fn nth(&self, txn: &impl ReadTxn, index: usize) -> YrsResult<Option<T>> {
+        self.iter(txn)
+            .nth(index)
+            .map(|item| item.map(|(_, value)| value))
+            .transpose()
+    }
+
+    /// Appends `value`, and returns the key of the new item.
+    pub fn push<P: Prelim<Return = T>>(
+        &self,
+        txn: &mut TransactionMut,
+        value: P,
+    ) -> YrsResult<(Uuid, P::Return)> {
+        let last = self.entries(txn)?.pop().map(|e| e.position);
+        let position = key_between(last.as_deref(), None);
+        Ok(insert_entry(&self.inner, txn, position, value))
+    }
+
+    /// Inserts `value` right after the item with key `after` and right before the one with key
+    /// `before`. `None` stands for the start and the end of the list, respectively.
+    pub fn insert_between<P: Prelim<Return = T>>(
+        &self,
+        txn: &mut TransactionMut,
+        after: Option<Uuid>,
+        before: Option<Uuid>,
+        value: P,
+    ) -> YrsResult<(Uuid, P::Return)> {
+        let position = self.position_between(txn, None, after, before)?;
+        Ok(insert_entry(&self.inner, txn, position, value))
+    }
+
+    /// Moves the item with key `key` between the items with keys `after` and `before`, like
+    /// `insert_between`.
+    pub fn move_between(
+        &self,
+        txn: &mut TransactionMut,
+        key: Uuid,
+        after: Option<Uuid>,
+        before: Option<Uuid>,
+    ) -> YrsResult<()> {
+        let entry = self
+            .inner
+            .get(txn, &key.to_string())
+            .ok_or(YrsWrapperError::DanglingRef { id: key })?
+            .unwrap_yrs_map()?;
+        let position = self.position_between(txn, Some(key), after, before)?;
+        entry.insert(txn, POSITION_KEY, position);
+        Ok(())
+    }
+
+    /// A position between the items `after` and `before`, ignoring the item `moved`.
+    fn position_between(
+        &self,
+        txn: &mut TransactionMut,
+        moved: Option<Uuid>,
+        after: Option<Uuid>,
+        before: Option<Uuid>,
+    ) -> YrsResult<String> {
+        let entries = self
+            .entries(txn)?
+            .into_iter()
+            .filter(|e| Some(e.key) != moved)
+            .collect::<Vec<_>>();
+        let index_of = |key: Uuid| {
+            entries
+                .iter()
+                .position(|e| e.key == key)
+                .ok_or(YrsWrapperError::DanglingRef { id: key })
+        };
+        let after_index = after.map(index_of).transpose()?;
+        let before_index = before.map(index_of).transpose()?;
+        if let (Some(after), Some(before), Some(a), Some(b)) =
+            (after, before, after_index, before_index)
+        {
+            if a >= b {
+                return Err(YrsWrapperError::NotInOrder { after, before });
+            }
+        }
+
+        let positions = entries
+            .iter()
+            .map(|e| e.position.clone())
+            .collect::<Vec<_>>();
+        Ok(place_between(
+            &positions,
+            after_index,
+            before_index,
+            |index, position| {
+                entries[index].map.insert(txn, POSITION_KEY, position);
+            },
+        ))
+    }
+}
+
+/// A position between `positions[after]` and `positions[before]`, where `None` stands for the
+/// start and the end of the list, respectively. `positions` are the ones of the items of a list,
+/// in order, and `after` must come before `before`.
+///
+/// Items that were concurrently put at the same spot share a position, so there may be none
+/// between the ones of `after` and `before`. Room is made then by giving the items that share the
+/// position of `after` and come after it new positions (through `reposition`, which is called with
+/// their indices), keeping them in order.
+fn place_between(
+    positions: &[String],
+    after: Option<usize>,
+    before: Option<usize>,
+    mut reposition: impl FnMut(usize, String),
+) -> String {
+    let lower = after.map(|i| positions[i].clone());
+    let mut upper = before.map(|i| positions[i].clone());
+
+    if let (Some(after), Some(l), Some(u)) = (after, &lower, &upper) {
+        if l == u {
+            let run = positions[after + 1..]
+                .iter()
+                .take_while(|position| *position == l)
+                .count();
+            let next = positions.get(after + 1 + run).cloned();
+            let mut position = l.clone();
+            for index in after + 1..after + 1 + run {
+                position = key_between(Some(&position), next.as_deref());
+                reposition(index, position.clone());
+                if Some(index) == before {
+                    upper = Some(position.clone());
+                }
+            }
+        }
+    }
+
+    key_between(lower.as_deref(), upper.as_deref())
+}
+
+/// The order of the positions of two items of a `YrsVec` of `Positioned` items: the items without
+/// one come after the ones with one.
+pub fn compare_positions(a: Option<&str>, b: Option<&str>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.cmp(b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// An item of a `YrsVec` that stores its own position from `fractional_index`, so that the list
+/// can be reordered with `YrsVec::move_in_order` without moving the item in the array.
+pub trait Positioned {
+    /// `None` if the item has never been given a position.
+    fn position_in_list(&self, txn: &impl ReadTxn) -> YrsResult<Option<String>>;
+
+    fn set_position_in_list(&self, txn: &mut TransactionMut, position: String);
+}
+
+impl<T> YrsVec<T>
+where
+    T: TryFromYrsValue + Positioned,
+{
+    /// The elements in the order the user put them in, along with their indices in the array and
+    /// their positions.
+    fn positioned(&self, txn: &impl ReadTxn) -> YrsResult<Vec<(u32, T, Option<String>)>> {
+        let mut items = self
+            .iter(txn)
+            .enumerate()
+            .map(|(index, item)| {
+                let item = item?;
+                let position = item
+                    .position_in_list(txn)
+                    .map_err(|e| e.at_index(index as u32))?;
+                if let Some(position) = position.as_ref().filter(|p| !is_valid_key(p)) {
+                    return Err(YrsWrapperError::MalformedString {
+                        expected: "fractional index",
+                        value: position.clone(),
+                    }
+                    .at_index(index as u32));
+                }
+                Ok((index as u32, item, position))
+            })
+            .collect::<YrsResult<Vec<_>>>()?;
+        // Stable, so that the elements without a position stay in the order of the array.
+        items.sort_by(|(_, _, a), (_, _, b)| compare_positions(a.as_deref(), b.as_deref()));
+        Ok(items)
+    }
+
+    /// The elements in the order the user put them in, which isn't the order of the array.
+    pub fn in_order(&self, txn: &impl ReadTxn) -> YrsResult<Vec<T>> {
+        Ok(self
+            .positioned(txn)?
+            .into_iter()
+            .map(|(_, item, _)| item)
+            .collect())
+    }
+
+    /// The indices of the elements in the array, in the order of `in_order`.
+    pub fn order(&self, txn: &impl ReadTxn) -> YrsResult<Vec<u32>> {
+        Ok(self
+            .positioned(txn)?
+            .into_iter()
+            .map(|(index, _, _)| index)
+            .collect())
+    }
+
+    /// Like `move_to`, but with indices in `in_order`. Only positions are written, so that
+    /// concurrent moves of the same element resolve to one of them, instead of duplicating it.
+    ///
+    /// The elements that don't have a position yet are given one first, where they already are.
+    pub fn move_in_order(
+        &self,
+        txn: &mut TransactionMut,
+        source: u32,
+        target: u32,
+    ) -> YrsResult<()> {
+        let mut items = self.positioned(&*txn)?;
+        let len = items.len() as u32;
+        if source >= len {
+            return Err(YrsWrapperError::IndexOutOfBounds { index: source, len });
+        }
+        if target > len {
+            return Err(YrsWrapperError::IndexOutOfBounds { index: target, len });
+        }
+        if target == source || target == source + 1 {
+            return Ok(());
+        }
+
+        let mut last = None::<String>;
+        for (_, item, position) in items.iter_mut() {
+            if position.is_none() {
+                let next = key_between(last.as_deref(), None);
+                item.set_position_in_list(txn, next.clone());
+                *position = Some(next);
+            }
+            last = position.clone();
+        }
+
+        let (_, moved, _) = items.remove(source as usize);
+        // The elements after `source` moved down by one.
+        let target = if target > source { target - 1 } else { target };
+        let target = target as usize;
+        let positions = items
+            .iter()
+            .map(|(_, _, position)| position.clone().unwrap_or_default())
+            .collect::<Vec<_>>();
+        let position = place_between(
+            &positions,
+            target.checked_sub(1),
+            (target < items.len()).then_some(target),
+            |index, position| items[index].1.set_position_in_list(txn, position),
+        );
+        moved.set_position_in_list(txn, position);
+        Ok(())
+    }
+}
+
+/// Like the ones of `YrsVec`, the methods that remove items return snapshots of them.
+impl<T> YrsOrdered<T>
+where
+    T: TryFromYrsValue + ToSnapshot,
+{
+    /// Returns `None` if there was no item with key `key`.
+    pub fn remove(&self, txn: &mut TransactionMut, key: Uuid) -> YrsResult<Option<T::Snapshot>> {
+        let removed = self
+            .get(txn, key)?
+            .map(|value| value.to_snapshot(txn))
+            .transpose()?;
+        self.inner.remove(txn, &key.to_string());
+        Ok(removed)
+    }
+}
+
+impl<T> YrsDisplay for YrsOrdered<T>
+where
+    T: YrsDisplay + TryFromYrsValue,
+{
+    fn fmt(&self, txn: &impl ReadTxn) -> YrsResult<String> {
+        let mut result = String::new();
+        result.push('[');
+        for (i, item) in self.iter(txn).enumerate() {
+            if i > 0 {
+                result.push_str(", ");
+            }
+            result.push_str(&item?.1.fmt(txn)?);
+        }
+        result.push(']');
+        Ok(result)
+    }
+}
+
+impl<T> ToSnapshot for YrsOrdered<T>
+where
+    T: ToSnapshot + TryFromYrsValue,
+{
+    type Snapshot = Vec<T::Snapshot>;
+
+    /// The keys aren't part of the snapshot, so restoring it gives the items new keys.
+    fn to_snapshot(&self, txn: &impl ReadTxn) -> YrsResult<Self::Snapshot> {
+        self.iter(txn)
+            .map(|item| item?.1.to_snapshot(txn))
+            .collect()
+    }
+}
+
+impl<S, P: FromSnapshot<S>> FromSnapshot<Vec<S>> for YrsOrderedPrelim<P> {
+    fn from_snapshot(snapshot: Vec<S>) -> Self {
+        snapshot.into_iter().map(P::from_snapshot).collect()
+    }
+}
+
+impl<T> YrsSerialize for YrsOrdered<T>
+where
+    T: YrsSerialize + TryFromYrsValue,
+{
+    fn yrs_serialize<S: Serializer>(
+        &self,
+        txn: &impl ReadTxn,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.len(txn) as usize))?;
+        for item in self.iter(txn) {
+            let (_, value) = item.map_err(S::Error::custom)?;
+            seq.serialize_element(&value.with_txn(txn))?;
+        }
+        seq.end()
+    }
+}
+
+impl<P: YrsDeserialize> YrsDeserialize for YrsOrderedPrelim<P> {
+    fn yrs_deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let values = Vec::<Deserialized<P>>::deserialize(deserializer)?;
+        Ok(values.into_iter().map(|value| value.0).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use yrs::updates::decoder::Decode;
+    use yrs::{Doc, Map, ReadTxn, StateVector, Transact, Update};
+
+    use crate::try_from_yrs_value::TryFromYrsValue;
+    use crate::yrs_basic_types::YStringPrelim;
+    use crate::yrs_struct::YrsStruct;
+    use crate::yrs_wrapper_error::{YrsResult, YrsWrapperError};
+
+    use super::YrsOrderedPrelim;
+
+    #[derive(YrsStruct)]
+    pub struct BoardPrelim {
+        pub cards: YrsOrderedPrelim<YStringPrelim>,
+    }
+
+    #[test]
+    fn test_yrs_ordered() -> YrsResult<()> {
+        let sync = |from: &Doc, to: &Doc| {
+            let update = from
+                .transact()
+                .encode_state_as_update_v1(&to.transact().state_vector());
+            to.transact_mut()
+                .apply_update(Update::decode_v1(&update).unwrap());
+        };
+        let names = |doc: &Doc, board: &Board| -> YrsResult<Vec<String>> {
+            let txn = doc.transact();
+            board
+                .cards(&txn)?
+                .iter(&txn)
+                .map(|card| card.map(|(_, name)| name.to_string()))
+                .collect()
+        };
+
+        // Two replicas, with fixed client IDs so that ties between them always break the same way.
+        let doc_a = Doc::with_client_id(1);
+        let board_a = doc_a.get_or_insert_map("state").insert(
+            &mut doc_a.transact_mut(),
+            "board",
+            BoardPrelim {
+                cards: vec!["a".into(), "b".into(), "c".into()].into(),
+            },
+        );
+        let doc_b = Doc::with_client_id(2);
+        let initial = doc_a
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        doc_b
+            .transact_mut()
+            .apply_update(Update::decode_v1(&initial).unwrap());
+        let board_b = Board::try_from_yrs_value(
+            doc_b
+                .get_or_insert_map("state")
+                .get(&doc_b.transact(), "board")
+                .unwrap(),
+            &doc_b.transact(),
+        )?;
+        let keys = board_a.cards(&doc_a.transact())?.keys(&doc_a.transact())?;
+        let (a, b, c) = (keys[0], keys[1], keys[2]);
+
+        // Both replicas move "a" at the same time, to different spots.
+        let cards_a = board_a.cards(&doc_a.transact())?;
+        cards_a.move_between(&mut doc_a.transact_mut(), a, Some(b), Some(c))?;
+        assert_eq!(names(&doc_a, &board_a)?, vec!["b", "a", "c"]);
+        let cards_b = board_b.cards(&doc_b.transact())?;
+        cards_b.move_between(&mut doc_b.transact_mut(), a, Some(c), None)?;
+        assert_eq!(names(&doc_b, &board_b)?, vec!["b", "c", "a"]);
+
+        sync(&doc_a, &doc_b);
+        sync(&doc_b, &doc_a);
+        let merged = names(&doc_a, &board_a)?;
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged, names(&doc_b, &board_b)?);
+
+        // Both replicas insert at the same spot, and then something goes between the two.
+        cards_a.insert_between(&mut doc_a.transact_mut(), None, Some(b), "x".into())?;
+        cards_b.insert_between(&mut doc_b.transact_mut(), None, Some(b), "y".into())?;
+        sync(&doc_a, &doc_b);
+        sync(&doc_b, &doc_a);
+        let keys = cards_a.keys(&doc_a.transact())?;
+        let (first, second) = (keys[0], keys[1]);
+        cards_a.insert_between(
+            &mut doc_a.transact_mut(),
+            Some(first),
+            Some(second),
+            "z".into(),
+        )?;
+        let names_a = names(&doc_a, &board_a)?;
+        assert_eq!(names_a[1], "z");
+        assert_eq!(names_a.len(), 6);
+        sync(&doc_a, &doc_b);
+        assert_eq!(names(&doc_b, &board_b)?, names_a);
+
+        assert_eq!(
+            cards_a.move_between(&mut doc_a.transact_mut(), c, Some(second), Some(first)),
+            Err(YrsWrapperError::NotInOrder {
+                after: second,
+                before: first,
+            })
+        );
+        assert_eq!(
+            cards_a
+                .nth(&doc_a.transact(), 1)?
+                .map(|name| name.to_string()),
+            Some("z".to_string())
+        );
+        assert_eq!(
+            cards_a.remove(&mut doc_a.transact_mut(), c)?,
+            Some("c".to_string())
+        );
+        assert_eq!(cards_a.remove(&mut doc_a.transact_mut(), c)?, None);
+        assert_eq!(
+            cards_a.move_between(&mut doc_a.transact_mut(), c, None, None),
+            Err(YrsWrapperError::DanglingRef { id: c })
+        );
+
+        Ok(())
+    }
+}
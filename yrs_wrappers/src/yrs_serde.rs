@@ -0,0 +1,72 @@
+//! serde support.
+//!
+//! Wrappers can only be read with a transaction, so they implement `YrsSerialize` instead of
+//! `serde::Serialize`. `YrsSerialize::with_txn` pairs a wrapper with a transaction, which gives a
+//! `serde::Serialize`.
+//!
+//! The other direction produces prelims, which can then be inserted into a document. Prelims
+//! implement `YrsDeserialize` rather than `serde::Deserialize`, so that foreign prelims like
+//! `yrs::TextPrelim` can take part too. `Deserialized` turns a `YrsDeserialize` into a
+//! `serde::Deserialize`.
+//!
+//! Durations are (de)serialized as milliseconds, which is how they are stored in the document.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use yrs::{GetString, ReadTxn};
+
+pub trait YrsSerialize {
+    fn yrs_serialize<S: Serializer>(
+        &self,
+        txn: &impl ReadTxn,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>;
+
+    fn with_txn<'a, Txn: ReadTxn>(&'a self, txn: &'a Txn) -> WithTxn<'a, Self, Txn> {
+        WithTxn { value: self, txn }
+    }
+}
+
+/// A wrapper, together with the transaction to read it with.
+pub struct WithTxn<'a, T: ?Sized, Txn> {
+    pub value: &'a T,
+    pub txn: &'a Txn,
+}
+
+impl<'a, T, Txn> Serialize for WithTxn<'a, T, Txn>
+where
+    T: YrsSerialize + ?Sized,
+    Txn: ReadTxn,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.value.yrs_serialize(self.txn, serializer)
+    }
+}
+
+pub trait YrsDeserialize: Sized {
+    fn yrs_deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>;
+}
+
+/// A prelim that was deserialized with `YrsDeserialize`.
+pub struct Deserialized<P>(pub P);
+
+impl<'de, P: YrsDeserialize> Deserialize<'de> for Deserialized<P> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        P::yrs_deserialize(deserializer).map(Deserialized)
+    }
+}
+
+impl YrsSerialize for yrs::TextRef {
+    fn yrs_serialize<S: Serializer>(
+        &self,
+        txn: &impl ReadTxn,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.get_string(txn))
+    }
+}
+
+impl YrsDeserialize for yrs::TextPrelim<String> {
+    fn yrs_deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(yrs::TextPrelim::new)
+    }
+}
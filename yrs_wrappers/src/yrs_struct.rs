@@ -1 +1,210 @@
 pub use yrs_struct_derive::YrsStruct;
+
+use crate::yrs_wrapper_error::YrsResult;
+
+/// The key under which enums deriving `YrsStruct` store the name of their variant.
+pub const VARIANT_KEY: &str = "$variant";
+
+/// Implemented by the structs deriving `YrsStruct`, for code that observes them without knowing
+/// which one it is.
+pub trait ObserveTyped {
+    /// The generated enum of the changes to the fields of the struct, like `TodoChange`.
+    type Change;
+
+    /// Like `DeepObservable::observe_deep`, but with typed changes instead of raw events.
+    fn observe_typed<F>(&mut self, f: F) -> yrs::types::DeepEventsSubscription
+    where
+        F: Fn(&yrs::TransactionMut, YrsResult<Vec<Self::Change>>) + 'static;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Deref;
+
+    use yrs::{GetString, Map, TextPrelim, Transact};
+
+    use crate::snapshot::ToSnapshot;
+    use crate::test_utils::test_doc;
+    use crate::try_from_yrs_value::TryFromYrsValue;
+    use crate::yrs_basic_types::{YBoolPrelim, YDateTimePrelim, YDurationPrelim};
+    use crate::yrs_serde::YrsSerialize;
+    use crate::yrs_wrapper_error::{UnwrapYrsValue, YrsResult, YrsWrapperError};
+
+    use super::{YrsStruct, VARIANT_KEY};
+
+    #[derive(YrsStruct)]
+    pub struct NoteV1Prelim {
+        pub body: TextPrelim<String>,
+    }
+
+    #[derive(YrsStruct)]
+    pub struct NoteV2Prelim {
+        #[yrs(rename = "body")]
+        pub text: TextPrelim<String>,
+        #[yrs(default = true.into())]
+        pub pinned: YBoolPrelim,
+        #[yrs(skip)]
+        pub unsaved_edits: Vec<String>,
+    }
+
+    #[derive(YrsStruct)]
+    pub enum EntryKindPrelim {
+        Todo,
+        Execution(YDateTimePrelim, Option<YDateTimePrelim>),
+        Estimate {
+            duration: YDurationPrelim,
+            confident: YBoolPrelim,
+        },
+    }
+
+    fn start() -> chrono::NaiveDateTime {
+        chrono::NaiveDate::from_ymd_opt(2023, 5, 1)
+            .unwrap()
+            .and_hms_opt(8, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_field_attributes() -> YrsResult<()> {
+        let (doc, map) = test_doc();
+        let mut txn = doc.try_transact_mut().unwrap();
+
+        map.insert(
+            &mut txn,
+            "v1",
+            NoteV1Prelim {
+                body: TextPrelim::new("yo".into()),
+            },
+        );
+
+        // A document written before `pinned` existed can still be read.
+        let note = NoteV2::try_from_yrs_value(map.get(&txn, "v1").unwrap(), &txn)?;
+        assert_eq!(note.text(&txn)?.get_string(&txn), "yo");
+        assert_eq!(note.pinned(&txn)?.deref(), &true);
+
+        let note = map.insert(
+            &mut txn,
+            "v2",
+            NoteV2Prelim {
+                text: TextPrelim::new("yo".into()),
+                pinned: false.into(),
+                unsaved_edits: vec!["not stored".into()],
+            },
+        );
+        assert_eq!(note.pinned(&txn)?.deref(), &false);
+
+        let raw_note = map.get(&txn, "v2").unwrap().unwrap_yrs_map()?;
+        assert!(raw_note.contains_key(&txn, "body"));
+        assert!(!raw_note.contains_key(&txn, "text"));
+        assert!(!raw_note.contains_key(&txn, "unsaved_edits"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_enum_variants() -> YrsResult<()> {
+        let start = start();
+
+        let (doc, map) = test_doc();
+        let mut txn = doc.try_transact_mut().unwrap();
+
+        let todo = map.insert(&mut txn, "todo", EntryKindPrelim::Todo);
+        let execution = map.insert(
+            &mut txn,
+            "execution",
+            EntryKindPrelim::Execution(start.into(), None),
+        );
+        let estimate = map.insert(
+            &mut txn,
+            "estimate",
+            EntryKindPrelim::Estimate {
+                duration: chrono::Duration::minutes(45).into(),
+                confident: true.into(),
+            },
+        );
+
+        assert!(matches!(todo.variant(&txn)?, EntryKindVariant::Todo));
+
+        match execution.variant(&txn)? {
+            EntryKindVariant::Execution(execution_start, execution_end) => {
+                assert_eq!(execution_start.deref(), &start);
+                assert!(execution_end.is_none());
+            }
+            _ => panic!("Expected the Execution variant"),
+        }
+
+        match estimate.variant(&txn)? {
+            EntryKindVariant::Estimate {
+                duration,
+                confident,
+            } => {
+                assert_eq!(duration.deref(), &chrono::Duration::minutes(45));
+                assert_eq!(confident.deref(), &true);
+            }
+            _ => panic!("Expected the Estimate variant"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_enum_variant() -> YrsResult<()> {
+        let (doc, map) = test_doc();
+        let mut txn = doc.try_transact_mut().unwrap();
+
+        map.insert(&mut txn, "kind", EntryKindPrelim::Todo);
+        map.get(&txn, "kind").unwrap().unwrap_yrs_map()?.insert(
+            &mut txn,
+            VARIANT_KEY,
+            "Bogus".to_string(),
+        );
+
+        assert_eq!(
+            EntryKind::try_from_yrs_value(map.get(&txn, "kind").unwrap(), &txn).unwrap_err(),
+            YrsWrapperError::UnknownVariant {
+                enum_name: "EntryKind",
+                variant: "Bogus".to_string(),
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_enum_round_trips() -> YrsResult<()> {
+        let start = start();
+        let end = start + chrono::Duration::minutes(45);
+
+        let (doc, map) = test_doc();
+        let mut txn = doc.try_transact_mut().unwrap();
+
+        let kind = map.insert(
+            &mut txn,
+            "execution",
+            EntryKindPrelim::Execution(start.into(), Some(end.into())),
+        );
+        assert_eq!(
+            kind.to_snapshot(&txn)?,
+            EntryKindSnapshot::Execution(start, Some(end))
+        );
+
+        let kind = map.insert(
+            &mut txn,
+            "estimate",
+            EntryKindPrelim::Estimate {
+                duration: chrono::Duration::minutes(1).into(),
+                confident: false.into(),
+            },
+        );
+        let json = serde_json::to_value(kind.with_txn(&txn)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "Estimate": { "duration": 60_000, "confident": false } })
+        );
+        let prelim: EntryKindPrelim = serde_json::from_value(json).unwrap();
+        let copy = map.insert(&mut txn, "estimate_copy", prelim);
+        assert_eq!(copy.to_snapshot(&txn)?, kind.to_snapshot(&txn)?);
+
+        Ok(())
+    }
+}
@@ -1,10 +1,15 @@
+use crate::snapshot::{FromSnapshot, ToSnapshot};
 use crate::yrs_display::YrsDisplay;
+use crate::yrs_serde::{Deserialized, YrsDeserialize, YrsSerialize};
 use crate::yrs_wrapper_error::UnwrapYrsValue;
-use crate::yrs_wrapper_error::YrsResult;
+use crate::yrs_wrapper_error::{YrsResult, YrsWrapperError};
+use serde::ser::{Error as _, SerializeSeq};
+use serde::{Deserialize, Deserializer, Serializer};
 use yrs::Array;
 
 use super::try_from_yrs_value::TryFromYrsValue;
-use yrs::{block::Prelim, ArrayPrelim, ReadTxn, Transaction, TransactionMut};
+use std::ops::{Bound, RangeBounds};
+use yrs::{block::Prelim, ArrayPrelim, ReadTxn, TransactionMut};
 
 #[derive(Clone, Debug)]
 pub struct YrsVec<T> {
@@ -22,7 +27,12 @@ where
         // Verify that the array contains deserializable values.
         array_ref
             .iter(txn)
-            .map(|v| T::try_from_yrs_value(v, txn).map(|_| ()))
+            .enumerate()
+            .map(|(i, v)| {
+                T::try_from_yrs_value(v, txn)
+                    .map(|_| ())
+                    .map_err(|e| e.at_index(i as u32))
+            })
             .collect::<Result<(), _>>()?;
 
         Ok(YrsVec {
@@ -30,6 +40,17 @@ where
             phantom: std::marker::PhantomData,
         })
     }
+
+    /// The elements are checked when they're accessed.
+    fn try_from_yrs_value_shallow(
+        value: yrs::types::Value,
+        _txn: &impl yrs::ReadTxn,
+    ) -> YrsResult<Self> {
+        Ok(YrsVec {
+            inner: value.unwrap_yrs_array()?,
+            phantom: std::marker::PhantomData,
+        })
+    }
 }
 
 impl<T> core::convert::AsMut<yrs::types::Branch> for YrsVec<T> {
@@ -95,9 +116,9 @@ where
         &'a self,
         txn: &'a impl yrs::ReadTxn,
     ) -> impl Iterator<Item = YrsResult<T>> + 'a {
-        self.inner
-            .iter(txn)
-            .map(move |value| T::try_from_yrs_value(value, txn))
+        self.inner.iter(txn).enumerate().map(move |(i, value)| {
+            T::try_from_yrs_value_shallow(value, txn).map_err(|e| e.at_index(i as u32))
+        })
     }
 
     pub fn len(&self, txn: &impl ReadTxn) -> u32 {
@@ -121,12 +142,138 @@ where
         self.inner.insert(txn, self.inner.len(txn), value)
     }
 
-    pub fn get(&self, txn: &Transaction, index: u32) -> YrsResult<Option<T>> {
+    pub fn get(&self, txn: &impl ReadTxn, index: u32) -> YrsResult<Option<T>> {
         self.inner
             .get(txn, index)
-            .map(|value| T::try_from_yrs_value(value, txn))
+            .map(|value| T::try_from_yrs_value_shallow(value, txn).map_err(|e| e.at_index(index)))
             .transpose()
     }
+
+    /// Moves the element at `source` so that it ends up before the element that is currently at
+    /// `target`. A `target` equal to `len` moves the element to the end.
+    pub fn move_to(&self, txn: &mut TransactionMut, source: u32, target: u32) -> YrsResult<()> {
+        let len = self.len(txn);
+        if source >= len {
+            return Err(YrsWrapperError::IndexOutOfBounds { index: source, len });
+        }
+        if target > len {
+            return Err(YrsWrapperError::IndexOutOfBounds { index: target, len });
+        }
+        self.inner.move_to(txn, source, target);
+        Ok(())
+    }
+}
+
+/// The methods that remove elements return snapshots of them, because once removed from the
+/// document, they can't be read through a wrapper anymore. Passing a snapshot to
+/// `FromSnapshot::from_snapshot` gives a prelim that can be inserted elsewhere.
+///
+/// Unlike the methods of `Vec`, they return an error instead of panicking when an index or a range
+/// is out of bounds: concurrent edits can shorten the array between computing an index and using
+/// it.
+impl<T> YrsVec<T>
+where
+    T: TryFromYrsValue + ToSnapshot,
+{
+    /// Like `Vec::remove`.
+    pub fn remove(&self, txn: &mut TransactionMut, index: u32) -> YrsResult<T::Snapshot> {
+        let removed = self.snapshot_at(txn, index)?;
+        self.inner.remove(txn, index);
+        Ok(removed)
+    }
+
+    /// Like `Vec::drain`.
+    pub fn remove_range(
+        &self,
+        txn: &mut TransactionMut,
+        range: impl RangeBounds<u32>,
+    ) -> YrsResult<Vec<T::Snapshot>> {
+        let (start, end) = self.resolve_range(txn, range)?;
+        let removed = (start..end)
+            .map(|index| self.snapshot_at(txn, index))
+            .collect::<YrsResult<Vec<_>>>()?;
+        if end > start {
+            self.inner.remove_range(txn, start, end - start);
+        }
+        Ok(removed)
+    }
+
+    pub fn pop(&self, txn: &mut TransactionMut) -> YrsResult<Option<T::Snapshot>> {
+        match self.len(txn) {
+            0 => Ok(None),
+            len => self.remove(txn, len - 1).map(Some),
+        }
+    }
+
+    /// Like `Vec::splice`, replaces the elements in `range` with `replace_with`.
+    pub fn splice<P: Prelim<Return = T>>(
+        &self,
+        txn: &mut TransactionMut,
+        range: impl RangeBounds<u32>,
+        replace_with: impl IntoIterator<Item = P>,
+    ) -> YrsResult<Vec<T::Snapshot>> {
+        let (start, end) = self.resolve_range(txn, range)?;
+        let removed = self.remove_range(txn, start..end)?;
+        for (index, value) in (start..).zip(replace_with) {
+            self.inner.insert(txn, index, value);
+        }
+        Ok(removed)
+    }
+
+    /// Like `Vec::retain`, removes the elements for which `f` returns false, but also returns
+    /// them.
+    pub fn retain(
+        &self,
+        txn: &mut TransactionMut,
+        mut f: impl FnMut(&T, &TransactionMut) -> bool,
+    ) -> YrsResult<Vec<T::Snapshot>> {
+        let mut to_remove = vec![];
+        for (index, value) in self.iter(txn).enumerate() {
+            if !f(&value?, txn) {
+                to_remove.push(index as u32);
+            }
+        }
+
+        // Removing from the back keeps the indices that are yet to be removed valid.
+        let mut removed = to_remove
+            .into_iter()
+            .rev()
+            .map(|index| self.remove(txn, index))
+            .collect::<YrsResult<Vec<_>>>()?;
+        removed.reverse();
+        Ok(removed)
+    }
+
+    fn snapshot_at(&self, txn: &impl ReadTxn, index: u32) -> YrsResult<T::Snapshot> {
+        self.get(txn, index)?
+            .ok_or(YrsWrapperError::IndexOutOfBounds {
+                index,
+                len: self.len(txn),
+            })?
+            .to_snapshot(txn)
+    }
+
+    fn resolve_range(
+        &self,
+        txn: &impl ReadTxn,
+        range: impl RangeBounds<u32>,
+    ) -> YrsResult<(u32, u32)> {
+        let len = self.len(txn);
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end.saturating_add(1),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => len,
+        };
+        if start > end || end > len {
+            return Err(YrsWrapperError::RangeOutOfBounds { start, end, len });
+        }
+        Ok((start, end))
+    }
 }
 
 impl<T> YrsDisplay for YrsVec<T>
@@ -146,3 +293,59 @@ where
         Ok(result)
     }
 }
+
+impl<T> ToSnapshot for YrsVec<T>
+where
+    T: ToSnapshot + TryFromYrsValue,
+{
+    type Snapshot = Vec<T::Snapshot>;
+
+    fn to_snapshot(&self, txn: &impl ReadTxn) -> YrsResult<Self::Snapshot> {
+        self.iter(txn)
+            .map(|value| value?.to_snapshot(txn))
+            .collect()
+    }
+}
+
+impl<S, P: FromSnapshot<S>> FromSnapshot<Vec<S>> for YrsVecPrelim<P> {
+    fn from_snapshot(snapshot: Vec<S>) -> Self {
+        YrsVecPrelim(
+            snapshot
+                .into_iter()
+                .map(P::from_snapshot)
+                .collect::<Vec<_>>()
+                .into(),
+        )
+    }
+}
+
+impl<T> YrsSerialize for YrsVec<T>
+where
+    T: YrsSerialize + TryFromYrsValue,
+{
+    fn yrs_serialize<S: Serializer>(
+        &self,
+        txn: &impl ReadTxn,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.len(txn) as usize))?;
+        for value in self.iter(txn) {
+            let value = value.map_err(S::Error::custom)?;
+            seq.serialize_element(&value.with_txn(txn))?;
+        }
+        seq.end()
+    }
+}
+
+impl<P: YrsDeserialize> YrsDeserialize for YrsVecPrelim<P> {
+    fn yrs_deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let values = Vec::<Deserialized<P>>::deserialize(deserializer)?;
+        Ok(YrsVecPrelim(
+            values
+                .into_iter()
+                .map(|value| value.0)
+                .collect::<Vec<_>>()
+                .into(),
+        ))
+    }
+}
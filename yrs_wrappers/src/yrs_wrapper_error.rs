@@ -1,19 +1,171 @@
+use std::fmt;
+
 #[derive(Debug, thiserror::Error, Clone, PartialEq, Eq)]
 pub enum YrsWrapperError {
-    #[error("Unexpected Yrs value: expected {expected}")]
-    UnexpectedYrsValue { expected: &'static str },
+    #[error("Unexpected Yrs value: expected {expected}, found {found}")]
+    UnexpectedYrsValue {
+        expected: &'static str,
+        found: &'static str,
+    },
 
     #[error("Attribute {attr} doesn't exist in YMap")]
     YMapMissingAttr { attr: String },
 
-    #[error("Expected variant {expected} in lib0::any::Any")]
-    UnexpectedAnyVariant { expected: &'static str },
+    #[error("Expected variant {expected} in lib0::any::Any, found {found}")]
+    UnexpectedAnyVariant {
+        expected: &'static str,
+        found: &'static str,
+    },
 
     #[error("BigInt out of range")]
     BigIntOutOfRange,
 
+    #[error("Malformed {expected}: {value}")]
+    MalformedString {
+        expected: &'static str,
+        value: String,
+    },
+
     #[error("Failed to convert from BlockPtr to {expected}")]
     FromBlockPtrError { expected: &'static str },
+
+    #[error("Index {index} is out of bounds for length {len}")]
+    IndexOutOfBounds { index: u32, len: u32 },
+
+    #[error("Range {start}..{end} is out of bounds for length {len}")]
+    RangeOutOfBounds { start: u32, end: u32, len: u32 },
+
+    #[error("Reference to {id}, which doesn't exist")]
+    DanglingRef { id: uuid::Uuid },
+
+    #[error("{before} was supposed to come after {after}, but doesn't")]
+    NotInOrder {
+        after: uuid::Uuid,
+        before: uuid::Uuid,
+    },
+
+    #[error("Unknown variant {variant} for enum {enum_name}")]
+    UnknownVariant {
+        enum_name: &'static str,
+        variant: String,
+    },
+
+    /// An error that happened while reading the value at `path`, relative to the value that was
+    /// being read.
+    #[error("At {path}: {source}")]
+    AtPath {
+        path: YrsPath,
+        source: Box<YrsWrapperError>,
+    },
+}
+
+impl YrsWrapperError {
+    pub fn unexpected_yrs_value(expected: &'static str, found: &yrs::types::Value) -> Self {
+        YrsWrapperError::UnexpectedYrsValue {
+            expected,
+            found: yrs_value_kind(found),
+        }
+    }
+
+    pub fn unexpected_any_variant(expected: &'static str, found: &lib0::any::Any) -> Self {
+        YrsWrapperError::UnexpectedAnyVariant {
+            expected,
+            found: any_variant(found),
+        }
+    }
+
+    /// Marks the error as having happened in the map field stored under `key`.
+    pub fn in_field(self, key: impl Into<String>) -> Self {
+        self.prepend(PathSegment::Field(key.into()))
+    }
+
+    /// Marks the error as having happened in the array element at `index`.
+    pub fn at_index(self, index: u32) -> Self {
+        self.prepend(PathSegment::Index(index))
+    }
+
+    fn prepend(self, segment: PathSegment) -> Self {
+        match self {
+            YrsWrapperError::AtPath { mut path, source } => {
+                path.0.insert(0, segment);
+                YrsWrapperError::AtPath { path, source }
+            }
+            other => YrsWrapperError::AtPath {
+                path: YrsPath(vec![segment]),
+                source: Box::new(other),
+            },
+        }
+    }
+
+    /// The path at which the error happened, if it happened below the value being read.
+    pub fn path(&self) -> Option<&YrsPath> {
+        match self {
+            YrsWrapperError::AtPath { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// The error without the path.
+    pub fn root_cause(&self) -> &YrsWrapperError {
+        match self {
+            YrsWrapperError::AtPath { source, .. } => source,
+            other => other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    Field(String),
+    Index(u32),
+}
+
+/// A path into a value, displayed like `todos[3].child_todos[0].end`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct YrsPath(pub Vec<PathSegment>);
+
+impl fmt::Display for YrsPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.0.iter().enumerate() {
+            match segment {
+                PathSegment::Field(key) if i == 0 => write!(f, "{key}")?,
+                PathSegment::Field(key) => write!(f, ".{key}")?,
+                PathSegment::Index(index) => write!(f, "[{index}]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn yrs_value_kind(value: &yrs::types::Value) -> &'static str {
+    #[allow(unreachable_patterns)]
+    match value {
+        yrs::types::Value::Any(_) => "Any",
+        yrs::types::Value::YText(_) => "TextRef",
+        yrs::types::Value::YArray(_) => "ArrayRef",
+        yrs::types::Value::YMap(_) => "MapRef",
+        yrs::types::Value::YXmlElement(_) => "XmlElementRef",
+        yrs::types::Value::YXmlFragment(_) => "XmlFragmentRef",
+        yrs::types::Value::YXmlText(_) => "XmlTextRef",
+        yrs::types::Value::YDoc(_) => "Doc",
+        _ => "unknown",
+    }
+}
+
+fn any_variant(any: &lib0::any::Any) -> &'static str {
+    #[allow(unreachable_patterns)]
+    match any {
+        lib0::any::Any::Null => "Null",
+        lib0::any::Any::Undefined => "Undefined",
+        lib0::any::Any::Bool(_) => "Bool",
+        lib0::any::Any::Number(_) => "Number",
+        lib0::any::Any::BigInt(_) => "BigInt",
+        lib0::any::Any::String(_) => "String",
+        lib0::any::Any::Buffer(_) => "Buffer",
+        lib0::any::Any::Array(_) => "Array",
+        lib0::any::Any::Map(_) => "Map",
+        _ => "unknown",
+    }
 }
 
 pub type YrsResult<T> = Result<T, YrsWrapperError>;
@@ -29,38 +181,36 @@ pub trait UnwrapAny {
     fn unwrap_any_bool(self) -> YrsResult<bool>;
     fn unwrap_any_bigint(self) -> YrsResult<i64>;
     fn unwrap_any_string(self) -> YrsResult<String>;
+    fn unwrap_any_number(self) -> YrsResult<f64>;
+    fn unwrap_any_buffer(self) -> YrsResult<Vec<u8>>;
 }
 
 impl UnwrapYrsValue for yrs::types::Value {
     fn unwrap_yrs_map(self) -> YrsResult<yrs::MapRef> {
         match self {
             yrs::types::Value::YMap(map) => Ok(map),
-            _ => Err(YrsWrapperError::UnexpectedYrsValue { expected: "MapRef" }),
+            other => Err(YrsWrapperError::unexpected_yrs_value("MapRef", &other)),
         }
     }
 
     fn unwrap_yrs_text(self) -> YrsResult<yrs::TextRef> {
         match self {
             yrs::types::Value::YText(text) => Ok(text),
-            _ => Err(YrsWrapperError::UnexpectedYrsValue {
-                expected: "TextRef",
-            }),
+            other => Err(YrsWrapperError::unexpected_yrs_value("TextRef", &other)),
         }
     }
 
     fn unwrap_yrs_array(self) -> YrsResult<yrs::ArrayRef> {
         match self {
             yrs::types::Value::YArray(array) => Ok(array),
-            _ => Err(YrsWrapperError::UnexpectedYrsValue {
-                expected: "ArrayRef",
-            }),
+            other => Err(YrsWrapperError::unexpected_yrs_value("ArrayRef", &other)),
         }
     }
 
     fn unwrap_yrs_any(self) -> YrsResult<lib0::any::Any> {
         match self {
             yrs::types::Value::Any(any) => Ok(any),
-            _ => Err(YrsWrapperError::UnexpectedYrsValue { expected: "Any" }),
+            other => Err(YrsWrapperError::unexpected_yrs_value("Any", &other)),
         }
     }
 }
@@ -69,21 +219,35 @@ impl UnwrapAny for lib0::any::Any {
     fn unwrap_any_bool(self) -> YrsResult<bool> {
         match self {
             lib0::any::Any::Bool(b) => Ok(b),
-            _ => Err(YrsWrapperError::UnexpectedAnyVariant { expected: "Bool" }),
+            other => Err(YrsWrapperError::unexpected_any_variant("Bool", &other)),
         }
     }
 
     fn unwrap_any_bigint(self) -> YrsResult<i64> {
         match self {
             lib0::any::Any::BigInt(n) => Ok(n),
-            _ => Err(YrsWrapperError::UnexpectedAnyVariant { expected: "BigInt" }),
+            other => Err(YrsWrapperError::unexpected_any_variant("BigInt", &other)),
         }
     }
 
     fn unwrap_any_string(self) -> YrsResult<String> {
         match self {
             lib0::any::Any::String(s) => Ok(s.into()),
-            _ => Err(YrsWrapperError::UnexpectedAnyVariant { expected: "String" }),
+            other => Err(YrsWrapperError::unexpected_any_variant("String", &other)),
+        }
+    }
+
+    fn unwrap_any_number(self) -> YrsResult<f64> {
+        match self {
+            lib0::any::Any::Number(n) => Ok(n),
+            other => Err(YrsWrapperError::unexpected_any_variant("Number", &other)),
+        }
+    }
+
+    fn unwrap_any_buffer(self) -> YrsResult<Vec<u8>> {
+        match self {
+            lib0::any::Any::Buffer(b) => Ok(b.into()),
+            other => Err(YrsWrapperError::unexpected_any_variant("Buffer", &other)),
         }
     }
 }